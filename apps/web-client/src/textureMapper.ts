@@ -21,6 +21,11 @@ textureData.set(BlockType.RedFlower, { offsetX: 0, offsetY: 2 });
 textureData.set(BlockType.Water, { offsetX: 2, offsetY: 2 });
 textureData.set(BlockType.Planks, { offsetX: 3, offsetY: 0 });
 textureData.set(BlockType.Red, { offsetX: 3, offsetY: 2 });
+// These borrow textures until they get their own spots in the atlas
+textureData.set(BlockType.Torch, { offsetX: 0, offsetY: 1 });
+textureData.set(BlockType.Glowstone, { offsetX: 2, offsetY: 1 });
+textureData.set(BlockType.Sand, { offsetX: 2, offsetY: 0 });
+textureData.set(BlockType.Gravel, { offsetX: 1, offsetY: 0 });
 textureData.set(ThrowableItem.Fireball, { offsetX: 3, offsetY: 1 });
 
 class Textures {
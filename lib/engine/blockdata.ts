@@ -9,3 +9,8 @@ export function getBlockData(block: BlockType) {
   cache.set(block, data);
   return data;
 }
+
+/** Needs to be called after a new block registry is installed */
+export function clearBlockDataCache() {
+  cache.clear();
+}
@@ -364,11 +364,7 @@ export class Player extends MovableEntity<PlayerDto> implements IEntity {
     const { cube } = lookingData;
     if (!cube) return;
 
-    const newCubePos = lookingData.cube.pos.add(
-      Vector3D.fromDirection(lookingData.face)
-    );
-
-    const newCube = CubeHelpers.createCube(blockType, newCubePos);
+    const newCube = CubeHelpers.createCube(blockType, lookingData.placePos);
 
     console.log("Placed Cube", newCube);
 
@@ -137,6 +137,10 @@ export class Game {
   public update(delta: number) {
     this.entities.update(this, this.world, delta);
 
+    for (const chunkId of this.world.tick(delta / 1000)) {
+      this.stateDiff.updateChunk(chunkId);
+    }
+
     for (const script of this.gameScripts) {
       script.update?.(delta);
     }
@@ -38,6 +38,13 @@ class WorldModuleClass {
     const world = new World(wasmWorld, data);
     return world;
   }
+
+  public makeBlockData(
+    blockType: WorldWasm.BlockType,
+    properties: Record<string, unknown>
+  ) {
+    return WorldModule.module.make_block_data_wasm(blockType, properties);
+  }
 }
 
 export const WorldModule = new WorldModuleClass();
@@ -58,8 +65,29 @@ class TerrainGenModuleClass {
   }
 
   getTerrainGenerator(seed: number, flatWorld: boolean) {
-    const terrainGenerator = new this.module.TerrainGenerator(seed, flatWorld);
+    return this.wrapTerrainGenerator(
+      new this.module.TerrainGenerator(seed, flatWorld)
+    );
+  }
+
+  /** Makes a generator by its registered name, like "basic" or "flat", with its options */
+  getNamedTerrainGenerator(name: string, seed: number, options: object = {}) {
+    return this.wrapTerrainGenerator(
+      this.module.TerrainGenerator.with_generator(
+        name,
+        seed,
+        JSON.stringify(options)
+      )
+    );
+  }
+
+  getGeneratorNames(): string[] {
+    return this.module.TerrainGenerator.get_generator_names();
+  }
 
+  private wrapTerrainGenerator(
+    terrainGenerator: TerrainGenWasm.TerrainGenerator
+  ) {
     return {
       getChunk: (chunkPos: Vector2D) => {
         console.log("Generating Chunk", chunkPos);
@@ -68,6 +96,17 @@ class TerrainGenModuleClass {
           .serialize();
         return chunk as unknown as ISerializedChunk;
       },
+      getBiome: (worldX: number, worldZ: number) => {
+        const sample = terrainGenerator.get_biome(worldX, worldZ);
+        const biome = {
+          name: sample.get_name(),
+          temperature: sample.temperature,
+          humidity: sample.humidity,
+          height: sample.height,
+        };
+        sample.free();
+        return biome;
+      },
     };
   }
 }
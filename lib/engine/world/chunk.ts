@@ -1,10 +1,12 @@
 import { Cube } from "../entities/cube.js";
-import { Direction, Vector2D } from "../utils/vector.js";
+import { Direction, Vector2D, Vector3D } from "../utils/vector.js";
 import { BlockType } from "@craft/rust-world";
 export interface ILookingAtData {
   cube: Cube;
   face: Direction;
   dist: number;
+  /** Where a block placed against the face goes */
+  placePos: Vector3D;
 }
 
 export interface ISerializedChunk {
@@ -12,8 +14,15 @@ export interface ISerializedChunk {
     x: number;
     y: number;
   };
-  blocks: BlockType[];
-  block_data: ("None" | { Image: string })[];
+  /**
+   * 16 high sections from the bottom up, null when a section has no blocks.
+   * Sections with block data (image directions, block states) list it as [section index, data] pairs.
+   */
+  sections: (
+    | BlockType[]
+    | { blocks: BlockType[]; data: [number, unknown][] }
+    | null
+  )[];
 }
 
 export const getChunkId = (serChunk: ISerializedChunk) => {
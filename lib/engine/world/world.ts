@@ -191,7 +191,11 @@ export class World {
     }
     const diff: { chunk_ids: string[] } = this.wasmWorld.add_block_wasm({
       block_type: cube.type,
-      extra_data: "None",
+      // Leaves placed by players never decay
+      extra_data:
+        cube.type === WorldModuleTypes.BlockType.Leaf
+          ? WorldModule.makeBlockData(cube.type, { persistent: true })
+          : "None",
       world_pos: {
         x: cube.pos.get(0),
         y: cube.pos.get(1),
@@ -216,11 +220,36 @@ export class World {
     return diff.chunk_ids;
   }
 
+  /**
+   * Runs block ticks (fluids, falling blocks) for the time that has passed
+   * @returns array of chunk ids that were affected
+   * */
+  tick(seconds: number): string[] {
+    const diff: { chunk_ids: string[] } = this.wasmWorld.tick_wasm(seconds);
+    return diff.chunk_ids;
+  }
+
+  /**
+   * Breaks the blocks caught in an explosion all at once
+   * @returns array of chunk ids that were affected
+   * */
+  explode(center: Vector3D, power: number): string[] {
+    const explosion: { diff: { chunk_ids: string[] } } =
+      this.wasmWorld.explode_wasm(
+        center.get(0),
+        center.get(1),
+        center.get(2),
+        power
+      );
+    return explosion.diff.chunk_ids;
+  }
+
   lookingAt(camera: CameraRay): ILookingAtData | null {
     const lookingData: {
       block: ISerializedCube;
       face: string;
       distance: number;
+      place_pos: { x: number; y: number; z: number };
     } | null = this.wasmWorld.get_pointed_at_block_wasm(camera);
 
     console.log("Cam looking at ", lookingData, camera);
@@ -231,6 +260,11 @@ export class World {
       cube: CubeHelpers.fromWasmCube(lookingData.block),
       face: getDirectionFromString(lookingData.face),
       dist: lookingData.distance,
+      placePos: new Vector3D([
+        lookingData.place_pos.x,
+        lookingData.place_pos.y,
+        lookingData.place_pos.z,
+      ]),
     };
   }
 }
@@ -0,0 +1,257 @@
+use crate::seed::{derive_world_seed, FeatureSalt};
+use noise::{NoiseFn, Perlin};
+use wasm_bindgen::prelude::*;
+use world::block::BlockType;
+
+/** How quickly temperature and humidity change across the world. Smaller values make bigger biomes */
+const CLIMATE_SCALE: f64 = 1.0 / 300.0;
+/**
+ * How far past the border between two biomes, in climate space, the heights are still mixed.
+ * Bigger values give wider and gentler slopes between biomes.
+ */
+const BLEND_WIDTH: f64 = 0.15;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Biome {
+    Plains,
+    Mountain,
+    Forest,
+    Desert,
+}
+
+pub const ALL_BIOMES: [Biome; 4] = [Biome::Plains, Biome::Mountain, Biome::Forest, Biome::Desert];
+
+pub struct BiomeInfo {
+    pub name: &'static str,
+    /** The temperature and humidity this biome is at its strongest, both roughly between -1 and 1 */
+    pub temperature: f64,
+    pub humidity: f64,
+    /** The block placed on top of every column */
+    pub surface_block: BlockType,
+    /** The lowest the ground goes */
+    pub base_height: f64,
+    /** How far above the base height the hills reach */
+    pub height_variation: f64,
+    /** How quickly the hills change. Bigger values make the terrain more jagged */
+    pub jag_factor: f64,
+    /** The chance each potential tree spot grows a tree */
+    pub tree_density: f64,
+    /** The chance each potential flower spot grows a flower */
+    pub flower_density: f64,
+}
+
+const PLAINS: BiomeInfo = BiomeInfo {
+    name: "Plains",
+    temperature: 0.0,
+    humidity: 0.0,
+    surface_block: BlockType::Grass,
+    base_height: 5.0,
+    height_variation: 4.0,
+    jag_factor: 1.0 / 120.0,
+    tree_density: 0.05,
+    flower_density: 0.6,
+};
+
+const MOUNTAIN: BiomeInfo = BiomeInfo {
+    name: "Mountain",
+    temperature: -0.25,
+    humidity: 0.05,
+    surface_block: BlockType::Stone,
+    base_height: 8.0,
+    height_variation: 30.0,
+    jag_factor: 1.0 / 50.0,
+    tree_density: 0.0,
+    flower_density: 0.05,
+};
+
+const FOREST: BiomeInfo = BiomeInfo {
+    name: "Forest",
+    temperature: 0.05,
+    humidity: 0.25,
+    surface_block: BlockType::Grass,
+    base_height: 5.0,
+    height_variation: 10.0,
+    jag_factor: 1.0 / 100.0,
+    tree_density: 1.0,
+    flower_density: 0.3,
+};
+
+const DESERT: BiomeInfo = BiomeInfo {
+    name: "Desert",
+    temperature: 0.25,
+    humidity: -0.2,
+    surface_block: BlockType::Sand,
+    base_height: 4.0,
+    height_variation: 3.0,
+    jag_factor: 1.0 / 150.0,
+    tree_density: 0.0,
+    flower_density: 0.0,
+};
+
+impl Biome {
+    pub fn info(&self) -> &'static BiomeInfo {
+        match self {
+            Biome::Plains => &PLAINS,
+            Biome::Mountain => &MOUNTAIN,
+            Biome::Forest => &FOREST,
+            Biome::Desert => &DESERT,
+        }
+    }
+
+    fn climate_distance(&self, temperature: f64, humidity: f64) -> f64 {
+        let info = self.info();
+        ((info.temperature - temperature).powi(2) + (info.humidity - humidity).powi(2)).sqrt()
+    }
+}
+
+/** Everything the client needs to know about a column, like which biome it is in to tint the grass */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BiomeSample {
+    pub biome: Biome,
+    pub temperature: f64,
+    pub humidity: f64,
+    /** The y of the surface block */
+    pub height: i32,
+}
+
+#[wasm_bindgen]
+impl BiomeSample {
+    pub fn get_name(&self) -> String {
+        self.biome.info().name.to_string()
+    }
+}
+
+pub struct BiomeGenerator {
+    height_noise: Perlin,
+    temperature_noise: Perlin,
+    humidity_noise: Perlin,
+}
+
+impl BiomeGenerator {
+    pub fn new(seed: u32) -> BiomeGenerator {
+        BiomeGenerator {
+            height_noise: Perlin::new(seed),
+            temperature_noise: Perlin::new(derive_world_seed(seed, FeatureSalt::Temperature)),
+            humidity_noise: Perlin::new(derive_world_seed(seed, FeatureSalt::Humidity)),
+        }
+    }
+
+    fn get_climate(&self, world_x: f64, world_z: f64) -> (f64, f64) {
+        let point = [world_x * CLIMATE_SCALE, world_z * CLIMATE_SCALE];
+        (
+            self.temperature_noise.get(point),
+            self.humidity_noise.get(point),
+        )
+    }
+
+    /** The biome whose climate is closest to the climate of the column */
+    fn closest_biome(temperature: f64, humidity: f64) -> Biome {
+        ALL_BIOMES
+            .iter()
+            .copied()
+            .min_by(|a, b| {
+                a.climate_distance(temperature, humidity)
+                    .total_cmp(&b.climate_distance(temperature, humidity))
+            })
+            .unwrap_or(Biome::Plains)
+    }
+
+    /**
+     * How much each biome shapes the column. The closest biome always has a weight of 1,
+     * and the others fade out the further they are past the border, so heights change smoothly between biomes.
+     */
+    fn get_weights(temperature: f64, humidity: f64) -> Vec<(Biome, f64)> {
+        let closest =
+            Self::closest_biome(temperature, humidity).climate_distance(temperature, humidity);
+        ALL_BIOMES
+            .iter()
+            .map(|biome| {
+                let past_border = biome.climate_distance(temperature, humidity) - closest;
+                (*biome, (1.0 - past_border / BLEND_WIDTH).max(0.0))
+            })
+            .filter(|(_, weight)| *weight > 0.0)
+            .collect()
+    }
+
+    fn get_biome_height(&self, biome: Biome, world_x: f64, world_z: f64) -> f64 {
+        let info = biome.info();
+        let per_val = self
+            .height_noise
+            .get([world_x * info.jag_factor, world_z * info.jag_factor]);
+        per_val.abs() * info.height_variation + info.base_height
+    }
+
+    pub fn get_biome(&self, world_x: i32, world_z: i32) -> Biome {
+        let (temperature, humidity) = self.get_climate(world_x as f64, world_z as f64);
+        Self::closest_biome(temperature, humidity)
+    }
+
+    /** The y of the surface block, mixing the heights of every biome near the column */
+    pub fn get_height(&self, world_x: i32, world_z: i32) -> i32 {
+        self.sample(world_x, world_z).height
+    }
+
+    pub fn sample(&self, world_x: i32, world_z: i32) -> BiomeSample {
+        let (x, z) = (world_x as f64, world_z as f64);
+        let (temperature, humidity) = self.get_climate(x, z);
+
+        let mut total_height = 0.0;
+        let mut total_weight = 0.0;
+        for (biome, weight) in Self::get_weights(temperature, humidity) {
+            total_height += self.get_biome_height(biome, x, z) * weight;
+            total_weight += weight;
+        }
+
+        BiomeSample {
+            biome: Self::closest_biome(temperature, humidity),
+            temperature,
+            humidity,
+            height: (total_height / total_weight) as i32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BiomeGenerator, ALL_BIOMES};
+    use std::collections::HashSet;
+
+    #[test]
+    fn every_biome_shows_up() {
+        let biomes = BiomeGenerator::new(7);
+        let mut seen = HashSet::new();
+        for x in -50..50 {
+            for z in -50..50 {
+                seen.insert(biomes.get_biome(x * 40, z * 40));
+            }
+        }
+        assert_eq!(seen.len(), ALL_BIOMES.len());
+    }
+
+    #[test]
+    fn heights_blend_across_biome_borders() {
+        let biomes = BiomeGenerator::new(7);
+        let mut borders = 0;
+        for z in -300..300 {
+            for x in -300..300 {
+                let here = biomes.sample(x, z);
+                let next = biomes.sample(x + 1, z);
+                if here.biome != next.biome {
+                    borders += 1;
+                }
+                // Without blending a column next to a mountain would be a cliff
+                assert!(
+                    (here.height - next.height).abs() <= 3,
+                    "cliff between {} and {} at {}, {}",
+                    here.height,
+                    next.height,
+                    x,
+                    z
+                );
+            }
+        }
+        assert!(borders > 0);
+    }
+}
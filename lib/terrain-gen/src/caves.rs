@@ -0,0 +1,114 @@
+use crate::seed::{derive_world_seed, FeatureSalt};
+use noise::{NoiseFn, Perlin};
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CaveConfig {
+    /** How quickly the cave noise changes. Bigger values make smaller, twistier caves */
+    pub frequency: f64,
+    /** Blocks are carved out where the noise is above this. Lower values carve out more of the world */
+    pub threshold: f64,
+    /** Caves are squashed vertically by this much so they spread out sideways more than they go down */
+    pub vertical_squash: f64,
+}
+
+impl Default for CaveConfig {
+    fn default() -> Self {
+        CaveConfig {
+            frequency: 1.0 / 20.0,
+            threshold: 0.3,
+            vertical_squash: 2.0,
+        }
+    }
+}
+
+/** Carves caves, tunnels and overhangs out of the terrain with 3D noise */
+pub struct CaveCarver {
+    noise: Perlin,
+    config: CaveConfig,
+}
+
+impl CaveCarver {
+    pub fn new(seed: u32, config: CaveConfig) -> CaveCarver {
+        CaveCarver {
+            noise: Perlin::new(derive_world_seed(seed, FeatureSalt::Caves)),
+            config,
+        }
+    }
+
+    /** If the block should be left empty. The bottom layer of the world is never carved so there is always a floor */
+    pub fn is_carved(&self, world_x: i32, y: i32, world_z: i32) -> bool {
+        if y <= 0 {
+            return false;
+        }
+        let frequency = self.config.frequency;
+        let density = self.noise.get([
+            world_x as f64 * frequency,
+            y as f64 * frequency * self.config.vertical_squash,
+            world_z as f64 * frequency,
+        ]);
+        density > self.config.threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CaveCarver, CaveConfig};
+
+    fn count_carved(carver: &CaveCarver) -> usize {
+        let mut carved = 0;
+        for x in 0..32 {
+            for y in 0..32 {
+                for z in 0..32 {
+                    if carver.is_carved(x, y, z) {
+                        carved += 1;
+                    }
+                }
+            }
+        }
+        carved
+    }
+
+    #[test]
+    fn threshold_controls_how_much_is_carved() {
+        let default = count_carved(&CaveCarver::new(1, CaveConfig::default()));
+        let total = 32 * 32 * 32;
+        assert!(default > 0 && default < total / 5);
+
+        let hollow = count_carved(&CaveCarver::new(
+            1,
+            CaveConfig {
+                threshold: 0.0,
+                ..Default::default()
+            },
+        ));
+        assert!(hollow > default * 2);
+
+        let solid = count_carved(&CaveCarver::new(
+            1,
+            CaveConfig {
+                threshold: 1.0,
+                ..Default::default()
+            },
+        ));
+        assert_eq!(solid, 0);
+    }
+
+    #[test]
+    fn never_carves_the_floor() {
+        let carver = CaveCarver::new(
+            1,
+            CaveConfig {
+                threshold: -2.0,
+                ..Default::default()
+            },
+        );
+        for x in 0..32 {
+            for z in 0..32 {
+                assert!(!carver.is_carved(x, 0, z));
+                assert!(carver.is_carved(x, 1, z));
+            }
+        }
+    }
+}
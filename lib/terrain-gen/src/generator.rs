@@ -0,0 +1,226 @@
+use crate::{
+    biome::{Biome, BiomeSample},
+    BasicChunkGetter, FlatWorldChunkGetter, FlatWorldConfig,
+};
+use lazy_static::lazy_static;
+use serde_json::Value;
+use std::{collections::BTreeMap, fmt, sync::RwLock};
+use world::{
+    chunk::{Chunk, MAX_WORLD_HEIGHT},
+    positions::ChunkPos,
+};
+
+pub const BASIC_GENERATOR: &str = "basic";
+pub const FLAT_GENERATOR: &str = "flat";
+
+pub trait ChunkGenerator {
+    fn get_chunk(&self, chunk_pos: &ChunkPos) -> Chunk;
+
+    /** Generators without biomes are plains everywhere, with the ground at y = 0 */
+    fn get_biome(&self, _world_x: i32, _world_z: i32) -> BiomeSample {
+        BiomeSample {
+            biome: Biome::Plains,
+            temperature: 0.0,
+            humidity: 0.0,
+            height: 0,
+        }
+    }
+}
+
+/** Makes a generator from the world seed and its options */
+pub type GeneratorFactory = fn(u32, Value) -> Result<Box<dyn ChunkGenerator>, serde_json::Error>;
+
+#[derive(Debug)]
+pub enum GeneratorError {
+    UnknownGenerator(String),
+    InvalidOptions(String, String),
+}
+
+impl std::error::Error for GeneratorError {}
+
+impl fmt::Display for GeneratorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GeneratorError::UnknownGenerator(name) => {
+                write!(f, "There is no terrain generator called {}", name)
+            }
+            GeneratorError::InvalidOptions(name, e) => {
+                write!(f, "Invalid options for the {} generator: {}", name, e)
+            }
+        }
+    }
+}
+
+/** Every generator a world can be made with, by name */
+pub struct GeneratorRegistry {
+    factories: BTreeMap<String, GeneratorFactory>,
+}
+
+impl Default for GeneratorRegistry {
+    fn default() -> Self {
+        let mut registry = GeneratorRegistry::empty();
+        registry.register(BASIC_GENERATOR, |seed, options| {
+            let config = serde_json::from_value(options)?;
+            Ok(Box::new(BasicChunkGetter::make_with_config(seed, config)))
+        });
+        registry.register(FLAT_GENERATOR, |_seed, options| {
+            let config: FlatWorldConfig = serde_json::from_value(options)?;
+            // Every layer is a y in the chunk, so there can't be more of them than the world is tall
+            if config.layers.len() > MAX_WORLD_HEIGHT as usize {
+                return Err(serde::de::Error::custom(format!(
+                    "a flat world can have at most {} layers",
+                    MAX_WORLD_HEIGHT
+                )));
+            }
+            Ok(Box::new(FlatWorldChunkGetter { config }))
+        });
+        registry
+    }
+}
+
+lazy_static! {
+    static ref SHARED: RwLock<GeneratorRegistry> = RwLock::new(GeneratorRegistry::default());
+}
+
+impl GeneratorRegistry {
+    pub fn empty() -> GeneratorRegistry {
+        GeneratorRegistry {
+            factories: BTreeMap::new(),
+        }
+    }
+
+    /** Adds a generator, replacing any generator already registered with the name */
+    pub fn register(&mut self, name: &str, factory: GeneratorFactory) {
+        self.factories.insert(name.to_string(), factory);
+    }
+
+    pub fn get_names(&self) -> Vec<String> {
+        self.factories.keys().cloned().collect()
+    }
+
+    /** Makes the named generator. Options are a JSON object, and an empty string uses the defaults */
+    pub fn create(
+        &self,
+        name: &str,
+        seed: u32,
+        options: &str,
+    ) -> Result<Box<dyn ChunkGenerator>, GeneratorError> {
+        let factory = self
+            .factories
+            .get(name)
+            .ok_or_else(|| GeneratorError::UnknownGenerator(name.to_string()))?;
+        let invalid_options =
+            |e: serde_json::Error| GeneratorError::InvalidOptions(name.to_string(), e.to_string());
+
+        let options = if options.trim().is_empty() {
+            Value::Object(Default::default())
+        } else {
+            serde_json::from_str(options).map_err(invalid_options)?
+        };
+        factory(seed, options).map_err(invalid_options)
+    }
+
+    /** Adds a generator to the registry TerrainGenerator makes its generators from, so every later world can use it */
+    pub fn register_shared(name: &str, factory: GeneratorFactory) {
+        SHARED.write().unwrap().register(name, factory);
+    }
+
+    /** Runs f with the registry TerrainGenerator makes its generators from */
+    pub fn with_shared<T>(f: impl FnOnce(&GeneratorRegistry) -> T) -> T {
+        f(&SHARED.read().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChunkGenerator, GeneratorError, GeneratorRegistry, FLAT_GENERATOR};
+    use world::{
+        block::BlockType,
+        chunk::Chunk,
+        positions::{ChunkPos, InnerChunkPos},
+    };
+
+    struct VoidGenerator {}
+
+    impl ChunkGenerator for VoidGenerator {
+        fn get_chunk(&self, chunk_pos: &ChunkPos) -> Chunk {
+            Chunk::new(*chunk_pos)
+        }
+    }
+
+    #[test]
+    fn makes_generators_by_name_with_options() {
+        let registry = GeneratorRegistry::default();
+        assert_eq!(registry.get_names(), vec!["basic", "flat"]);
+
+        let flat = registry
+            .create(FLAT_GENERATOR, 0, r#"{ "layers": [1, 1, 8] }"#)
+            .unwrap();
+        let chunk = flat.get_chunk(&ChunkPos { x: -3, y: 2 });
+        let block_at = |y| chunk.get_block(&InnerChunkPos::new(5, y, 9)).block_type;
+        assert_eq!(block_at(0), BlockType::Stone);
+        assert_eq!(block_at(1), BlockType::Stone);
+        assert_eq!(block_at(2), BlockType::Grass);
+        assert_eq!(block_at(3), BlockType::Void);
+        assert_eq!(flat.get_biome(10, 10).height, 2);
+
+        let basic = registry
+            .create(
+                "basic",
+                9,
+                r#"{ "caves": { "threshold": 2.0 }, "ores": [] }"#,
+            )
+            .unwrap();
+        let chunk = basic.get_chunk(&ChunkPos { x: 0, y: 0 });
+        assert!(chunk
+            .get_all_blocks()
+            .iter()
+            .all(|block| block.block_type != BlockType::Gold));
+    }
+
+    #[test]
+    fn rejects_unknown_generators_and_bad_options() {
+        let mut registry = GeneratorRegistry::default();
+        assert!(matches!(
+            registry.create("void", 0, ""),
+            Err(GeneratorError::UnknownGenerator(_))
+        ));
+        assert!(matches!(
+            registry.create("flat", 0, r#"{ "layers": "grass" }"#),
+            Err(GeneratorError::InvalidOptions(_, _))
+        ));
+        assert!(matches!(
+            registry.create("basic", 0, "{"),
+            Err(GeneratorError::InvalidOptions(_, _))
+        ));
+        let flat_options = |layers: usize| format!(r#"{{ "layers": {:?} }}"#, vec![1; layers]);
+        assert!(registry.create("flat", 0, &flat_options(256)).is_ok());
+        assert!(matches!(
+            registry.create("flat", 0, &flat_options(257)),
+            Err(GeneratorError::InvalidOptions(_, _))
+        ));
+
+        registry.register("void", |_seed, _options| Ok(Box::new(VoidGenerator {})));
+        let void = registry.create("void", 0, "").unwrap();
+        assert!(void
+            .get_chunk(&ChunkPos { x: 0, y: 0 })
+            .get_all_blocks()
+            .is_empty());
+    }
+
+    #[test]
+    fn shared_registrations_are_kept() {
+        GeneratorRegistry::register_shared("shared void", |_seed, _options| {
+            Ok(Box::new(VoidGenerator {}))
+        });
+        assert!(
+            GeneratorRegistry::with_shared(|registry| registry.get_names())
+                .contains(&"shared void".to_string())
+        );
+
+        let generator = crate::TerrainGenerator::with_generator("shared void", 0, "")
+            .ok()
+            .unwrap();
+        assert!(generator.get_chunk(0, 0).get_all_blocks().is_empty());
+    }
+}
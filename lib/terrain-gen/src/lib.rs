@@ -1,11 +1,22 @@
+pub mod biome;
+pub mod caves;
+pub mod generator;
+pub mod ores;
+pub mod seed;
 mod utils;
-use noise::{NoiseFn, Perlin};
+use biome::{Biome, BiomeGenerator, BiomeSample};
+use caves::{CaveCarver, CaveConfig};
+use generator::{ChunkGenerator, GeneratorRegistry};
+use ores::{default_ore_rules, OrePlacer, OreRule};
 use rand::rngs::StdRng;
-use rand::SeedableRng;
+use rand::{Rng, SeedableRng};
 use rand_distr::{Distribution, Uniform};
+use seed::{derive_chunk_seed, FeatureSalt};
+use serde::Deserialize;
+use serde_wasm_bindgen::{to_value, Error};
 use wasm_bindgen::prelude::*;
 use world::{
-    block::{self, BlockType, ChunkBlock},
+    block::{self, BlockId, BlockType, ChunkBlock},
     chunk::{Chunk, CHUNK_WIDTH},
     direction::{Direction, Directions, EVERY_FLAT_DIRECTION},
     positions::{ChunkPos, InnerChunkPos, WorldPos},
@@ -82,7 +93,7 @@ impl TreeLocator {
                     y: y + y_pos,
                     z: self.world_z,
                 },
-                block_type: BlockType::Wood,
+                block_type: BlockType::Wood.into(),
                 extra_data: block::BlockData::None,
             })
             .collect::<Vec<WorldBlock>>();
@@ -100,7 +111,7 @@ impl TreeLocator {
 
             blocks.push(WorldBlock {
                 world_pos: pos,
-                block_type: BlockType::Leaf,
+                block_type: BlockType::Leaf.into(),
                 extra_data: block::BlockData::None,
             });
         }
@@ -109,16 +120,18 @@ impl TreeLocator {
     }
 }
 
-pub struct TreeRandomSpreadGenerator {
-    seed: u64,
+pub struct TreeRandomSpreadGenerator<'a> {
+    /** The world seed */
+    seed: u32,
+    biomes: &'a BiomeGenerator,
 }
 
-impl TreeRandomSpreadGenerator {
+impl<'a> TreeRandomSpreadGenerator<'a> {
     fn get_potential_tree_locations(
         &self,
         chunk_pos: ChunkPos,
     ) -> Box<dyn Iterator<Item = WorldPos>> {
-        let chunk_seed = self.seed + (chunk_pos.x as u64 * 1000) + (chunk_pos.y as u64 * 1000000);
+        let chunk_seed = derive_chunk_seed(self.seed, &chunk_pos, FeatureSalt::Trees);
         let mut rng: StdRng = SeedableRng::seed_from_u64(chunk_seed);
         let dist = Uniform::new(0, CHUNK_WIDTH);
 
@@ -134,6 +147,12 @@ impl TreeRandomSpreadGenerator {
                 z: (chunk_pos.y * CHUNK_WIDTH) as i32 + z as i32,
             };
 
+            // sparser biomes skip more of the spots
+            let tree_density = self.biomes.get_biome(pos.x, pos.z).info().tree_density;
+            if rng.gen::<f64>() >= tree_density {
+                continue;
+            }
+
             // loop over and make sure that the tree is not too close to any other trees
             let mut too_close = false;
             for other_pos in tree_locations.iter() {
@@ -224,8 +243,10 @@ impl TreeRandomSpreadGenerator {
     }
 }
 
-struct FlowerGetter {
-    seed: u64,
+struct FlowerGetter<'a> {
+    /** The world seed */
+    seed: u32,
+    biomes: &'a BiomeGenerator,
 }
 
 #[derive(Eq, PartialEq)]
@@ -243,16 +264,16 @@ impl FlowerLocator {
                 self.world_z % CHUNK_WIDTH as i32,
             )
             .to_inner_chunk_pos(),
-            block_type: BlockType::RedFlower,
+            block_type: BlockType::RedFlower.into(),
             extra_data: block::BlockData::None,
         }
     }
 }
 
-impl FlowerGetter {
+impl<'a> FlowerGetter<'a> {
     fn get_flowers(self: &Self, chunk_pos: &ChunkPos) -> Vec<FlowerLocator> {
         // generate 15-25 random flowers per chunk
-        let chunk_seed = self.seed + (chunk_pos.x as u64 * 1000) + (chunk_pos.y as u64 * 1000000);
+        let chunk_seed = derive_chunk_seed(self.seed, chunk_pos, FeatureSalt::Flowers);
         let mut rng: StdRng = SeedableRng::seed_from_u64(chunk_seed);
         let dist = Uniform::new(0, CHUNK_WIDTH);
         let flower_count_getter = Uniform::new(15, 25);
@@ -270,6 +291,15 @@ impl FlowerGetter {
                 world_z: (chunk_pos.y * CHUNK_WIDTH) as i32 + z as i32,
             };
 
+            let flower_density = self
+                .biomes
+                .get_biome(loc.world_x, loc.world_z)
+                .info()
+                .flower_density;
+            if rng.gen::<f64>() >= flower_density {
+                continue;
+            }
+
             let already_has_flower = flowers.iter().any(|other_loc| *other_loc == loc);
 
             if already_has_flower {
@@ -283,39 +313,111 @@ impl FlowerGetter {
     }
 }
 
+/** Everything about the basic terrain that can be tuned without touching the generator */
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct BasicTerrainConfig {
+    pub caves: CaveConfig,
+    pub ores: Vec<OreRule>,
+}
+
+impl Default for BasicTerrainConfig {
+    fn default() -> Self {
+        BasicTerrainConfig {
+            caves: CaveConfig::default(),
+            ores: default_ore_rules(),
+        }
+    }
+}
+
 struct BasicChunkGetter {
     seed: u32,
-    jag_factor: f64,
-    height_multiplier: f64,
+    biomes: BiomeGenerator,
+    caves: CaveCarver,
+    ores: OrePlacer,
 }
 
 impl BasicChunkGetter {
     pub fn make(seed: u32) -> BasicChunkGetter {
+        BasicChunkGetter::make_with_config(seed, BasicTerrainConfig::default())
+    }
+
+    pub fn make_with_config(seed: u32, config: BasicTerrainConfig) -> BasicChunkGetter {
         BasicChunkGetter {
             seed,
-            jag_factor: 1.0 / 100.0,
-            height_multiplier: 10.0,
+            biomes: BiomeGenerator::new(seed),
+            caves: CaveCarver::new(seed, config.caves),
+            ores: OrePlacer::new(seed, config.ores),
         }
     }
 
-    pub fn get_chunk(&self, chunk_pos: &ChunkPos) -> Chunk {
-        let noise = Perlin::new(self.seed);
+    /** The y of the surface block, or None if a cave has carved it away and there is nothing to stand on */
+    fn get_ground_height(&self, world_x: i32, world_z: i32) -> Option<i32> {
+        let height = self.biomes.get_height(world_x, world_z);
+        if self.caves.is_carved(world_x, height, world_z) {
+            return None;
+        }
+        Some(height)
+    }
+}
 
-        let get_height = |x: f64, z: f64| -> f64 {
-            let per_val = noise.get([x * self.jag_factor, z * self.jag_factor]);
-            let height = ((per_val.abs() * self.height_multiplier) + 5.0) as i32;
-            height as f64
-        };
+impl ChunkGenerator for BasicChunkGetter {
+    fn get_biome(&self, world_x: i32, world_z: i32) -> BiomeSample {
+        self.biomes.sample(world_x, world_z)
+    }
 
+    fn get_chunk(&self, chunk_pos: &ChunkPos) -> Chunk {
         let mut chunk = Chunk::new(*chunk_pos);
 
-        let trees_in_chunk = TreeRandomSpreadGenerator { seed: 100 };
+        for x in 0u8..CHUNK_WIDTH as u8 {
+            for z in 0u8..CHUNK_WIDTH as u8 {
+                let world_x = (chunk_pos.x * CHUNK_WIDTH) as i32 + x as i32;
+                let world_z = (chunk_pos.y * CHUNK_WIDTH) as i32 + z as i32;
+
+                let column = self.biomes.sample(world_x, world_z);
+                let height = column.height as u8;
+
+                for y in 0u8..=height {
+                    // carve out caves as we go
+                    if self.caves.is_carved(world_x, y as i32, world_z) {
+                        continue;
+                    }
+
+                    // the biome's surface block goes on top
+                    let block_type = if y == height {
+                        column.biome.info().surface_block
+                    } else {
+                        BlockType::Stone
+                    };
+                    let block = ChunkBlock {
+                        pos: InnerChunkPos::new(x, y, z),
+                        block_type: block_type.into(),
+                        extra_data: block::BlockData::None,
+                    };
+
+                    chunk.add_block(block);
+                }
+            }
+        }
+
+        self.ores.place_ores(&mut chunk, |world_x, world_z| {
+            self.biomes.get_height(world_x, world_z)
+        });
+
+        // decorate once the terrain is carved, so nothing is placed over a cave
+        let trees_in_chunk = TreeRandomSpreadGenerator {
+            seed: self.seed,
+            biomes: &self.biomes,
+        };
 
         let trees = trees_in_chunk.get_trees(*chunk_pos);
 
         for tree in trees {
-            let height = get_height(tree.world_x as f64, tree.world_z as f64) as i32;
-            let blocks = tree.get_world_blocks(height);
+            let height = match self.get_ground_height(tree.world_x, tree.world_z) {
+                Some(height) => height,
+                None => continue,
+            };
+            let blocks = tree.get_world_blocks(height + 1);
             for block in blocks {
                 let block_chunnk_pos = block.world_pos.to_chunk_pos();
                 if block_chunnk_pos != *chunk_pos {
@@ -326,12 +428,18 @@ impl BasicChunkGetter {
         }
 
         // place flowers
-        let flowers_in_chunk = FlowerGetter { seed: 100 };
+        let flowers_in_chunk = FlowerGetter {
+            seed: self.seed,
+            biomes: &self.biomes,
+        };
 
         let flowers = flowers_in_chunk.get_flowers(&chunk_pos);
 
         for flower in flowers {
-            let height = get_height(flower.world_x as f64, flower.world_z as f64) as i32;
+            let height = match self.get_ground_height(flower.world_x, flower.world_z) {
+                Some(height) => height,
+                None => continue,
+            };
 
             let flower = flower.make_chunk_block(height + 1);
 
@@ -343,70 +451,89 @@ impl BasicChunkGetter {
             chunk.add_block(flower);
         }
 
-        for x in 0u8..CHUNK_WIDTH as u8 {
-            for z in 0u8..CHUNK_WIDTH as u8 {
-                let world_x = (chunk_pos.x * CHUNK_WIDTH as i16) as f64 + (x as f64);
-                let world_z = (chunk_pos.y * CHUNK_WIDTH as i16) as f64 + (z as f64);
+        chunk
+    }
+}
 
-                let per_val = noise.get([world_x * self.jag_factor, world_z * self.jag_factor]);
-                let height = ((per_val.abs() * self.height_multiplier) + 5.0) as u8;
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct FlatWorldConfig {
+    /** The blocks stacked up in every column, from y = 0 up. No layers makes an empty world */
+    pub layers: Vec<BlockId>,
+}
+
+impl Default for FlatWorldConfig {
+    fn default() -> Self {
+        FlatWorldConfig {
+            layers: vec![BlockType::Grass.into()],
+        }
+    }
+}
 
-                // use web_sys::console;
-                // console::log_1(&format!("height: {}", height).into());
+struct FlatWorldChunkGetter {
+    config: FlatWorldConfig,
+}
 
-                for y in 0u8..height {
+impl ChunkGenerator for FlatWorldChunkGetter {
+    fn get_chunk(&self, chunk_pos: &ChunkPos) -> Chunk {
+        let mut chunk = Chunk::new(*chunk_pos);
+        for x in 0u8..CHUNK_WIDTH as u8 {
+            for z in 0u8..CHUNK_WIDTH as u8 {
+                for (y, block_type) in self.config.layers.iter().enumerate() {
                     let block = ChunkBlock {
-                        pos: InnerChunkPos::new(x, y, z),
-                        block_type: BlockType::Stone,
+                        pos: InnerChunkPos::new(x, y as u8, z),
+                        block_type: *block_type,
                         extra_data: block::BlockData::None,
                     };
-
                     chunk.add_block(block);
                 }
-                // add top grass block
-                let block = ChunkBlock {
-                    pos: InnerChunkPos::new(x, height, z),
-                    block_type: BlockType::Grass,
-                    extra_data: block::BlockData::None,
-                };
-                chunk.add_block(block);
             }
         }
-
         chunk
     }
-}
-
-struct FlatWorldChunkGetter {}
 
-impl FlatWorldChunkGetter {
-    pub fn get_chunk(&self, chunk_pos: &ChunkPos) -> Chunk {
-        let mut chunk = Chunk::new(*chunk_pos);
-        for x in 0u8..CHUNK_WIDTH as u8 {
-            for z in 0u8..CHUNK_WIDTH as u8 {
-                let block = ChunkBlock {
-                    pos: InnerChunkPos::new(x, 0, z),
-                    block_type: BlockType::Grass,
-                    extra_data: block::BlockData::None,
-                };
-                chunk.add_block(block);
-            }
+    fn get_biome(&self, _world_x: i32, _world_z: i32) -> BiomeSample {
+        BiomeSample {
+            biome: Biome::Plains,
+            temperature: 0.0,
+            humidity: 0.0,
+            height: self.config.layers.len() as i32 - 1,
         }
-        chunk
     }
 }
 
 #[wasm_bindgen]
 pub struct TerrainGenerator {
     pub seed: u32,
-    pub flat_world: bool,
+    generator: Box<dyn ChunkGenerator>,
 }
 
 #[wasm_bindgen]
 impl TerrainGenerator {
+    /** Makes the basic or flat generator with their default options */
     #[wasm_bindgen(constructor)]
     pub fn new(seed: u32, flat_world: bool) -> TerrainGenerator {
-        TerrainGenerator { seed, flat_world }
+        let config = FlatWorldConfig::default();
+        let generator: Box<dyn ChunkGenerator> = if flat_world {
+            Box::new(FlatWorldChunkGetter { config })
+        } else {
+            Box::new(BasicChunkGetter::make(seed))
+        };
+        TerrainGenerator { seed, generator }
+    }
+
+    /** Makes a generator registered in the shared registry under the name. Options are a JSON object, or an empty string for the defaults */
+    pub fn with_generator(name: &str, seed: u32, options: &str) -> Result<TerrainGenerator, Error> {
+        let generator =
+            GeneratorRegistry::with_shared(|registry| registry.create(name, seed, options))
+                .map_err(|e| Error::new(format!("{}", e)))?;
+        Ok(TerrainGenerator { seed, generator })
+    }
+
+    pub fn get_generator_names() -> Result<JsValue, Error> {
+        to_value(&GeneratorRegistry::with_shared(|registry| {
+            registry.get_names()
+        }))
     }
 
     pub fn get_chunk(&self, chunk_x: i16, chunk_y: i16) -> Chunk {
@@ -415,12 +542,136 @@ impl TerrainGenerator {
             y: chunk_y,
         };
 
-        if self.flat_world {
-            let chunk_getter = FlatWorldChunkGetter {};
-            return chunk_getter.get_chunk(&chunk_pos);
-        }
+        self.generator.get_chunk(&chunk_pos)
+    }
+
+    /** The biome and climate of a column, so the client can tint grass and show biome names */
+    pub fn get_biome(&self, world_x: i32, world_z: i32) -> BiomeSample {
+        self.generator.get_biome(world_x, world_z)
+    }
+}
 
-        let chunk_getter = BasicChunkGetter::make(self.seed);
-        chunk_getter.get_chunk(&chunk_pos)
+#[cfg(test)]
+mod tests {
+    use super::{BasicChunkGetter, BasicTerrainConfig, FlowerGetter, TreeRandomSpreadGenerator};
+    use crate::{
+        biome::{Biome, BiomeGenerator},
+        caves::CaveConfig,
+        generator::ChunkGenerator,
+    };
+    use world::{
+        block::BlockType,
+        chunk::CHUNK_WIDTH,
+        positions::{ChunkPos, InnerChunkPos},
+    };
+
+    /** The first chunk along the x axis that, along with the chunk after it, is forest all the way across */
+    fn find_forest_chunk(biomes: &BiomeGenerator) -> ChunkPos {
+        (0..1000)
+            .map(|x| ChunkPos { x, y: 0 })
+            .find(|chunk_pos| {
+                let corner_x = chunk_pos.x as i32 * CHUNK_WIDTH as i32;
+                (0..CHUNK_WIDTH as i32 * 2)
+                    .all(|i| biomes.get_biome(corner_x + i, i / 2) == Biome::Forest)
+            })
+            .unwrap()
+    }
+
+    /** Where trees and flowers could go in the chunk, relative to its corner */
+    fn get_decoration_offsets(
+        seed: u32,
+        biomes: &BiomeGenerator,
+        chunk_pos: ChunkPos,
+    ) -> Vec<(i32, i32)> {
+        let corner_x = chunk_pos.x as i32 * CHUNK_WIDTH as i32;
+        let corner_z = chunk_pos.y as i32 * CHUNK_WIDTH as i32;
+        let trees = TreeRandomSpreadGenerator { seed, biomes }
+            .get_potential_tree_locations(chunk_pos)
+            .map(|pos| (pos.x - corner_x, pos.z - corner_z));
+        let flowers = FlowerGetter { seed, biomes }
+            .get_flowers(&chunk_pos)
+            .into_iter()
+            .map(|flower| (flower.world_x - corner_x, flower.world_z - corner_z));
+        trees.chain(flowers).collect()
+    }
+
+    #[test]
+    fn different_seeds_decorate_differently() {
+        let biomes = BiomeGenerator::new(1);
+        let chunk_pos = find_forest_chunk(&biomes);
+
+        let first = get_decoration_offsets(1, &biomes, chunk_pos);
+        let second = get_decoration_offsets(2, &biomes, chunk_pos);
+        assert!(!first.is_empty() && !second.is_empty());
+        assert_ne!(first, second);
+        assert_eq!(first, get_decoration_offsets(1, &biomes, chunk_pos));
+    }
+
+    #[test]
+    fn different_chunks_decorate_differently() {
+        let biomes = BiomeGenerator::new(1);
+        let chunk_pos = find_forest_chunk(&biomes);
+        let next_chunk_pos = ChunkPos {
+            x: chunk_pos.x + 1,
+            y: chunk_pos.y,
+        };
+
+        let first = get_decoration_offsets(1, &biomes, chunk_pos);
+        let next = get_decoration_offsets(1, &biomes, next_chunk_pos);
+        assert!(!first.is_empty() && !next.is_empty());
+        assert_ne!(first, next);
+    }
+
+    #[test]
+    fn decorations_are_never_placed_over_caves() {
+        let getter = BasicChunkGetter::make_with_config(
+            4,
+            BasicTerrainConfig {
+                caves: CaveConfig {
+                    threshold: 0.1,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+
+        let mut decorations = 0;
+        let mut carved_surfaces = 0;
+        for chunk_x in -2..2 {
+            for chunk_z in -2..2 {
+                let chunk_pos = ChunkPos {
+                    x: chunk_x,
+                    y: chunk_z,
+                };
+                let chunk = getter.get_chunk(&chunk_pos);
+                for block in chunk.get_all_blocks() {
+                    let is_decoration = block.block_type == BlockType::RedFlower
+                        || block.block_type == BlockType::Wood;
+                    if !is_decoration || block.pos.y == 0 {
+                        continue;
+                    }
+                    decorations += 1;
+                    let below = InnerChunkPos::new(block.pos.x, block.pos.y - 1, block.pos.z);
+                    assert!(
+                        chunk.has_block(&below),
+                        "{:?} at {:?} is floating",
+                        block.block_type,
+                        block.pos
+                    );
+                }
+
+                for x in 0..CHUNK_WIDTH as i32 {
+                    for z in 0..CHUNK_WIDTH as i32 {
+                        let world_x = chunk_x as i32 * CHUNK_WIDTH as i32 + x;
+                        let world_z = chunk_z as i32 * CHUNK_WIDTH as i32 + z;
+                        if getter.get_ground_height(world_x, world_z).is_none() {
+                            carved_surfaces += 1;
+                        }
+                    }
+                }
+            }
+        }
+        assert!(decorations > 0);
+        assert!(carved_surfaces > 0);
     }
 }
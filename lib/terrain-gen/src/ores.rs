@@ -0,0 +1,208 @@
+use crate::seed::{derive_chunk_seed, FeatureSalt};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use world::{
+    block::{BlockData, BlockId, BlockType, ChunkBlock},
+    chunk::{Chunk, CHUNK_WIDTH},
+    direction::Directions,
+    positions::{ChunkPos, InnerChunkPos, WorldPos},
+};
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct OreRule {
+    pub block_type: BlockId,
+    /** Veins only start between these heights, inclusive */
+    pub min_y: i32,
+    pub max_y: i32,
+    /** How many blocks each vein wanders through. Some of them may not be replaceable */
+    pub vein_size: u32,
+    pub veins_per_chunk: u32,
+    /** The blocks the ore can replace, so veins never fill in caves or poke out of the ground */
+    #[serde(default = "default_replaces")]
+    pub replaces: Vec<BlockId>,
+}
+
+fn default_replaces() -> Vec<BlockId> {
+    vec![BlockType::Stone.into()]
+}
+
+pub fn default_ore_rules() -> Vec<OreRule> {
+    vec![OreRule {
+        block_type: BlockType::Gold.into(),
+        min_y: 1,
+        max_y: 12,
+        vein_size: 6,
+        veins_per_chunk: 3,
+        replaces: default_replaces(),
+    }]
+}
+
+pub struct OrePlacer {
+    seed: u32,
+    rules: Vec<OreRule>,
+}
+
+impl OrePlacer {
+    pub fn new(seed: u32, rules: Vec<OreRule>) -> OrePlacer {
+        OrePlacer { seed, rules }
+    }
+
+    fn get_rng(&self, chunk_pos: &ChunkPos) -> StdRng {
+        SeedableRng::seed_from_u64(derive_chunk_seed(self.seed, chunk_pos, FeatureSalt::Ores))
+    }
+
+    /**
+     * Grows every rule's veins through the chunk. Each vein starts at a random spot in the rule's
+     * depth range and wanders one block at a time, turning whatever host blocks it passes through into ore.
+     * Veins stay inside the chunk, so chunks can be generated in any order.
+     * They also stay below the surface block of each column, which surface_height gives for a world x and z,
+     * so ore never shows on top of the ground even where the surface is one of the host blocks.
+     */
+    pub fn place_ores<F>(&self, chunk: &mut Chunk, surface_height: F)
+    where
+        F: Fn(i32, i32) -> i32,
+    {
+        let mut rng = self.get_rng(&chunk.position);
+        let chunk_x = (chunk.position.x * CHUNK_WIDTH) as i32;
+        let chunk_z = (chunk.position.y * CHUNK_WIDTH) as i32;
+        let directions = Directions::all().into_iter().collect::<Vec<_>>();
+
+        for rule in &self.rules {
+            if rule.min_y > rule.max_y {
+                continue;
+            }
+            for _ in 0..rule.veins_per_chunk {
+                let mut pos = WorldPos::new(
+                    rng.gen_range(0..CHUNK_WIDTH as i32),
+                    rng.gen_range(rule.min_y..=rule.max_y),
+                    rng.gen_range(0..CHUNK_WIDTH as i32),
+                );
+
+                for _ in 0..rule.vein_size {
+                    let in_chunk = (0..CHUNK_WIDTH as i32).contains(&pos.x)
+                        && (0..CHUNK_WIDTH as i32).contains(&pos.z)
+                        && (0..=u8::MAX as i32).contains(&pos.y);
+                    if in_chunk && pos.y < surface_height(chunk_x + pos.x, chunk_z + pos.z) {
+                        let inner_pos = InnerChunkPos::new(pos.x as u8, pos.y as u8, pos.z as u8);
+                        let host = chunk.get_block(&inner_pos).block_type;
+                        if rule.replaces.contains(&host) {
+                            chunk.add_block(ChunkBlock {
+                                pos: inner_pos,
+                                block_type: rule.block_type,
+                                extra_data: BlockData::None,
+                            });
+                        }
+                    }
+
+                    let direction = directions[rng.gen_range(0..directions.len())];
+                    pos = pos.move_direction(&direction);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{default_ore_rules, OrePlacer, OreRule};
+    use world::{
+        block::{BlockData, BlockType, ChunkBlock},
+        chunk::Chunk,
+        positions::{ChunkPos, InnerChunkPos},
+    };
+
+    /** Stone up to y = 8 with sand on top up to y = 15 */
+    fn make_chunk() -> Chunk {
+        make_chunk_with_stone_up_to(8)
+    }
+
+    fn make_chunk_with_stone_up_to(stone_height: u8) -> Chunk {
+        let mut chunk = Chunk::new(ChunkPos { x: 3, y: -2 });
+        for x in 0..16 {
+            for y in 0..16 {
+                for z in 0..16 {
+                    chunk.add_block(ChunkBlock {
+                        pos: InnerChunkPos::new(x, y, z),
+                        block_type: if y <= stone_height {
+                            BlockType::Stone.into()
+                        } else {
+                            BlockType::Sand.into()
+                        },
+                        extra_data: BlockData::None,
+                    });
+                }
+            }
+        }
+        chunk
+    }
+
+    fn get_ore_positions(chunk: &Chunk, block_type: BlockType) -> Vec<InnerChunkPos> {
+        chunk
+            .get_all_blocks()
+            .into_iter()
+            .filter(|block| block.block_type == block_type)
+            .map(|block| block.pos)
+            .collect()
+    }
+
+    #[test]
+    fn generates_gold_the_same_way_every_time() {
+        let mut chunk = make_chunk();
+        OrePlacer::new(5, default_ore_rules()).place_ores(&mut chunk, |_, _| 15);
+        let gold = get_ore_positions(&chunk, BlockType::Gold);
+        assert!(!gold.is_empty());
+
+        let mut again = make_chunk();
+        OrePlacer::new(5, default_ore_rules()).place_ores(&mut again, |_, _| 15);
+        assert_eq!(gold, get_ore_positions(&again, BlockType::Gold));
+    }
+
+    #[test]
+    fn veins_only_replace_host_blocks() {
+        let rules = vec![OreRule {
+            block_type: BlockType::Gold.into(),
+            min_y: 6,
+            max_y: 11,
+            vein_size: 20,
+            veins_per_chunk: 10,
+            replaces: vec![BlockType::Stone.into()],
+        }];
+        let mut chunk = make_chunk();
+        OrePlacer::new(5, rules).place_ores(&mut chunk, |_, _| 15);
+
+        let gold = get_ore_positions(&chunk, BlockType::Gold);
+        assert!(!gold.is_empty());
+        for pos in gold {
+            assert!(pos.y <= 8, "gold replaced sand at {:?}", pos);
+        }
+        assert_eq!(
+            get_ore_positions(&chunk, BlockType::Sand).len(),
+            16 * 16 * 7
+        );
+    }
+
+    #[test]
+    fn veins_never_replace_the_surface() {
+        let rules = vec![OreRule {
+            block_type: BlockType::Gold.into(),
+            min_y: 10,
+            max_y: 15,
+            vein_size: 20,
+            veins_per_chunk: 20,
+            replaces: vec![BlockType::Stone.into()],
+        }];
+        // A mountain made of stone all the way up, with the surface at y = 12
+        let mut chunk = make_chunk_with_stone_up_to(12);
+        OrePlacer::new(5, rules).place_ores(&mut chunk, |world_x, world_z| {
+            assert!((48..64).contains(&world_x) && (-32..-16).contains(&world_z));
+            12
+        });
+
+        let gold = get_ore_positions(&chunk, BlockType::Gold);
+        assert!(!gold.is_empty());
+        for pos in gold {
+            assert!(pos.y < 12, "gold replaced the surface at {:?}", pos);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use world::positions::ChunkPos;
+
+/** Mixed into seeds so every feature gets its own randomness, even in the same chunk */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeatureSalt {
+    Trees = 1,
+    Flowers = 2,
+    Ores = 3,
+    Temperature = 4,
+    Humidity = 5,
+    Caves = 6,
+}
+
+/** The splitmix64 finalizer. It maps every u64 to a different u64 and scrambles the bits well */
+fn mix(value: u64) -> u64 {
+    let mut z = value;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/**
+ * The seed a feature uses for one chunk.
+ * The world seed and chunk position are packed together without overlapping and mix never maps two values to the same one,
+ * so for a feature, no two chunks in any two worlds share a seed.
+ */
+pub fn derive_chunk_seed(world_seed: u32, chunk_pos: &ChunkPos, salt: FeatureSalt) -> u64 {
+    let chunk = ((chunk_pos.x as u16 as u64) << 16) | chunk_pos.y as u16 as u64;
+    let key = ((world_seed as u64) << 32) | chunk;
+    mix(key ^ mix(salt as u64))
+}
+
+/** The seed for noise a feature samples across the whole world */
+pub fn derive_world_seed(world_seed: u32, salt: FeatureSalt) -> u32 {
+    (mix(world_seed as u64 ^ mix(salt as u64)) >> 32) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{derive_chunk_seed, derive_world_seed, FeatureSalt};
+    use std::collections::HashSet;
+    use world::positions::ChunkPos;
+
+    #[test]
+    fn chunks_seeds_and_features_get_different_seeds() {
+        let mut seen = HashSet::new();
+        for world_seed in &[0, 1, 100, u32::MAX] {
+            for salt in &[FeatureSalt::Trees, FeatureSalt::Flowers] {
+                for x in -20..20 {
+                    for y in -20..20 {
+                        let seed = derive_chunk_seed(*world_seed, &ChunkPos { x, y }, *salt);
+                        assert!(seen.insert(seed), "seed collision at {}, {}", x, y);
+                    }
+                }
+            }
+        }
+
+        // The old formula mapped both of these to the same seed
+        assert_ne!(
+            derive_chunk_seed(0, &ChunkPos { x: 1000, y: 0 }, FeatureSalt::Trees),
+            derive_chunk_seed(0, &ChunkPos { x: 0, y: 1 }, FeatureSalt::Trees)
+        );
+        assert_ne!(
+            derive_world_seed(1, FeatureSalt::Caves),
+            derive_world_seed(2, FeatureSalt::Caves)
+        );
+    }
+}
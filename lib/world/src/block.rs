@@ -3,12 +3,18 @@ use crate::{
     positions::{ChunkPos, InnerChunkPos},
     world::world_block::WorldBlock,
 };
-use lazy_static::lazy_static;
+use block_registry::BlockRegistry;
+use block_state::BlockState;
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
-use std::collections::HashMap;
+use std::{convert::TryFrom, fmt};
 use wasm_bindgen::prelude::*;
 
+pub mod block_registry;
+mod block_registry_duct;
+pub mod block_state;
+mod block_state_duct;
+
 #[wasm_bindgen]
 #[derive(Eq, Hash, PartialEq, Clone, Copy, Debug, Serialize_repr, Deserialize_repr)]
 #[repr(u8)]
@@ -25,6 +31,74 @@ pub enum BlockType {
     Image = 9,
     Planks = 10,
     Red = 11,
+    Torch = 12,
+    Glowstone = 13,
+    Sand = 14,
+    Gravel = 15,
+}
+
+/**
+ * The id a block is stored under in chunks, worlds and saved data.
+ * Any id defined in the installed block registry is valid, so blocks loaded from a document
+ * can be placed like the built in ones. BlockType names the ids of the built in blocks.
+ */
+#[derive(Eq, Hash, PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub struct BlockId(u8);
+
+impl BlockId {
+    pub const VOID: BlockId = BlockId(BlockType::Void as u8);
+
+    /** Checks the id against a registry rather than the installed one */
+    pub fn new(id: u8, registry: &BlockRegistry) -> Result<BlockId, UnknownBlockId> {
+        match registry.get(id) {
+            Some(_) => Ok(BlockId(id)),
+            None => Err(UnknownBlockId(id)),
+        }
+    }
+
+    pub fn to_index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl From<BlockType> for BlockId {
+    fn from(block_type: BlockType) -> Self {
+        BlockId(block_type as u8)
+    }
+}
+
+impl From<BlockId> for u8 {
+    fn from(id: BlockId) -> Self {
+        id.0
+    }
+}
+
+impl TryFrom<u8> for BlockId {
+    type Error = UnknownBlockId;
+
+    /** Checks the id against the installed block registry */
+    fn try_from(id: u8) -> Result<Self, Self::Error> {
+        BlockRegistry::with_installed(|registry| BlockId::new(id, registry))
+    }
+}
+
+impl PartialEq<BlockType> for BlockId {
+    fn eq(&self, other: &BlockType) -> bool {
+        self.0 == *other as u8
+    }
+}
+
+/** A block id that the block registry doesn't define */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownBlockId(pub u8);
+
+impl std::error::Error for UnknownBlockId {}
+
+impl fmt::Display for UnknownBlockId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "There is no block with id {}", self.0)
+    }
 }
 
 #[wasm_bindgen]
@@ -35,7 +109,7 @@ pub struct WasmImageData {
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct ChunkBlock {
-    pub block_type: BlockType,
+    pub block_type: BlockId,
     pub extra_data: BlockData,
     pub pos: InnerChunkPos,
 }
@@ -56,6 +130,27 @@ impl ChunkBlock {
 pub enum BlockData {
     None,
     Image(Direction),
+    /** Property values for block types that declare properties, see block_state */
+    State(BlockState),
+}
+
+impl BlockData {
+    /** Blocks without a stored state read as every property being 0 */
+    pub fn get_state(&self) -> BlockState {
+        match self {
+            BlockData::State(state) => *state,
+            _ => BlockState::default(),
+        }
+    }
+
+    /** The default state is stored as None so plain blocks share palette entries */
+    pub fn from_state(state: BlockState) -> BlockData {
+        if state == BlockState::default() {
+            BlockData::None
+        } else {
+            BlockData::State(state)
+        }
+    }
 }
 
 pub mod wasm {
@@ -70,13 +165,25 @@ pub mod wasm {
 }
 
 #[wasm_bindgen]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum BlockShape {
     Cube,
     X,
     Flat,
 }
 
+/** Which render pass a block's faces are drawn in */
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RenderLayer {
+    /** Fully solid faces, drawn first with depth writes */
+    Opaque = 0,
+    /** Faces with fully transparent holes (leaves, flowers, images), alpha tested */
+    Cutout = 1,
+    /** Partially see through faces (water), alpha blended back to front */
+    Translucent = 2,
+}
+
 #[wasm_bindgen]
 #[derive(Clone, Copy)]
 pub struct BlockMetaData {
@@ -85,170 +192,56 @@ pub struct BlockMetaData {
     pub transparent: bool,
     pub intangible: bool,
     pub fluid: bool,
+    /** How much block light the block gives off, 0 to 15 */
+    pub light_emission: u8,
+    /** How much light is lost passing through the block, on top of the usual 1 per block. 15 stops light completely */
+    pub light_filter: u8,
+    /** How long the block takes to break, 0 breaks instantly */
+    pub hardness: f32,
+    /** How much of an explosion's power the block soaks up */
+    pub blast_resistance: f32,
 }
 
-// Define a default value for BlockMetaData
-impl Default for &BlockMetaData {
+impl Default for BlockMetaData {
     fn default() -> Self {
-        &BlockMetaData {
+        BlockMetaData {
             gravitable: false,
             intangible: false,
             fluid: false,
             shape: BlockShape::Cube,
             transparent: false,
+            light_emission: 0,
+            light_filter: 15,
+            hardness: 1.0,
+            blast_resistance: 1.0,
         }
     }
 }
 
 impl BlockMetaData {
-    pub fn get_for_type(block_type: BlockType) -> &'static BlockMetaData {
-        BLOCK_DATA.get(&block_type).unwrap_or_default()
+    /** Looks the block up in the installed block registry */
+    pub fn get_for_type(block_type: BlockId) -> BlockMetaData {
+        BlockRegistry::with_installed(|registry| registry.get_metadata(block_type))
     }
 }
 
 #[wasm_bindgen]
 impl BlockMetaData {
-    pub fn get_for_type_wasm(block_type: BlockType) -> Option<BlockMetaData> {
-        BLOCK_DATA.get(&block_type).copied()
+    pub fn get_for_type_wasm(block_id: u8) -> Option<BlockMetaData> {
+        BlockRegistry::with_installed(|registry| {
+            registry
+                .get(block_id)
+                .map(|definition| definition.get_metadata())
+        })
     }
-}
 
-lazy_static! {
-    static ref BLOCK_DATA: HashMap<BlockType, BlockMetaData> = {
-        let mut map: HashMap<BlockType, BlockMetaData> = HashMap::new();
-        map.insert(
-            BlockType::Void,
-            BlockMetaData {
-                gravitable: false,
-                intangible: true,
-                shape: BlockShape::Cube,
-                transparent: true,
-                fluid: false,
-            },
-        );
-        map.insert(
-            BlockType::Stone,
-            BlockMetaData {
-                gravitable: false,
-                intangible: false,
-                shape: BlockShape::Cube,
-                transparent: false,
-                fluid: false,
-            },
-        );
-        map.insert(
-            BlockType::Image,
-            BlockMetaData {
-                gravitable: false,
-                intangible: false,
-                shape: BlockShape::Flat,
-                transparent: true,
-                fluid: false,
-            },
-        );
-        map.insert(
-            BlockType::Grass,
-            BlockMetaData {
-                gravitable: false,
-                intangible: false,
-                shape: BlockShape::Cube,
-                transparent: false,
-                fluid: false,
-            },
-        );
-        map.insert(
-            BlockType::Wood,
-            BlockMetaData {
-                gravitable: false,
-                intangible: false,
-                shape: BlockShape::Cube,
-                fluid: false,
-                transparent: false,
-            },
-        );
-        map.insert(
-            BlockType::Leaf,
-            BlockMetaData {
-                gravitable: false,
-                intangible: false,
-                fluid: false,
-                shape: BlockShape::Cube,
-                transparent: true,
-            },
-        );
-        map.insert(
-            BlockType::Cloud,
-            BlockMetaData {
-                gravitable: false,
-                intangible: false,
-                fluid: false,
-                shape: BlockShape::Cube,
-                transparent: false,
-            },
-        );
-        map.insert(
-            BlockType::Gold,
-            BlockMetaData {
-                gravitable: false,
-                intangible: false,
-                fluid: false,
-                shape: BlockShape::Cube,
-                transparent: false,
-            },
-        );
-        map.insert(
-            BlockType::RedFlower,
-            BlockMetaData {
-                gravitable: false,
-                intangible: false,
-                fluid: false,
-                shape: BlockShape::X,
-                transparent: true,
-            },
-        );
-        map.insert(
-            BlockType::Water,
-            BlockMetaData {
-                gravitable: false,
-                intangible: true,
-                fluid: true,
-                shape: BlockShape::Cube,
-                transparent: false,
-            },
-        );
-        map.insert(
-            BlockType::RedFlower,
-            BlockMetaData {
-                gravitable: false,
-                intangible: false,
-                fluid: false,
-                shape: BlockShape::X,
-                transparent: true,
-            },
-        );
-
-        map.insert(
-            BlockType::Planks,
-            BlockMetaData {
-                gravitable: false,
-                intangible: false,
-                fluid: false,
-                shape: BlockShape::Cube,
-                transparent: false,
-            },
-        );
-
-        map.insert(
-            BlockType::Red,
-            BlockMetaData {
-                gravitable: false,
-                intangible: false,
-                fluid: false,
-                shape: BlockShape::Cube,
-                transparent: false,
-            },
-        );
-
-        map
-    };
+    pub fn get_render_layer(&self) -> RenderLayer {
+        if self.fluid {
+            RenderLayer::Translucent
+        } else if self.transparent {
+            RenderLayer::Cutout
+        } else {
+            RenderLayer::Opaque
+        }
+    }
 }
@@ -0,0 +1,539 @@
+use super::{
+    block_state::{BlockProperty, BlockState, BlockStateError, BlockStateProperties},
+    BlockData, BlockId, BlockMetaData, BlockShape, BlockType,
+};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt, sync::RwLock};
+
+use crate::chunk::chunk_light::MAX_LIGHT;
+
+/** Everything the engine needs to know about one kind of block */
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BlockDefinition {
+    pub name: String,
+    /** The id stored in chunks */
+    pub id: u8,
+    pub shape: BlockShape,
+    pub transparent: bool,
+    pub fluid: bool,
+    pub gravitable: bool,
+    pub intangible: bool,
+    /** How long the block takes to break, 0 breaks instantly */
+    pub hardness: f32,
+    /** How much of an explosion's power the block soaks up */
+    pub blast_resistance: f32,
+    pub light_emission: u8,
+    pub light_filter: u8,
+    /** Texture names keyed by face, "all" covers every face that isn't listed */
+    pub textures: BTreeMap<String, String>,
+    /** The state properties blocks of this type can have */
+    pub properties: Vec<BlockProperty>,
+}
+
+impl Default for BlockDefinition {
+    fn default() -> Self {
+        BlockDefinition {
+            name: String::new(),
+            id: 0,
+            shape: BlockShape::Cube,
+            transparent: false,
+            fluid: false,
+            gravitable: false,
+            intangible: false,
+            hardness: 1.0,
+            blast_resistance: 1.0,
+            light_emission: 0,
+            light_filter: MAX_LIGHT,
+            textures: BTreeMap::new(),
+            properties: Vec::new(),
+        }
+    }
+}
+
+impl BlockDefinition {
+    fn builtin(name: &str, block_type: BlockType) -> BlockDefinition {
+        let mut textures = BTreeMap::new();
+        textures.insert("all".to_string(), name.to_string());
+
+        BlockDefinition {
+            name: name.to_string(),
+            id: block_type as u8,
+            textures,
+            ..Default::default()
+        }
+    }
+
+    pub fn get_metadata(&self) -> BlockMetaData {
+        BlockMetaData {
+            gravitable: self.gravitable,
+            shape: self.shape,
+            transparent: self.transparent,
+            intangible: self.intangible,
+            fluid: self.fluid,
+            light_emission: self.light_emission,
+            light_filter: self.light_filter,
+            hardness: self.hardness,
+            blast_resistance: self.blast_resistance,
+        }
+    }
+
+    /** The texture for a face, falling back to the "all" texture */
+    pub fn get_texture(&self, face: &str) -> Option<&str> {
+        self.textures
+            .get(face)
+            .or_else(|| self.textures.get("all"))
+            .map(|s| s.as_str())
+    }
+}
+
+/**
+ * A block as written in a JSON or TOML document. Only the id is required.
+ * The fields that are given are laid over the built in block with that id,
+ * or over the defaults if the id is new.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct PartialBlockDefinition {
+    pub id: u8,
+    pub name: Option<String>,
+    pub shape: Option<BlockShape>,
+    pub transparent: Option<bool>,
+    pub fluid: Option<bool>,
+    pub gravitable: Option<bool>,
+    pub intangible: Option<bool>,
+    pub hardness: Option<f32>,
+    pub blast_resistance: Option<f32>,
+    pub light_emission: Option<u8>,
+    pub light_filter: Option<u8>,
+    pub textures: Option<BTreeMap<String, String>>,
+    pub properties: Option<Vec<BlockProperty>>,
+}
+
+impl PartialBlockDefinition {
+    fn apply_to(self, definition: BlockDefinition) -> BlockDefinition {
+        BlockDefinition {
+            id: self.id,
+            name: self.name.unwrap_or(definition.name),
+            shape: self.shape.unwrap_or(definition.shape),
+            transparent: self.transparent.unwrap_or(definition.transparent),
+            fluid: self.fluid.unwrap_or(definition.fluid),
+            gravitable: self.gravitable.unwrap_or(definition.gravitable),
+            intangible: self.intangible.unwrap_or(definition.intangible),
+            hardness: self.hardness.unwrap_or(definition.hardness),
+            blast_resistance: self.blast_resistance.unwrap_or(definition.blast_resistance),
+            light_emission: self.light_emission.unwrap_or(definition.light_emission),
+            light_filter: self.light_filter.unwrap_or(definition.light_filter),
+            textures: self.textures.unwrap_or(definition.textures),
+            properties: self.properties.unwrap_or(definition.properties),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum BlockRegistryError {
+    Parse(String),
+    DuplicateName(String),
+    DuplicateId(u8),
+    InvalidLightLevel(String),
+    /** Void is always id 0 and can't be redefined */
+    ReservedId(String),
+}
+
+impl std::error::Error for BlockRegistryError {}
+
+impl fmt::Display for BlockRegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BlockRegistryError::Parse(e) => write!(f, "Couldn't parse block definitions: {}", e),
+            BlockRegistryError::DuplicateName(name) => {
+                write!(f, "Block {} is defined more than once", name)
+            }
+            BlockRegistryError::DuplicateId(id) => {
+                write!(f, "Block id {} is used more than once", id)
+            }
+            BlockRegistryError::InvalidLightLevel(name) => {
+                write!(f, "Block {} has a light level above {}", name, MAX_LIGHT)
+            }
+            BlockRegistryError::ReservedId(name) => {
+                write!(f, "Block {} can't use id 0, it is reserved for void", name)
+            }
+        }
+    }
+}
+
+/** The document format, a list of blocks under a "blocks" key */
+#[derive(Deserialize)]
+struct BlockDocument {
+    blocks: Vec<PartialBlockDefinition>,
+}
+
+/**
+ * Lookup table of block definitions indexed by id.
+ * The default registry holds the built in blocks.
+ */
+#[derive(Clone)]
+pub struct BlockRegistry {
+    definitions: Vec<Option<BlockDefinition>>,
+    /** Cached per id so lookups in the mesher and lighting don't have to build metadata */
+    metadata: Vec<BlockMetaData>,
+}
+
+impl Default for BlockRegistry {
+    fn default() -> Self {
+        BlockRegistry::builtin()
+    }
+}
+
+lazy_static! {
+    static ref INSTALLED: RwLock<BlockRegistry> = RwLock::new(BlockRegistry::builtin());
+}
+
+impl BlockRegistry {
+    fn empty() -> BlockRegistry {
+        BlockRegistry {
+            definitions: vec![None; u8::MAX as usize + 1],
+            metadata: vec![BlockMetaData::default(); u8::MAX as usize + 1],
+        }
+    }
+
+    pub fn builtin() -> BlockRegistry {
+        let mut registry = BlockRegistry::empty();
+        for definition in builtin_definitions() {
+            registry.insert(definition);
+        }
+        registry
+    }
+
+    /** Loads a JSON document on top of the built in blocks */
+    pub fn from_json(json: &str) -> Result<BlockRegistry, BlockRegistryError> {
+        let document: BlockDocument =
+            serde_json::from_str(json).map_err(|e| BlockRegistryError::Parse(e.to_string()))?;
+        BlockRegistry::with_definitions(document.blocks)
+    }
+
+    /** Loads a TOML document on top of the built in blocks */
+    pub fn from_toml(source: &str) -> Result<BlockRegistry, BlockRegistryError> {
+        let document: BlockDocument =
+            toml::from_str(source).map_err(|e| BlockRegistryError::Parse(e.to_string()))?;
+        BlockRegistry::with_definitions(document.blocks)
+    }
+
+    /**
+     * Starts from the built in blocks and applies the definitions.
+     * A definition with a built in id only changes the fields it gives, the rest keep their built in values.
+     */
+    pub fn with_definitions(
+        definitions: Vec<PartialBlockDefinition>,
+    ) -> Result<BlockRegistry, BlockRegistryError> {
+        let mut registry = BlockRegistry::builtin();
+        let mut seen_ids = Vec::new();
+        let mut seen_names = Vec::new();
+
+        for partial in definitions {
+            if partial.id == BlockType::Void as u8 {
+                return Err(BlockRegistryError::ReservedId(
+                    partial.name.unwrap_or_default(),
+                ));
+            }
+            let existing = registry.get(partial.id).cloned().unwrap_or_default();
+            let definition = partial.apply_to(existing);
+            if definition.light_emission > MAX_LIGHT || definition.light_filter > MAX_LIGHT {
+                return Err(BlockRegistryError::InvalidLightLevel(definition.name));
+            }
+            if seen_ids.contains(&definition.id) {
+                return Err(BlockRegistryError::DuplicateId(definition.id));
+            }
+            if seen_names.contains(&definition.name) {
+                return Err(BlockRegistryError::DuplicateName(definition.name));
+            }
+            // Taking the name of a built in block that isn't being replaced would make lookups ambiguous
+            if let Some(existing) = registry.get_by_name(&definition.name) {
+                if existing.id != definition.id {
+                    return Err(BlockRegistryError::DuplicateName(definition.name));
+                }
+            }
+
+            seen_ids.push(definition.id);
+            seen_names.push(definition.name.clone());
+            registry.insert(definition);
+        }
+
+        Ok(registry)
+    }
+
+    fn insert(&mut self, definition: BlockDefinition) {
+        let id = definition.id as usize;
+        self.metadata[id] = definition.get_metadata();
+        self.definitions[id] = Some(definition);
+    }
+
+    pub fn get(&self, id: u8) -> Option<&BlockDefinition> {
+        self.definitions[id as usize].as_ref()
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<&BlockDefinition> {
+        self.definitions
+            .iter()
+            .flatten()
+            .find(|definition| definition.name == name)
+    }
+
+    pub fn get_metadata(&self, block_type: BlockId) -> BlockMetaData {
+        self.metadata[block_type.to_index()]
+    }
+
+    pub fn get_properties(&self, block_type: BlockId) -> &[BlockProperty] {
+        self.get(block_type.into())
+            .map(|definition| definition.properties.as_slice())
+            .unwrap_or_default()
+    }
+
+    /** Packs typed properties into block data, checking them against the block type's declared properties */
+    pub fn make_block_data(
+        &self,
+        block_type: BlockId,
+        properties: &BlockStateProperties,
+    ) -> Result<BlockData, BlockStateError> {
+        BlockState::from_properties(properties, self.get_properties(block_type))
+            .map(BlockData::from_state)
+    }
+
+    pub fn read_block_data(
+        &self,
+        block_type: BlockId,
+        block_data: &BlockData,
+    ) -> BlockStateProperties {
+        block_data
+            .get_state()
+            .to_properties(self.get_properties(block_type))
+    }
+
+    pub fn definitions(&self) -> impl Iterator<Item = &BlockDefinition> {
+        self.definitions.iter().flatten()
+    }
+
+    /**
+     * Makes this the registry used by BlockMetaData::get_for_type.
+     * Worlds that are already loaded should be re-meshed and re-lit afterwards.
+     */
+    pub fn install(self) {
+        *INSTALLED.write().unwrap() = self;
+    }
+
+    /** Runs f with the installed registry */
+    pub fn with_installed<T>(f: impl FnOnce(&BlockRegistry) -> T) -> T {
+        f(&INSTALLED.read().unwrap())
+    }
+}
+
+/**
+ * The blocks the game ships with, one for every BlockType.
+ * Loaded documents can change these or add blocks with new ids.
+ */
+fn builtin_definitions() -> Vec<BlockDefinition> {
+    vec![
+        BlockDefinition {
+            intangible: true,
+            transparent: true,
+            hardness: 0.0,
+            blast_resistance: 0.0,
+            light_filter: 0,
+            textures: BTreeMap::new(),
+            ..BlockDefinition::builtin("void", BlockType::Void)
+        },
+        BlockDefinition {
+            hardness: 1.5,
+            blast_resistance: 6.0,
+            ..BlockDefinition::builtin("stone", BlockType::Stone)
+        },
+        BlockDefinition {
+            hardness: 2.0,
+            blast_resistance: 2.0,
+            ..BlockDefinition::builtin("wood", BlockType::Wood)
+        },
+        BlockDefinition {
+            transparent: true,
+            hardness: 0.2,
+            blast_resistance: 0.2,
+            light_filter: 1,
+            properties: vec![BlockProperty::Distance, BlockProperty::Persistent],
+            ..BlockDefinition::builtin("leaf", BlockType::Leaf)
+        },
+        BlockDefinition {
+            hardness: 0.5,
+            blast_resistance: 0.5,
+            ..BlockDefinition::builtin("cloud", BlockType::Cloud)
+        },
+        BlockDefinition {
+            hardness: 3.0,
+            blast_resistance: 6.0,
+            ..BlockDefinition::builtin("gold", BlockType::Gold)
+        },
+        BlockDefinition {
+            shape: BlockShape::X,
+            transparent: true,
+            hardness: 0.0,
+            blast_resistance: 0.0,
+            light_filter: 0,
+            ..BlockDefinition::builtin("red_flower", BlockType::RedFlower)
+        },
+        BlockDefinition {
+            fluid: true,
+            intangible: true,
+            hardness: 100.0,
+            blast_resistance: 100.0,
+            light_filter: 2,
+            properties: vec![BlockProperty::Level],
+            ..BlockDefinition::builtin("water", BlockType::Water)
+        },
+        BlockDefinition {
+            hardness: 0.6,
+            blast_resistance: 0.6,
+            ..BlockDefinition::builtin("grass", BlockType::Grass)
+        },
+        BlockDefinition {
+            shape: BlockShape::Flat,
+            transparent: true,
+            hardness: 0.5,
+            blast_resistance: 0.5,
+            light_filter: 0,
+            ..BlockDefinition::builtin("image", BlockType::Image)
+        },
+        BlockDefinition {
+            hardness: 2.0,
+            blast_resistance: 3.0,
+            ..BlockDefinition::builtin("planks", BlockType::Planks)
+        },
+        BlockDefinition {
+            hardness: 1.0,
+            blast_resistance: 1.0,
+            ..BlockDefinition::builtin("red", BlockType::Red)
+        },
+        BlockDefinition {
+            shape: BlockShape::X,
+            transparent: true,
+            intangible: true,
+            hardness: 0.0,
+            blast_resistance: 0.0,
+            light_emission: 14,
+            light_filter: 0,
+            ..BlockDefinition::builtin("torch", BlockType::Torch)
+        },
+        BlockDefinition {
+            hardness: 0.3,
+            blast_resistance: 0.3,
+            light_emission: 15,
+            ..BlockDefinition::builtin("glowstone", BlockType::Glowstone)
+        },
+        BlockDefinition {
+            gravitable: true,
+            hardness: 0.5,
+            blast_resistance: 0.5,
+            ..BlockDefinition::builtin("sand", BlockType::Sand)
+        },
+        BlockDefinition {
+            gravitable: true,
+            hardness: 0.6,
+            blast_resistance: 0.6,
+            ..BlockDefinition::builtin("gravel", BlockType::Gravel)
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlockRegistry, BlockRegistryError};
+    use crate::block::{BlockShape, BlockType};
+
+    #[test]
+    fn builtin_registry_has_every_block_type() {
+        let registry = BlockRegistry::builtin();
+        for id in 0..=BlockType::Gravel as u8 {
+            assert!(registry.get(id).is_some(), "missing block {}", id);
+        }
+
+        let flower = registry.get_by_name("red_flower").unwrap();
+        assert_eq!(flower.id, BlockType::RedFlower as u8);
+        assert_eq!(registry.definitions().count(), 16);
+
+        let torch = registry.get_metadata(BlockType::Torch.into());
+        assert_eq!(torch.light_emission, 14);
+        assert!(torch.intangible);
+    }
+
+    #[test]
+    fn loads_json_and_toml_documents() {
+        let json = r#"{
+            "blocks": [
+                { "name": "stone", "id": 1, "hardness": 4.0, "textures": { "all": "cobble" } },
+                { "name": "lantern", "id": 40, "shape": "X", "transparent": true, "light_emission": 12, "light_filter": 0 }
+            ]
+        }"#;
+        let registry = BlockRegistry::from_json(json).unwrap();
+        let stone = registry.get(BlockType::Stone as u8).unwrap();
+        assert_eq!(stone.hardness, 4.0);
+        assert_eq!(stone.get_texture("top"), Some("cobble"));
+        // Fields that weren't given keep their built in values
+        assert_eq!(stone.blast_resistance, 6.0);
+        assert_eq!(stone.name, "stone");
+
+        let lantern = registry.get_by_name("lantern").unwrap();
+        assert_eq!(lantern.id, 40);
+        assert!(lantern.shape == BlockShape::X);
+        assert_eq!(lantern.get_metadata().light_emission, 12);
+
+        let toml = r#"
+            [[blocks]]
+            name = "red_sand"
+            id = 41
+            gravitable = true
+            hardness = 0.5
+
+            [blocks.textures]
+            top = "red_sand_top"
+            all = "red_sand"
+        "#;
+        let registry = BlockRegistry::from_toml(toml).unwrap();
+        let red_sand = registry.get(41).unwrap();
+        assert!(red_sand.gravitable);
+        assert_eq!(red_sand.get_texture("top"), Some("red_sand_top"));
+        assert_eq!(red_sand.get_texture("side"), Some("red_sand"));
+        assert!(registry.get_by_name("glowstone").is_some());
+    }
+
+    #[test]
+    fn rejects_bad_documents() {
+        let load = |json: &str| BlockRegistry::from_json(json).err();
+
+        assert!(matches!(
+            load(r#"{ "blocks": [{ "name": "a", "id": 30 }, { "name": "b", "id": 30 }] }"#),
+            Some(BlockRegistryError::DuplicateId(30))
+        ));
+        assert!(matches!(
+            load(r#"{ "blocks": [{ "name": "a", "id": 30 }, { "name": "a", "id": 31 }] }"#),
+            Some(BlockRegistryError::DuplicateName(_))
+        ));
+        // Only a definition with stone's id can take the name stone
+        assert!(matches!(
+            load(r#"{ "blocks": [{ "name": "stone", "id": 30 }] }"#),
+            Some(BlockRegistryError::DuplicateName(_))
+        ));
+        assert!(matches!(
+            load(r#"{ "blocks": [{ "name": "sun", "id": 30, "light_emission": 20 }] }"#),
+            Some(BlockRegistryError::InvalidLightLevel(_))
+        ));
+        assert!(matches!(
+            load(r#"{ "blocks": [{ "name": "air", "id": 0 }] }"#),
+            Some(BlockRegistryError::ReservedId(_))
+        ));
+        assert!(matches!(
+            load(r#"{ "blocks": [{ "name": 5 }] }"#),
+            Some(BlockRegistryError::Parse(_))
+        ));
+        // Without an id there is nothing to say which block it is
+        assert!(matches!(
+            load(r#"{ "blocks": [{ "name": "lantern", "light_emission": 12 }] }"#),
+            Some(BlockRegistryError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,42 @@
+use super::block_registry::{BlockDefinition, BlockRegistry};
+use serde_wasm_bindgen::{to_value, Error};
+use wasm_bindgen::prelude::*;
+
+/** Loads a JSON block document on top of the built in blocks and installs it */
+#[wasm_bindgen]
+pub fn load_block_registry_json_wasm(json: &str) -> Result<(), Error> {
+    BlockRegistry::from_json(json)
+        .map(BlockRegistry::install)
+        .map_err(|e| Error::new(format!("{}", e)))
+}
+
+/** Loads a TOML block document on top of the built in blocks and installs it */
+#[wasm_bindgen]
+pub fn load_block_registry_toml_wasm(source: &str) -> Result<(), Error> {
+    BlockRegistry::from_toml(source)
+        .map(BlockRegistry::install)
+        .map_err(|e| Error::new(format!("{}", e)))
+}
+
+#[wasm_bindgen]
+pub fn reset_block_registry_wasm() {
+    BlockRegistry::builtin().install();
+}
+
+#[wasm_bindgen]
+pub fn get_block_definition_wasm(id: u8) -> Result<JsValue, Error> {
+    BlockRegistry::with_installed(|registry| to_value(&registry.get(id)))
+}
+
+#[wasm_bindgen]
+pub fn get_block_definition_by_name_wasm(name: &str) -> Result<JsValue, Error> {
+    BlockRegistry::with_installed(|registry| to_value(&registry.get_by_name(name)))
+}
+
+#[wasm_bindgen]
+pub fn get_block_definitions_wasm() -> Result<JsValue, Error> {
+    BlockRegistry::with_installed(|registry| {
+        let definitions: Vec<&BlockDefinition> = registry.definitions().collect();
+        to_value(&definitions)
+    })
+}
@@ -0,0 +1,319 @@
+use crate::direction::Direction;
+use serde::{Deserialize, Serialize};
+use std::{convert::TryFrom, fmt};
+
+/**
+ * A property a block type can declare, like which way it faces or how grown it is.
+ * Every property has a fixed place in the packed state, so a state read back
+ * with a different set of declared properties still decodes the same values.
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockProperty {
+    /** A direction, for stairs, doors and the like */
+    Facing,
+    /** Top or bottom half, for slabs, stairs and doors */
+    Half,
+    Open,
+    /** 0 to 15, fluid levels */
+    Level,
+    /** 0 to 7, crop growth */
+    Age,
+    /** 0 to 7, how many blocks leaves are from the nearest log. 0 until it has been worked out */
+    Distance,
+    /** Leaves placed by players never decay */
+    Persistent,
+}
+
+pub const EVERY_PROPERTY: [BlockProperty; 7] = [
+    BlockProperty::Facing,
+    BlockProperty::Half,
+    BlockProperty::Open,
+    BlockProperty::Level,
+    BlockProperty::Age,
+    BlockProperty::Distance,
+    BlockProperty::Persistent,
+];
+
+impl BlockProperty {
+    fn offset(&self) -> u16 {
+        match self {
+            BlockProperty::Facing => 0,
+            BlockProperty::Half => 3,
+            BlockProperty::Open => 4,
+            BlockProperty::Level => 5,
+            BlockProperty::Age => 9,
+            BlockProperty::Distance => 12,
+            BlockProperty::Persistent => 15,
+        }
+    }
+
+    fn bits(&self) -> u16 {
+        match self {
+            BlockProperty::Facing => 3,
+            BlockProperty::Half => 1,
+            BlockProperty::Open => 1,
+            BlockProperty::Level => 4,
+            BlockProperty::Age => 3,
+            BlockProperty::Distance => 3,
+            BlockProperty::Persistent => 1,
+        }
+    }
+
+    pub fn max_value(&self) -> u8 {
+        match self {
+            BlockProperty::Facing => 5,
+            property => ((1 << property.bits()) - 1) as u8,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
+pub enum Half {
+    Bottom = 0,
+    Top = 1,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum BlockStateError {
+    /** The block type doesn't declare the property */
+    UndeclaredProperty(BlockProperty),
+    ValueOutOfRange(BlockProperty, u8),
+}
+
+impl std::error::Error for BlockStateError {}
+
+impl fmt::Display for BlockStateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BlockStateError::UndeclaredProperty(property) => {
+                write!(f, "Block doesn't have the {:?} property", property)
+            }
+            BlockStateError::ValueOutOfRange(property, value) => write!(
+                f,
+                "{} is out of range for {:?}, the max is {}",
+                value,
+                property,
+                property.max_value()
+            ),
+        }
+    }
+}
+
+/**
+ * The values of every property for one block, packed into 16 bits so that
+ * states can sit in chunk palettes next to the block type.
+ * Properties that haven't been set read as 0.
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default, Serialize, Deserialize)]
+#[serde(try_from = "u16", into = "u16")]
+pub struct BlockState(u16);
+
+impl TryFrom<u16> for BlockState {
+    type Error = BlockStateError;
+
+    /** Checks every property is in range, since a facing field can hold values that aren't directions */
+    fn try_from(bits: u16) -> Result<Self, Self::Error> {
+        let state = BlockState(bits);
+        for property in EVERY_PROPERTY {
+            let value = state.get(property);
+            if value > property.max_value() {
+                return Err(BlockStateError::ValueOutOfRange(property, value));
+            }
+        }
+        Ok(state)
+    }
+}
+
+impl From<BlockState> for u16 {
+    fn from(state: BlockState) -> Self {
+        state.0
+    }
+}
+
+impl BlockState {
+    pub fn to_bits(&self) -> u16 {
+        self.0
+    }
+
+    pub fn get(&self, property: BlockProperty) -> u8 {
+        let mask = (1 << property.bits()) - 1;
+        ((self.0 >> property.offset()) & mask) as u8
+    }
+
+    pub fn with(&self, property: BlockProperty, value: u8) -> Result<BlockState, BlockStateError> {
+        if value > property.max_value() {
+            return Err(BlockStateError::ValueOutOfRange(property, value));
+        }
+        let mask = ((1 << property.bits()) - 1) << property.offset();
+        Ok(BlockState(
+            (self.0 & !mask) | ((value as u16) << property.offset()),
+        ))
+    }
+
+    pub fn get_facing(&self) -> Direction {
+        Direction::from_index(self.get(BlockProperty::Facing) as usize)
+    }
+
+    pub fn get_half(&self) -> Half {
+        match self.get(BlockProperty::Half) {
+            0 => Half::Bottom,
+            _ => Half::Top,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.get(BlockProperty::Open) == 1
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        self.get(BlockProperty::Persistent) == 1
+    }
+
+    /**
+     * Packs typed property values, checking each one is declared for the block type.
+     * Declared properties that aren't given are left at 0.
+     */
+    pub fn from_properties(
+        properties: &BlockStateProperties,
+        declared: &[BlockProperty],
+    ) -> Result<BlockState, BlockStateError> {
+        let mut state = BlockState::default();
+        for (property, value) in properties.values() {
+            if !declared.contains(&property) {
+                return Err(BlockStateError::UndeclaredProperty(property));
+            }
+            state = state.with(property, value)?;
+        }
+        Ok(state)
+    }
+
+    /** Unpacks the declared properties into typed values */
+    pub fn to_properties(&self, declared: &[BlockProperty]) -> BlockStateProperties {
+        let has = |property| declared.contains(&property);
+        BlockStateProperties {
+            facing: has(BlockProperty::Facing).then(|| self.get_facing()),
+            half: has(BlockProperty::Half).then(|| self.get_half()),
+            open: has(BlockProperty::Open).then(|| self.is_open()),
+            level: has(BlockProperty::Level).then(|| self.get(BlockProperty::Level)),
+            age: has(BlockProperty::Age).then(|| self.get(BlockProperty::Age)),
+            distance: has(BlockProperty::Distance).then(|| self.get(BlockProperty::Distance)),
+            persistent: has(BlockProperty::Persistent).then(|| self.is_persistent()),
+        }
+    }
+}
+
+/** Typed property values, what JS sends and gets back. Missing properties are None */
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct BlockStateProperties {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub facing: Option<Direction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub half: Option<Half>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distance: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persistent: Option<bool>,
+}
+
+impl BlockStateProperties {
+    fn values(&self) -> Vec<(BlockProperty, u8)> {
+        vec![
+            (
+                BlockProperty::Facing,
+                self.facing.map(|d| d.to_index() as u8),
+            ),
+            (BlockProperty::Half, self.half.map(|h| h as u8)),
+            (BlockProperty::Open, self.open.map(|o| o as u8)),
+            (BlockProperty::Level, self.level),
+            (BlockProperty::Age, self.age),
+            (BlockProperty::Distance, self.distance),
+            (BlockProperty::Persistent, self.persistent.map(|p| p as u8)),
+        ]
+        .into_iter()
+        .filter_map(|(property, value)| value.map(|v| (property, v)))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlockProperty, BlockState, BlockStateError, BlockStateProperties, Half};
+    use crate::direction::Direction;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn properties_dont_overlap() {
+        let state = BlockState::default()
+            .with(BlockProperty::Facing, 5)
+            .and_then(|s| s.with(BlockProperty::Half, 1))
+            .and_then(|s| s.with(BlockProperty::Open, 1))
+            .and_then(|s| s.with(BlockProperty::Level, 15))
+            .and_then(|s| s.with(BlockProperty::Age, 7))
+            .and_then(|s| s.with(BlockProperty::Distance, 6))
+            .and_then(|s| s.with(BlockProperty::Persistent, 1))
+            .unwrap();
+
+        assert_eq!(state.get_facing(), Direction::West);
+        assert_eq!(state.get_half(), Half::Top);
+        assert!(state.is_open());
+        assert_eq!(state.get(BlockProperty::Level), 15);
+        assert_eq!(state.get(BlockProperty::Age), 7);
+        assert_eq!(state.get(BlockProperty::Distance), 6);
+        assert!(state.is_persistent());
+
+        let state = state.with(BlockProperty::Level, 3).unwrap();
+        assert_eq!(state.get(BlockProperty::Level), 3);
+        assert_eq!(state.get(BlockProperty::Age), 7);
+        assert_eq!(
+            state.with(BlockProperty::Age, 8),
+            Err(BlockStateError::ValueOutOfRange(BlockProperty::Age, 8))
+        );
+    }
+
+    #[test]
+    fn rejects_packed_facings_that_are_not_directions() {
+        let west = BlockState::default()
+            .with(BlockProperty::Facing, Direction::West.to_index() as u8)
+            .unwrap();
+        assert_eq!(BlockState::try_from(west.to_bits()), Ok(west));
+        assert_eq!(
+            BlockState::try_from(6),
+            Err(BlockStateError::ValueOutOfRange(BlockProperty::Facing, 6))
+        );
+        assert!(serde_json::from_str::<BlockState>("7").is_err());
+        assert_eq!(
+            serde_json::from_str::<BlockState>("5")
+                .unwrap()
+                .get(BlockProperty::Facing),
+            5
+        );
+    }
+
+    #[test]
+    fn converts_typed_properties() {
+        let declared = [BlockProperty::Facing, BlockProperty::Half];
+        let properties = BlockStateProperties {
+            facing: Some(Direction::East),
+            half: Some(Half::Top),
+            ..Default::default()
+        };
+        let state = BlockState::from_properties(&properties, &declared).unwrap();
+        assert_eq!(state.to_properties(&declared), properties);
+
+        let undeclared = BlockStateProperties {
+            age: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            BlockState::from_properties(&undeclared, &declared),
+            Err(BlockStateError::UndeclaredProperty(BlockProperty::Age))
+        );
+    }
+}
@@ -0,0 +1,40 @@
+use super::{
+    block_registry::BlockRegistry,
+    block_state::{BlockProperty, BlockStateProperties},
+    BlockData, BlockId,
+};
+use serde_wasm_bindgen::{from_value, to_value, Error};
+use std::convert::TryFrom;
+use wasm_bindgen::prelude::*;
+
+fn to_block_id(block_id: u8) -> Result<BlockId, Error> {
+    BlockId::try_from(block_id).map_err(|e| Error::new(format!("{}", e)))
+}
+
+/** Turns typed properties like `{ facing: "North", open: true }` into block data for a block */
+#[wasm_bindgen]
+pub fn make_block_data_wasm(block_id: u8, properties: JsValue) -> Result<JsValue, Error> {
+    let block_type = to_block_id(block_id)?;
+    let properties: BlockStateProperties = from_value(properties)?;
+    BlockRegistry::with_installed(|registry| registry.make_block_data(block_type, &properties))
+        .map_err(|e| Error::new(format!("{}", e)))
+        .and_then(|block_data| to_value(&block_data))
+}
+
+/** Reads the typed properties out of a block's data */
+#[wasm_bindgen]
+pub fn read_block_data_wasm(block_id: u8, block_data: JsValue) -> Result<JsValue, Error> {
+    let block_type = to_block_id(block_id)?;
+    let block_data: BlockData = from_value(block_data)?;
+    let properties =
+        BlockRegistry::with_installed(|registry| registry.read_block_data(block_type, &block_data));
+    to_value(&properties)
+}
+
+#[wasm_bindgen]
+pub fn get_block_properties_wasm(block_id: u8) -> Result<JsValue, Error> {
+    let block_type = to_block_id(block_id)?;
+    let properties: Vec<BlockProperty> =
+        BlockRegistry::with_installed(|registry| registry.get_properties(block_type).to_vec());
+    to_value(&properties)
+}
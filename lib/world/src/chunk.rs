@@ -1,13 +1,21 @@
-use crate::block::{BlockData, BlockType, ChunkBlock};
+use crate::block::{BlockData, BlockId, ChunkBlock};
 use crate::positions::{ChunkPos, InnerChunkPos};
 use crate::world::world_block::WorldBlock;
 use phf::set;
 use serde::{Deserialize, Serialize};
-use serde_big_array::BigArray;
 use wasm_bindgen::prelude::*;
 
+use self::chunk_light::{LightChannel, LightLevel, LightSection};
+use self::chunk_section::ChunkSection;
+use self::chunk_storage::BlockEntry;
+
+pub mod chunk_binary;
 mod chunk_duct;
+pub mod chunk_geometry;
+pub mod chunk_light;
 pub mod chunk_mesh;
+pub mod chunk_section;
+pub mod chunk_storage;
 #[cfg(test)]
 mod chunk_unit_tests;
 
@@ -21,26 +29,25 @@ interface ITextStyle {
 "#;
 
 pub const CHUNK_WIDTH: i16 = 16;
-pub const CHUNK_HEIGHT: i16 = 64;
-
-const CHUNK_MEM_SIZE: usize = (CHUNK_HEIGHT * CHUNK_WIDTH * CHUNK_WIDTH) as usize;
-
-type BlockDataArray = [BlockData; CHUNK_MEM_SIZE];
-
-fn default_block_data() -> BlockDataArray {
-    [BlockData::None; CHUNK_MEM_SIZE]
-}
+/** Chunks are split vertically into sections of this height */
+pub const SECTION_HEIGHT: i16 = 16;
+pub const DEFAULT_WORLD_HEIGHT: i16 = 64;
+/** Inner chunk positions store y in a u8, so worlds can't be taller than this */
+pub const MAX_WORLD_HEIGHT: i16 = 256;
 
 #[derive(Serialize, Deserialize)]
 #[wasm_bindgen]
 pub struct Chunk {
-    #[serde(with = "BigArray")]
-    blocks: [BlockType; CHUNK_MEM_SIZE],
+    /** Indexed by section y. None until a block is placed in the section */
+    #[serde(with = "chunk_section::sections")]
+    sections: Vec<Option<ChunkSection>>,
 
-    // #[serde(with = "BigArray")]
+    /**
+     * Indexed by section y like the blocks. None means the whole section is lit by the sky.
+     * Light isn't saved, it is recalculated whenever the chunk is inserted into a world.
+     */
     #[serde(skip)]
-    #[serde(default = "default_block_data")]
-    block_data: BlockDataArray,
+    light: Vec<Option<LightSection>>,
 
     #[wasm_bindgen(skip)]
     pub position: ChunkPos,
@@ -53,32 +60,36 @@ pub struct Chunk {
 
 impl Default for Chunk {
     fn default() -> Self {
-        Chunk {
-            blocks: [BlockType::Void; CHUNK_MEM_SIZE],
-            block_data: [BlockData::None; CHUNK_MEM_SIZE],
-            position: ChunkPos { x: 0, y: 0 },
-            dirty_blocks: Vec::new(),
-        }
+        Chunk::new(ChunkPos { x: 0, y: 0 })
     }
 }
 
 impl Chunk {
     pub fn new(position: ChunkPos) -> Chunk {
         Chunk {
-            blocks: [BlockType::Void; CHUNK_MEM_SIZE],
-            block_data: [BlockData::None; CHUNK_MEM_SIZE],
+            sections: Vec::new(),
+            light: Vec::new(),
             position,
             dirty_blocks: Vec::new(),
         }
     }
 
     pub fn get_all_blocks(&self) -> Vec<ChunkBlock> {
-        self.blocks
+        self.sections
             .iter()
             .enumerate()
-            .filter(|(_i, &b)| b != BlockType::Void)
-            .map(|(index, _block_type)| {
-                self.get_block(&InnerChunkPos::make_from_chunk_index(index))
+            .filter_map(|(section_y, section)| section.as_ref().map(|s| (section_y, s)))
+            .flat_map(|(section_y, section)| {
+                section
+                    .blocks
+                    .iter()
+                    .enumerate()
+                    .filter(|(_i, (b, _))| *b != BlockId::VOID)
+                    .map(move |(index, (block_type, extra_data))| ChunkBlock {
+                        block_type,
+                        extra_data,
+                        pos: InnerChunkPos::make_from_section_index(section_y, index),
+                    })
             })
             .collect()
     }
@@ -98,71 +109,117 @@ impl Chunk {
         self.position.to_index()
     }
 
-    pub fn add_block(&mut self, block: ChunkBlock) {
-        let index = block.pos.to_chunk_index();
-        self.blocks[index] = block.block_type;
-        self.block_data[index] = block.extra_data;
-        self.dirty_blocks.push(block.pos.clone());
+    /** How many sections are allocated, counting empty ones below the highest allocated section */
+    pub fn get_section_count(&self) -> usize {
+        self.sections.len()
     }
 
-    fn get_block_type_at_index(&self, index: usize) -> BlockType {
-        match self.blocks.get(index) {
-            Some(block) => *block,
-            None => BlockType::Void,
-        }
+    pub fn has_section(&self, section_y: usize) -> bool {
+        matches!(self.sections.get(section_y), Some(Some(_)))
     }
 
-    fn get_block_data_from_index(&self, index: &usize) -> &BlockData {
-        match self.block_data.get(*index) {
-            Some(block_data) => block_data,
-            None => &BlockData::None,
+    fn get_entry(&self, pos: &InnerChunkPos) -> BlockEntry {
+        match self.sections.get(pos.get_section()) {
+            Some(Some(section)) => section.blocks.get(pos.to_section_index()),
+            _ => (BlockId::VOID, BlockData::None),
         }
     }
 
-    fn get_world_block_from_index(&self, index: usize) -> WorldBlock {
-        let block_type = self.get_block_type_at_index(index);
-        let block_data = self.get_block_data_from_index(&index);
-        let inner_chunk_pos = InnerChunkPos::make_from_chunk_index(index);
+    fn set_entry(&mut self, pos: &InnerChunkPos, entry: BlockEntry) {
+        let section_y = pos.get_section();
+        if section_y >= self.sections.len() {
+            // Don't allocate a section just to store void
+            if entry.0 == BlockId::VOID {
+                return;
+            }
+            self.sections.resize_with(section_y + 1, || None);
+        }
 
-        WorldBlock {
-            block_type,
-            extra_data: *block_data,
-            world_pos: inner_chunk_pos.to_world_pos(&self.position),
+        let section = &mut self.sections[section_y];
+        if section.is_none() && entry.0 == BlockId::VOID {
+            return;
         }
+
+        section
+            .get_or_insert_with(ChunkSection::default)
+            .blocks
+            .set(pos.to_section_index(), entry);
     }
 
-    fn get_block_type(&self, pos: &InnerChunkPos) -> BlockType {
-        self.get_block_type_at_index(pos.to_chunk_index())
+    pub fn add_block(&mut self, block: ChunkBlock) {
+        self.set_entry(&block.pos, (block.block_type, block.extra_data));
+        self.dirty_blocks.push(block.pos.clone());
+    }
+
+    fn get_block_type(&self, pos: &InnerChunkPos) -> BlockId {
+        self.get_entry(pos).0
     }
 
     pub fn get_world_block(&self, pos: &InnerChunkPos) -> WorldBlock {
-        self.get_world_block_from_index(pos.to_chunk_index())
+        let (block_type, block_data) = self.get_entry(pos);
+
+        WorldBlock {
+            block_type,
+            extra_data: block_data,
+            world_pos: pos.to_world_pos(&self.position),
+        }
     }
 
     pub fn has_block(&self, pos: &InnerChunkPos) -> bool {
-        self.get_block_type(pos) != BlockType::Void
+        self.get_block_type(pos) != BlockId::VOID
     }
 
     pub fn get_block(&self, pos: &InnerChunkPos) -> ChunkBlock {
-        let index = pos.to_chunk_index();
-        let block_type = self.get_block_type_at_index(index);
-        let block_data = self.get_block_data_from_index(&index);
-        let inner_chunk_pos = InnerChunkPos::make_from_chunk_index(index);
+        let (block_type, block_data) = self.get_entry(pos);
 
         ChunkBlock {
             block_type,
-            extra_data: *block_data,
-            pos: inner_chunk_pos,
+            extra_data: block_data,
+            pos: *pos,
         }
     }
 
     pub fn remove_block(&mut self, pos: &InnerChunkPos) -> () {
-        let index = pos.to_chunk_index();
-        self.blocks[index] = BlockType::Void;
-        self.block_data[index] = BlockData::None;
+        self.set_entry(pos, (BlockId::VOID, BlockData::None));
         self.dirty_blocks.push(pos.clone());
     }
 
+    pub fn get_light(&self, pos: &InnerChunkPos) -> LightLevel {
+        match self.light.get(pos.get_section()) {
+            Some(Some(section)) => section.get(pos.to_section_index()),
+            _ => LightLevel::FULL_SKY,
+        }
+    }
+
+    pub fn set_light(&mut self, pos: &InnerChunkPos, channel: LightChannel, level: u8) {
+        let section_y = pos.get_section();
+        if section_y >= self.light.len() {
+            self.light.resize_with(section_y + 1, || None);
+        }
+        self.light[section_y]
+            .get_or_insert_with(|| LightSection::filled(LightLevel::FULL_SKY))
+            .set(pos.to_section_index(), channel, level);
+    }
+
+    /** Makes every section that has blocks dark, ready for the light to be recalculated */
+    pub fn reset_light(&mut self) {
+        self.light = (0..self.sections.len())
+            .map(|_| Some(LightSection::filled(LightLevel::DARK)))
+            .collect();
+    }
+
+    /** How many sections from the bottom have their own light stored */
+    pub fn get_light_section_count(&self) -> usize {
+        self.light.len()
+    }
+
+    /** Drops every section (and its light) at or above the given height */
+    pub fn truncate_to_height(&mut self, height: i16) {
+        let section_count = (height / SECTION_HEIGHT) as usize;
+        self.sections.truncate(section_count);
+        self.light.truncate(section_count);
+    }
+
     pub fn clean(&mut self) {
         self.dirty_blocks.clear();
     }
@@ -0,0 +1,587 @@
+use super::{
+    chunk_section::{ChunkSection, SECTION_MEM_SIZE},
+    Chunk, MAX_WORLD_HEIGHT, SECTION_HEIGHT,
+};
+use crate::{
+    block::{
+        block_registry::BlockRegistry, block_state::BlockState, BlockData, BlockId, ChunkBlock,
+    },
+    direction::Direction,
+    positions::{ChunkPos, InnerChunkPos},
+};
+use std::{convert::TryFrom, fmt};
+
+/**
+ * Binary layout of a chunk (all numbers little endian):
+ *
+ * header:     magic "TCCK", version u8, chunk x i16, chunk y i16
+ * sections:   section count u8, then per section a present flag u8 followed,
+ *             if the section is present, by its blocks
+ *
+ * Blocks are written as:
+ *
+ * palette:    entry count u16, then one block type id (u8) per entry
+ * blocks:     run count u32, then (palette index u8, run length u16) per run
+ * block data: entry count u32, then (block index u16, tag u8, payload) per entry
+ *
+ * Only blocks with data other than `BlockData::None` are written to the block data section.
+ * Tag 1 is an image with a direction u8 payload, tag 2 is a block state with a u16 payload.
+ *
+ * Version 1 had no sections and wrote the blocks of a 16x64x16 chunk directly after the header.
+ */
+pub const CHUNK_FORMAT_VERSION: u8 = 2;
+
+/** Block count of a version 1 chunk, which was always 64 blocks tall */
+const V1_BLOCK_COUNT: usize = 16 * 64 * 16;
+
+const MAGIC: &[u8; 4] = b"TCCK";
+
+const BLOCK_DATA_TAG_IMAGE: u8 = 1;
+const BLOCK_DATA_TAG_STATE: u8 = 2;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ChunkDecodeError {
+    BadMagic,
+    UnsupportedVersion(u8),
+    UnexpectedEnd,
+    UnknownBlockType(u8),
+    UnknownBlockDataTag(u8),
+    UnknownDirection(u8),
+    InvalidBlockState(u16),
+    PaletteIndexOutOfBounds(u8),
+    BlockIndexOutOfBounds(usize),
+    WrongBlockCount { expected: usize, got: usize },
+    TooManySections(u8),
+}
+
+impl std::error::Error for ChunkDecodeError {}
+
+impl fmt::Display for ChunkDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChunkDecodeError::BadMagic => write!(f, "Not a chunk"),
+            ChunkDecodeError::UnsupportedVersion(v) => {
+                write!(f, "Unsupported chunk format version {}", v)
+            }
+            ChunkDecodeError::UnexpectedEnd => write!(f, "Chunk data ended early"),
+            ChunkDecodeError::UnknownBlockType(id) => write!(f, "Unknown block type {}", id),
+            ChunkDecodeError::UnknownBlockDataTag(tag) => {
+                write!(f, "Unknown block data tag {}", tag)
+            }
+            ChunkDecodeError::UnknownDirection(dir) => write!(f, "Unknown direction {}", dir),
+            ChunkDecodeError::InvalidBlockState(bits) => {
+                write!(f, "Block state {} has a property out of range", bits)
+            }
+            ChunkDecodeError::PaletteIndexOutOfBounds(i) => {
+                write!(f, "Palette index {} out of bounds", i)
+            }
+            ChunkDecodeError::BlockIndexOutOfBounds(i) => {
+                write!(f, "Block index {} out of bounds", i)
+            }
+            ChunkDecodeError::WrongBlockCount { expected, got } => {
+                write!(f, "Expected {} blocks, got {}", expected, got)
+            }
+            ChunkDecodeError::TooManySections(count) => {
+                write!(
+                    f,
+                    "Chunk has {} sections, which is taller than any world",
+                    count
+                )
+            }
+        }
+    }
+}
+
+fn direction_from_u8(index: u8) -> Result<Direction, ChunkDecodeError> {
+    match index {
+        0..=5 => Ok(Direction::from_index(index as usize)),
+        _ => Err(ChunkDecodeError::UnknownDirection(index)),
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(bytes: &'a [u8]) -> ByteReader<'a> {
+        ByteReader { bytes, cursor: 0 }
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], ChunkDecodeError> {
+        let end = self.cursor + N;
+        let slice = self
+            .bytes
+            .get(self.cursor..end)
+            .ok_or(ChunkDecodeError::UnexpectedEnd)?;
+        self.cursor = end;
+        let mut out = [0; N];
+        out.copy_from_slice(slice);
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> Result<u8, ChunkDecodeError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, ChunkDecodeError> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn i16(&mut self) -> Result<i16, ChunkDecodeError> {
+        Ok(i16::from_le_bytes(self.take()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, ChunkDecodeError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+}
+
+fn write_block_data(out: &mut Vec<u8>, block_data: &BlockData) {
+    match block_data {
+        BlockData::None => {}
+        BlockData::Image(direction) => {
+            out.push(BLOCK_DATA_TAG_IMAGE);
+            out.push(direction.to_index() as u8);
+        }
+        BlockData::State(state) => {
+            out.push(BLOCK_DATA_TAG_STATE);
+            out.extend_from_slice(&state.to_bits().to_le_bytes());
+        }
+    }
+}
+
+fn read_block_data(reader: &mut ByteReader) -> Result<BlockData, ChunkDecodeError> {
+    match reader.u8()? {
+        BLOCK_DATA_TAG_IMAGE => Ok(BlockData::Image(direction_from_u8(reader.u8()?)?)),
+        BLOCK_DATA_TAG_STATE => {
+            let bits = reader.u16()?;
+            BlockState::try_from(bits)
+                .map(BlockData::State)
+                .map_err(|_| ChunkDecodeError::InvalidBlockState(bits))
+        }
+        tag => Err(ChunkDecodeError::UnknownBlockDataTag(tag)),
+    }
+}
+
+/** Writes the palette, runs and block data for a list of blocks */
+fn write_blocks(out: &mut Vec<u8>, blocks: impl Iterator<Item = (BlockId, BlockData)>) {
+    // Palette, in order of first appearance
+    let mut palette: Vec<BlockId> = Vec::new();
+    let mut runs: Vec<(u8, u16)> = Vec::new();
+    let mut block_data: Vec<(usize, BlockData)> = Vec::new();
+    for (index, (block_type, data)) in blocks.enumerate() {
+        let palette_index = match palette.iter().position(|b| *b == block_type) {
+            Some(index) => index,
+            None => {
+                palette.push(block_type);
+                palette.len() - 1
+            }
+        } as u8;
+
+        match runs.last_mut() {
+            Some((index, length)) if *index == palette_index && *length < u16::MAX => *length += 1,
+            _ => runs.push((palette_index, 1)),
+        }
+
+        if data != BlockData::None {
+            block_data.push((index, data));
+        }
+    }
+
+    out.extend_from_slice(&(palette.len() as u16).to_le_bytes());
+    out.extend(palette.iter().map(|block_type| u8::from(*block_type)));
+
+    out.extend_from_slice(&(runs.len() as u32).to_le_bytes());
+    for (palette_index, length) in runs {
+        out.push(palette_index);
+        out.extend_from_slice(&length.to_le_bytes());
+    }
+
+    out.extend_from_slice(&(block_data.len() as u32).to_le_bytes());
+    for (index, data) in block_data {
+        out.extend_from_slice(&(index as u16).to_le_bytes());
+        write_block_data(out, &data);
+    }
+}
+
+/**
+ * Reads `block_count` blocks written by `write_blocks`.
+ * Only the non void blocks are passed to `set`, along with their index.
+ */
+fn read_blocks(
+    reader: &mut ByteReader,
+    registry: &BlockRegistry,
+    block_count: usize,
+    mut set: impl FnMut(usize, BlockId, BlockData),
+) -> Result<(), ChunkDecodeError> {
+    let palette_len = reader.u16()?;
+    let palette_ids = (0..palette_len)
+        .map(|_| reader.u8())
+        .collect::<Result<Vec<u8>, ChunkDecodeError>>()?;
+    // Any id the registry defines can be stored, including blocks loaded from a document
+    let palette = palette_ids
+        .iter()
+        .map(|id| BlockId::new(*id, registry).map_err(|e| ChunkDecodeError::UnknownBlockType(e.0)))
+        .collect::<Result<Vec<BlockId>, ChunkDecodeError>>()?;
+
+    let mut block_types = Vec::with_capacity(block_count);
+    let run_count = reader.u32()?;
+    for _ in 0..run_count {
+        let palette_index = reader.u8()?;
+        let length = reader.u16()? as usize;
+        let block_type = *palette
+            .get(palette_index as usize)
+            .ok_or(ChunkDecodeError::PaletteIndexOutOfBounds(palette_index))?;
+        if block_types.len() + length > block_count {
+            return Err(ChunkDecodeError::WrongBlockCount {
+                expected: block_count,
+                got: block_types.len() + length,
+            });
+        }
+        block_types.extend(std::iter::repeat_n(block_type, length));
+    }
+    if block_types.len() != block_count {
+        return Err(ChunkDecodeError::WrongBlockCount {
+            expected: block_count,
+            got: block_types.len(),
+        });
+    }
+
+    let mut block_data = vec![];
+    let block_data_count = reader.u32()?;
+    for _ in 0..block_data_count {
+        let index = reader.u16()? as usize;
+        let data = read_block_data(reader)?;
+        if index >= block_count {
+            return Err(ChunkDecodeError::BlockIndexOutOfBounds(index));
+        }
+        block_data.push((index, data));
+    }
+
+    for (index, block_type) in block_types.iter().enumerate() {
+        if *block_type != BlockId::VOID {
+            set(index, *block_type, BlockData::None);
+        }
+    }
+    for (index, data) in block_data {
+        set(index, block_types[index], data);
+    }
+
+    Ok(())
+}
+
+impl Chunk {
+    /** Encodes the chunk in the versioned binary chunk format */
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(CHUNK_FORMAT_VERSION);
+        out.extend_from_slice(&self.position.x.to_le_bytes());
+        out.extend_from_slice(&self.position.y.to_le_bytes());
+
+        out.push(self.sections.len() as u8);
+        for section in self.sections.iter() {
+            match section {
+                Some(section) => {
+                    out.push(1);
+                    write_blocks(&mut out, section.blocks.iter());
+                }
+                None => out.push(0),
+            }
+        }
+
+        out
+    }
+
+    /** Decodes a chunk written by `to_bytes`, including ones written by older format versions */
+    pub fn from_bytes(bytes: &[u8]) -> Result<Chunk, ChunkDecodeError> {
+        BlockRegistry::with_installed(|registry| Self::from_bytes_with_registry(bytes, registry))
+    }
+
+    /** Decodes a chunk, only accepting the block ids the given registry defines */
+    pub fn from_bytes_with_registry(
+        bytes: &[u8],
+        registry: &BlockRegistry,
+    ) -> Result<Chunk, ChunkDecodeError> {
+        let mut reader = ByteReader::new(bytes);
+        if &reader.take::<4>()? != MAGIC {
+            return Err(ChunkDecodeError::BadMagic);
+        }
+
+        match reader.u8()? {
+            1 => Self::decode_v1(&mut reader, registry),
+            2 => Self::decode_v2(&mut reader, registry),
+            version => Err(ChunkDecodeError::UnsupportedVersion(version)),
+        }
+    }
+
+    fn decode_v1(
+        reader: &mut ByteReader,
+        registry: &BlockRegistry,
+    ) -> Result<Chunk, ChunkDecodeError> {
+        let x = reader.i16()?;
+        let y = reader.i16()?;
+        let mut chunk = Chunk::new(ChunkPos { x, y });
+
+        // Version 1 used 6 bits for y in the block index
+        read_blocks(
+            reader,
+            registry,
+            V1_BLOCK_COUNT,
+            |index, block_type, extra_data| {
+                let pos = InnerChunkPos::new(
+                    (index >> 10) as u8,
+                    ((index >> 4) & 0b111111) as u8,
+                    (index & 0b1111) as u8,
+                );
+                chunk.add_block(ChunkBlock {
+                    pos,
+                    block_type,
+                    extra_data,
+                });
+            },
+        )?;
+        chunk.clean();
+
+        Ok(chunk)
+    }
+
+    fn decode_v2(
+        reader: &mut ByteReader,
+        registry: &BlockRegistry,
+    ) -> Result<Chunk, ChunkDecodeError> {
+        let x = reader.i16()?;
+        let y = reader.i16()?;
+        let mut chunk = Chunk::new(ChunkPos { x, y });
+
+        let section_count = reader.u8()?;
+        if section_count as i16 > MAX_WORLD_HEIGHT / SECTION_HEIGHT {
+            return Err(ChunkDecodeError::TooManySections(section_count));
+        }
+        chunk.sections.resize_with(section_count as usize, || None);
+
+        for section_y in 0..section_count as usize {
+            if reader.u8()? == 0 {
+                continue;
+            }
+            let mut section = ChunkSection::default();
+            read_blocks(
+                reader,
+                registry,
+                SECTION_MEM_SIZE,
+                |index, block_type, data| {
+                    section.blocks.set(index, (block_type, data));
+                },
+            )?;
+            chunk.sections[section_y] = Some(section);
+        }
+
+        Ok(chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChunkDecodeError, CHUNK_FORMAT_VERSION, MAGIC, V1_BLOCK_COUNT};
+    use crate::{
+        block::{
+            block_registry::BlockRegistry,
+            block_state::{BlockProperty, BlockState},
+            BlockData, BlockId, BlockType, ChunkBlock,
+        },
+        chunk::Chunk,
+        direction::Direction,
+        positions::{ChunkPos, InnerChunkPos},
+    };
+
+    fn assert_same_chunk(a: &Chunk, b: &Chunk) {
+        assert_eq!(a.position, b.position);
+        assert_eq!(a.get_all_blocks(), b.get_all_blocks());
+    }
+
+    #[test]
+    fn round_trips_empty_chunk() {
+        let chunk = Chunk::new(ChunkPos::new(-3, 7));
+        let bytes = chunk.to_bytes();
+        let decoded = Chunk::from_bytes(&bytes).unwrap();
+        assert_same_chunk(&chunk, &decoded);
+        // A single run of void should be tiny
+        assert!(bytes.len() < 32);
+    }
+
+    #[test]
+    fn round_trips_blocks_and_block_data() {
+        let mut chunk = Chunk::new(ChunkPos::new(2, -1));
+        for x in 0..16 {
+            for z in 0..16 {
+                chunk.add_block(ChunkBlock {
+                    pos: InnerChunkPos::new(x, 0, z),
+                    block_type: BlockType::Stone.into(),
+                    extra_data: BlockData::None,
+                });
+            }
+        }
+        chunk.add_block(ChunkBlock {
+            pos: InnerChunkPos::new(4, 1, 4),
+            block_type: BlockType::Image.into(),
+            extra_data: BlockData::Image(Direction::West),
+        });
+        chunk.add_block(ChunkBlock {
+            pos: InnerChunkPos::new(15, 63, 15),
+            block_type: BlockType::Cloud.into(),
+            extra_data: BlockData::None,
+        });
+        let state = BlockState::default().with(BlockProperty::Level, 9).unwrap();
+        chunk.add_block(ChunkBlock {
+            pos: InnerChunkPos::new(6, 1, 6),
+            block_type: BlockType::Water.into(),
+            extra_data: BlockData::State(state),
+        });
+
+        let decoded = Chunk::from_bytes(&chunk.to_bytes()).unwrap();
+
+        assert_same_chunk(&chunk, &decoded);
+        assert_eq!(
+            decoded.get_block(&InnerChunkPos::new(4, 1, 4)).extra_data,
+            BlockData::Image(Direction::West)
+        );
+        assert_eq!(
+            decoded.get_block(&InnerChunkPos::new(6, 1, 6)).extra_data,
+            BlockData::State(state)
+        );
+    }
+
+    #[test]
+    fn round_trips_tall_chunks() {
+        let mut chunk = Chunk::new(ChunkPos::new(0, 0));
+        chunk.add_block(ChunkBlock {
+            pos: InnerChunkPos::new(3, 250, 9),
+            block_type: BlockType::Gold.into(),
+            extra_data: BlockData::None,
+        });
+
+        let bytes = chunk.to_bytes();
+        let decoded = Chunk::from_bytes(&bytes).unwrap();
+
+        assert_same_chunk(&chunk, &decoded);
+        assert_eq!(decoded.get_section_count(), 16);
+        assert!(decoded.has_section(15));
+        // Only the top section is written out
+        assert!(!decoded.has_section(0));
+        assert!(bytes.len() < 64);
+    }
+
+    #[test]
+    fn round_trips_blocks_loaded_from_a_document() {
+        let registry =
+            BlockRegistry::from_json(r#"{ "blocks": [{ "name": "lantern", "id": 40 }] }"#).unwrap();
+        let lantern = BlockId::new(40, &registry).unwrap();
+
+        let mut chunk = Chunk::new(ChunkPos::new(1, 2));
+        chunk.add_block(ChunkBlock {
+            block_type: lantern,
+            extra_data: BlockData::None,
+            pos: InnerChunkPos::new(4, 3, 8),
+        });
+        chunk.clean();
+
+        let mut bytes = chunk.to_bytes();
+        let decoded = Chunk::from_bytes_with_registry(&bytes, &registry).unwrap();
+        assert_same_chunk(&chunk, &decoded);
+        assert_eq!(
+            decoded.get_block(&InnerChunkPos::new(4, 3, 8)).block_type,
+            lantern
+        );
+
+        // The builtin registry doesn't know about lanterns
+        assert_eq!(
+            Chunk::from_bytes_with_registry(&bytes, &BlockRegistry::builtin()).err(),
+            Some(ChunkDecodeError::UnknownBlockType(40))
+        );
+
+        // The palette is void then the lantern, pointing it at an id nothing defines fails
+        let palette = bytes.windows(4).position(|w| w == [2, 0, 0, 40]).unwrap();
+        bytes[palette + 3] = 41;
+        assert_eq!(
+            Chunk::from_bytes_with_registry(&bytes, &registry).err(),
+            Some(ChunkDecodeError::UnknownBlockType(41))
+        );
+    }
+
+    #[test]
+    fn decodes_version_1_chunks() {
+        // A 16x64x16 chunk with a single stone block at (1, 40, 2)
+        let stone_index = (1 << 10) | (40 << 4) | 2;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(1);
+        bytes.extend_from_slice(&5i16.to_le_bytes());
+        bytes.extend_from_slice(&(-2i16).to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&[BlockType::Void as u8, BlockType::Stone as u8]);
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        for (palette_index, length) in [
+            (0u8, stone_index as u16),
+            (1, 1),
+            (0, (V1_BLOCK_COUNT - stone_index - 1) as u16),
+        ] {
+            bytes.push(palette_index);
+            bytes.extend_from_slice(&length.to_le_bytes());
+        }
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+
+        let chunk = Chunk::from_bytes(&bytes).unwrap();
+
+        assert_eq!(chunk.position, ChunkPos::new(5, -2));
+        assert_eq!(
+            chunk.get_all_blocks(),
+            vec![ChunkBlock {
+                pos: InnerChunkPos::new(1, 40, 2),
+                block_type: BlockType::Stone.into(),
+                extra_data: BlockData::None,
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert_eq!(
+            Chunk::from_bytes(b"nope").err(),
+            Some(ChunkDecodeError::BadMagic)
+        );
+
+        let mut bytes = Chunk::new(ChunkPos::new(0, 0)).to_bytes();
+        bytes[4] = CHUNK_FORMAT_VERSION + 1;
+        assert_eq!(
+            Chunk::from_bytes(&bytes).err(),
+            Some(ChunkDecodeError::UnsupportedVersion(
+                CHUNK_FORMAT_VERSION + 1
+            ))
+        );
+
+        let bytes = Chunk::new(ChunkPos::new(0, 0)).to_bytes();
+        assert_eq!(
+            Chunk::from_bytes(&bytes[..bytes.len() - 1]).err(),
+            Some(ChunkDecodeError::UnexpectedEnd)
+        );
+
+        // A facing of 6 fits in the state's bits but isn't a direction
+        let mut chunk = Chunk::new(ChunkPos::new(0, 0));
+        let state = BlockState::default().with(BlockProperty::Level, 9).unwrap();
+        chunk.add_block(ChunkBlock {
+            pos: InnerChunkPos::new(1, 1, 1),
+            block_type: BlockType::Water.into(),
+            extra_data: BlockData::State(state),
+        });
+        let mut bytes = chunk.to_bytes();
+        let state_at = bytes.windows(3).position(|w| w == [2, 0x20, 0x01]).unwrap();
+        bytes[state_at + 1] = 6;
+        bytes[state_at + 2] = 0;
+        assert_eq!(
+            Chunk::from_bytes(&bytes).err(),
+            Some(ChunkDecodeError::InvalidBlockState(6))
+        );
+    }
+}
@@ -1,8 +1,13 @@
 use crate::{
-    chunk::{Chunk, InnerChunkPos},
+    block::RenderLayer,
+    chunk::{
+        chunk_geometry::{ChunkGeometry, MeshBuffers},
+        Chunk, InnerChunkPos,
+    },
     positions::ChunkPos,
     world::world_block::WorldBlock,
 };
+use js_sys::{Float32Array, Uint32Array, Uint8Array};
 use serde_wasm_bindgen::{from_value, to_value, Error};
 use wasm_bindgen::prelude::*;
 
@@ -41,6 +46,14 @@ impl Chunk {
         Ok(serde_wasm_bindgen::to_value(&self)?)
     }
 
+    pub fn to_bytes_wasm(&self) -> Uint8Array {
+        Uint8Array::from(&self.to_bytes()[..])
+    }
+
+    pub fn from_bytes_wasm(bytes: &[u8]) -> Result<Chunk, Error> {
+        Chunk::from_bytes(bytes).map_err(|e| Error::new(format!("{}", e)))
+    }
+
     // Need to see if this will mess up the hash map that is pointing to this chunk.
     pub fn set(&mut self, value: JsValue) -> Result<(), Error> {
         from_value(value).and_then(|chunk: Chunk| {
@@ -53,3 +66,51 @@ impl Chunk {
         Chunk::new(ChunkPos { x, y })
     }
 }
+
+#[wasm_bindgen]
+impl ChunkGeometry {
+    pub fn get_layer_wasm(&self, layer: RenderLayer) -> MeshBuffers {
+        self.get_layer(layer).clone()
+    }
+
+    pub fn sort_translucent_wasm(&mut self, x: f32, y: f32, z: f32) {
+        self.sort_translucent([x, y, z]);
+    }
+}
+
+#[wasm_bindgen]
+impl MeshBuffers {
+    /** x, y, z per vertex */
+    pub fn positions_wasm(&self) -> Float32Array {
+        Float32Array::from(&self.positions[..])
+    }
+
+    /** x, y, z per vertex */
+    pub fn normals_wasm(&self) -> Float32Array {
+        Float32Array::from(&self.normals[..])
+    }
+
+    /** u, v per vertex, measured in blocks */
+    pub fn uvs_wasm(&self) -> Float32Array {
+        Float32Array::from(&self.uvs[..])
+    }
+
+    /** The block type of each vertex */
+    pub fn block_ids_wasm(&self) -> Uint32Array {
+        Uint32Array::from(&self.block_ids[..])
+    }
+
+    /** Sky light in the high nibble and block light in the low nibble, per vertex */
+    pub fn light_wasm(&self) -> Uint8Array {
+        Uint8Array::from(&self.light[..])
+    }
+
+    /** Ambient occlusion per vertex, 0 is the darkest and 3 is not occluded */
+    pub fn ao_wasm(&self) -> Uint8Array {
+        Uint8Array::from(&self.ao[..])
+    }
+
+    pub fn indices_wasm(&self) -> Uint32Array {
+        Uint32Array::from(&self.indices[..])
+    }
+}
@@ -0,0 +1,794 @@
+use super::{chunk_mesh::ChunkMesh, Chunk, CHUNK_WIDTH, SECTION_HEIGHT};
+use crate::{
+    block::{block_registry::BlockRegistry, BlockId, BlockShape, RenderLayer},
+    direction::{Axis, Direction, EVERY_DIRECTION},
+    positions::{InnerChunkPos, WorldPos},
+    world::World,
+};
+use wasm_bindgen::prelude::*;
+
+const WIDTH: usize = CHUNK_WIDTH as usize;
+
+/**
+ * Render ready vertex buffers.
+ * Every quad adds 4 vertices and 6 indices. Positions are in world space.
+ * UVs are in blocks, so a merged 3x2 face has uvs from 0 to 3 and 0 to 2 and the texture should repeat.
+ */
+#[wasm_bindgen]
+#[derive(Default, Debug, Clone)]
+pub struct MeshBuffers {
+    #[wasm_bindgen(skip)]
+    pub positions: Vec<f32>,
+    #[wasm_bindgen(skip)]
+    pub normals: Vec<f32>,
+    #[wasm_bindgen(skip)]
+    pub uvs: Vec<f32>,
+    #[wasm_bindgen(skip)]
+    pub block_ids: Vec<u32>,
+    /** Sky light in the high nibble and block light in the low nibble */
+    #[wasm_bindgen(skip)]
+    pub light: Vec<u8>,
+    /** Ambient occlusion from 0 (a corner surrounded by blocks) to 3 (nothing around it) */
+    #[wasm_bindgen(skip)]
+    pub ao: Vec<u8>,
+    #[wasm_bindgen(skip)]
+    pub indices: Vec<u32>,
+}
+
+/** The vertex buffers for a chunk, with one set of buffers per render layer */
+#[wasm_bindgen]
+#[derive(Default, Debug, Clone)]
+pub struct ChunkGeometry {
+    #[wasm_bindgen(skip)]
+    pub opaque: MeshBuffers,
+    #[wasm_bindgen(skip)]
+    pub cutout: MeshBuffers,
+    #[wasm_bindgen(skip)]
+    pub translucent: MeshBuffers,
+}
+
+struct Quad {
+    /** Counter clockwise when looking at the front of the quad */
+    corners: [[f32; 3]; 4],
+    uvs: [[f32; 2]; 4],
+    normal: [f32; 3],
+    block_type: BlockId,
+    light: u8,
+    /** Per corner, in the same order as the corners */
+    ao: [u8; 4],
+}
+
+/** Faces can only be merged when everything in their key matches */
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct FaceKey {
+    block_type: BlockId,
+    light: u8,
+    /** Per corner, in slice order: (u0, v0), (u1, v0), (u1, v1), (u0, v1) */
+    ao: [u8; 4],
+}
+
+const NO_OCCLUSION: [u8; 4] = [3; 4];
+
+/** A rectangle of faces found by the greedy mesher, in slice coordinates */
+struct FaceRect {
+    u: usize,
+    v: usize,
+    width: usize,
+    height: usize,
+    key: FaceKey,
+}
+
+/**
+ * Each face direction is meshed as a stack of 2d slices along its axis.
+ * The (u, v) axes are picked so that u x v points along the positive axis.
+ *
+ * X: u = y, v = z
+ * Y: u = z, v = x
+ * Z: u = x, v = y
+ */
+fn to_slice_coords(axis: Axis, pos: &InnerChunkPos) -> (usize, usize, usize) {
+    let (x, y, z) = (pos.x as usize, pos.y as usize, pos.z as usize);
+    match axis {
+        Axis::X => (x, y, z),
+        Axis::Y => (y, z, x),
+        Axis::Z => (z, x, y),
+    }
+}
+
+fn from_slice_coords(axis: Axis, slice: f32, u: f32, v: f32) -> [f32; 3] {
+    match axis {
+        Axis::X => [slice, u, v],
+        Axis::Y => [v, slice, u],
+        Axis::Z => [u, v, slice],
+    }
+}
+
+/** Unit vectors for the (u, v) axes of a slice */
+fn slice_axes(axis: Axis) -> ([i32; 3], [i32; 3]) {
+    match axis {
+        Axis::X => ([0, 1, 0], [0, 0, 1]),
+        Axis::Y => ([0, 0, 1], [1, 0, 0]),
+        Axis::Z => ([1, 0, 0], [0, 1, 0]),
+    }
+}
+
+/** (slice count, u size, v size) for a chunk that is height blocks tall */
+fn slice_dimensions(axis: Axis, height: usize) -> (usize, usize, usize) {
+    match axis {
+        Axis::X => (WIDTH, height, WIDTH),
+        Axis::Y => (height, WIDTH, WIDTH),
+        Axis::Z => (WIDTH, WIDTH, height),
+    }
+}
+
+fn direction_normal(direction: Direction) -> [f32; 3] {
+    match direction {
+        Direction::North => [0.0, 0.0, 1.0],
+        Direction::South => [0.0, 0.0, -1.0],
+        Direction::Up => [0.0, 1.0, 0.0],
+        Direction::Down => [0.0, -1.0, 0.0],
+        Direction::East => [1.0, 0.0, 0.0],
+        Direction::West => [-1.0, 0.0, 0.0],
+    }
+}
+
+/** Texture coordinates keep y pointing up on side faces */
+fn face_uv(axis: Axis, offset: [f32; 3]) -> [f32; 2] {
+    match axis {
+        Axis::X => [offset[2], offset[1]],
+        Axis::Y => [offset[0], offset[2]],
+        Axis::Z => [offset[0], offset[1]],
+    }
+}
+
+/** Which of the blocks in the 3x3x3 cube around a block cast ambient occlusion */
+struct Neighborhood {
+    occluders: [bool; 27],
+}
+
+impl Neighborhood {
+    /**
+     * Neighbors inside the chunk are read straight from its sections.
+     * Only the ones past the edge of the chunk are looked up in the world.
+     */
+    fn new(
+        chunk: &Chunk,
+        world: &World,
+        registry: &BlockRegistry,
+        pos: &InnerChunkPos,
+    ) -> Neighborhood {
+        let mut occluders = [false; 27];
+        let world_pos = pos.to_world_pos(&chunk.position);
+        let in_chunk = |x: i32, y: i32, z: i32| {
+            (0..CHUNK_WIDTH as i32).contains(&x)
+                && (0..CHUNK_WIDTH as i32).contains(&z)
+                && (0..=u8::MAX as i32).contains(&y)
+        };
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let (x, y, z) = (pos.x as i32 + dx, pos.y as i32 + dy, pos.z as i32 + dz);
+                    let block_type = if in_chunk(x, y, z) {
+                        chunk.get_block_type(&InnerChunkPos::new(x as u8, y as u8, z as u8))
+                    } else {
+                        let neighbor =
+                            WorldPos::new(world_pos.x + dx, world_pos.y + dy, world_pos.z + dz);
+                        world.get_block(&neighbor).block_type
+                    };
+                    let metadata = registry.get_metadata(block_type);
+                    occluders[Self::index([dx, dy, dz])] = metadata.shape == BlockShape::Cube
+                        && !metadata.transparent
+                        && !metadata.fluid;
+                }
+            }
+        }
+        Neighborhood { occluders }
+    }
+
+    fn index(offset: [i32; 3]) -> usize {
+        ((offset[0] + 1) * 9 + (offset[1] + 1) * 3 + (offset[2] + 1)) as usize
+    }
+
+    fn is_occluder(&self, offset: [i32; 3]) -> bool {
+        self.occluders[Self::index(offset)]
+    }
+
+    /**
+     * Ambient occlusion for each corner of a face, looking at the two blocks beside the corner
+     * and the one diagonal to it, in the layer of blocks the face is looking at.
+     */
+    fn face_ao(&self, direction: Direction) -> [u8; 4] {
+        let normal = WorldPos::new(0, 0, 0).move_direction(&direction);
+        let normal = [normal.x, normal.y, normal.z];
+        let (u_axis, v_axis) = slice_axes(direction.to_axis());
+        let offset =
+            |du: i32, dv: i32| [0, 1, 2].map(|i| normal[i] + du * u_axis[i] + dv * v_axis[i]);
+
+        [(-1, -1), (1, -1), (1, 1), (-1, 1)].map(|(du, dv)| {
+            let side_u = self.is_occluder(offset(du, 0));
+            let side_v = self.is_occluder(offset(0, dv));
+            let corner = self.is_occluder(offset(du, dv));
+            if side_u && side_v {
+                0
+            } else {
+                3 - side_u as u8 - side_v as u8 - corner as u8
+            }
+        })
+    }
+}
+
+/**
+ * Finds rectangles of equal faces in a width x height mask.
+ * The mask is cleared as it goes.
+ */
+fn greedy_rects(mask: &mut [Option<FaceKey>], width: usize, height: usize) -> Vec<FaceRect> {
+    let mut rects = Vec::new();
+    for v in 0..height {
+        let mut u = 0;
+        while u < width {
+            let key = match mask[v * width + u] {
+                Some(key) => key,
+                None => {
+                    u += 1;
+                    continue;
+                }
+            };
+
+            let mut rect_width = 1;
+            while u + rect_width < width && mask[v * width + u + rect_width] == Some(key) {
+                rect_width += 1;
+            }
+
+            let mut rect_height = 1;
+            'grow: while v + rect_height < height {
+                let row = (v + rect_height) * width;
+                for i in u..u + rect_width {
+                    if mask[row + i] != Some(key) {
+                        break 'grow;
+                    }
+                }
+                rect_height += 1;
+            }
+
+            for row in v..v + rect_height {
+                for i in u..u + rect_width {
+                    mask[row * width + i] = None;
+                }
+            }
+
+            rects.push(FaceRect {
+                u,
+                v,
+                width: rect_width,
+                height: rect_height,
+                key,
+            });
+            u += rect_width;
+        }
+    }
+    rects
+}
+
+impl ChunkGeometry {
+    /**
+     * Builds the geometry for a chunk from the visible faces in its mesh.
+     * Coplanar faces of the same block type, light and ambient occlusion are merged into a single quad.
+     * Faces go into the buffers for their block's render layer.
+     * Each face is lit by the light in the block it faces. Light and occlusion are read from the world
+     * so faces on the edge of the chunk can see into the neighboring chunks.
+     */
+    pub fn from_chunk(chunk: &Chunk, mesh: &ChunkMesh, world: &World) -> ChunkGeometry {
+        BlockRegistry::with_installed(|registry| Self::build(chunk, mesh, world, registry))
+    }
+
+    fn build(
+        chunk: &Chunk,
+        mesh: &ChunkMesh,
+        world: &World,
+        registry: &BlockRegistry,
+    ) -> ChunkGeometry {
+        let mut geometry = ChunkGeometry::default();
+        let offset = [
+            (chunk.position.x * CHUNK_WIDTH) as f32,
+            0.0,
+            (chunk.position.y * CHUNK_WIDTH) as f32,
+        ];
+        let height = chunk.get_section_count() * SECTION_HEIGHT as usize;
+
+        // One mask per direction, indexed by [slice][v][u]
+        let mut masks: Vec<Vec<Option<FaceKey>>> = EVERY_DIRECTION
+            .iter()
+            .map(|direction| {
+                let (slices, u_size, v_size) = slice_dimensions(direction.to_axis(), height);
+                vec![None; slices * u_size * v_size]
+            })
+            .collect();
+
+        for block in chunk.get_all_blocks() {
+            let faces = mesh.get_faces(&block.pos);
+            let shape = registry.get_metadata(block.block_type).shape;
+            let world_pos = block.pos.to_world_pos(&chunk.position);
+            let face_key = |direction: Direction, ao: [u8; 4]| FaceKey {
+                block_type: block.block_type,
+                light: world
+                    .get_light(&world_pos.move_direction(&direction))
+                    .pack(),
+                ao,
+            };
+            match shape {
+                BlockShape::Cube => {
+                    if faces.into_iter().next().is_none() {
+                        continue;
+                    }
+                    let neighborhood = Neighborhood::new(chunk, world, registry, &block.pos);
+                    for direction in faces {
+                        let axis = direction.to_axis();
+                        let (_, u_size, v_size) = slice_dimensions(axis, height);
+                        let (slice, u, v) = to_slice_coords(axis, &block.pos);
+                        masks[direction.to_index()][(slice * v_size + v) * u_size + u] =
+                            Some(face_key(direction, neighborhood.face_ao(direction)));
+                    }
+                }
+                BlockShape::Flat => {
+                    for direction in faces {
+                        let axis = direction.to_axis();
+                        let (slice, u, v) = to_slice_coords(axis, &block.pos);
+                        let rect = FaceRect {
+                            u,
+                            v,
+                            width: 1,
+                            height: 1,
+                            key: face_key(direction, NO_OCCLUSION),
+                        };
+                        geometry
+                            .push_quad(&Self::face_quad(direction, slice, &rect, offset), registry);
+                    }
+                }
+                BlockShape::X => {
+                    if faces.into_iter().next().is_some() {
+                        let light = world.get_light(&world_pos).pack();
+                        geometry.push_x_shape(
+                            &block.pos,
+                            block.block_type,
+                            light,
+                            offset,
+                            registry,
+                        );
+                    }
+                }
+            }
+        }
+
+        for direction in EVERY_DIRECTION {
+            let (slices, u_size, v_size) = slice_dimensions(direction.to_axis(), height);
+            let mask = &mut masks[direction.to_index()];
+            for slice in 0..slices {
+                let slice_mask = &mut mask[slice * u_size * v_size..(slice + 1) * u_size * v_size];
+                for rect in greedy_rects(slice_mask, u_size, v_size) {
+                    geometry.push_quad(&Self::face_quad(direction, slice, &rect, offset), registry);
+                }
+            }
+        }
+
+        geometry
+    }
+
+    fn face_quad(direction: Direction, slice: usize, rect: &FaceRect, offset: [f32; 3]) -> Quad {
+        let axis = direction.to_axis();
+        let plane = slice as f32 + if direction.is_outward() { 1.0 } else { 0.0 };
+        let (u0, v0) = (rect.u as f32, rect.v as f32);
+        let (u1, v1) = (u0 + rect.width as f32, v0 + rect.height as f32);
+
+        let mut corners = [
+            from_slice_coords(axis, plane, u0, v0),
+            from_slice_coords(axis, plane, u1, v0),
+            from_slice_coords(axis, plane, u1, v1),
+            from_slice_coords(axis, plane, u0, v1),
+        ];
+        let mut ao = rect.key.ao;
+        // Faces pointing down an axis are seen from the other side
+        if !direction.is_outward() {
+            corners.reverse();
+            ao.reverse();
+        }
+
+        let origin = from_slice_coords(axis, plane, u0, v0);
+        let uvs = corners.map(|corner| {
+            face_uv(
+                axis,
+                [
+                    corner[0] - origin[0],
+                    corner[1] - origin[1],
+                    corner[2] - origin[2],
+                ],
+            )
+        });
+
+        Quad {
+            corners: corners.map(|c| [c[0] + offset[0], c[1] + offset[1], c[2] + offset[2]]),
+            uvs,
+            normal: direction_normal(direction),
+            block_type: rect.key.block_type,
+            light: rect.key.light,
+            ao,
+        }
+    }
+
+    /** Two crossed quads through the middle of the block, visible from both sides */
+    fn push_x_shape(
+        &mut self,
+        pos: &InnerChunkPos,
+        block_type: BlockId,
+        light: u8,
+        offset: [f32; 3],
+        registry: &BlockRegistry,
+    ) {
+        let x = pos.x as f32 + offset[0];
+        let y = pos.y as f32 + offset[1];
+        let z = pos.z as f32 + offset[2];
+        let diagonals = [
+            ([[x, y, z], [x + 1.0, y, z + 1.0]], [1.0, 0.0, -1.0]),
+            ([[x + 1.0, y, z], [x, y, z + 1.0]], [1.0, 0.0, 1.0]),
+        ];
+
+        for ([start, end], normal) in diagonals {
+            let corners = [
+                start,
+                end,
+                [end[0], end[1] + 1.0, end[2]],
+                [start[0], start[1] + 1.0, start[2]],
+            ];
+            let normal = normal.map(|n: f32| n * std::f32::consts::FRAC_1_SQRT_2);
+            let uvs = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+            self.push_quad(
+                &Quad {
+                    corners,
+                    uvs,
+                    normal,
+                    block_type,
+                    light,
+                    ao: NO_OCCLUSION,
+                },
+                registry,
+            );
+
+            let mut back_corners = corners;
+            back_corners.reverse();
+            let mut back_uvs = uvs;
+            back_uvs.reverse();
+            self.push_quad(
+                &Quad {
+                    corners: back_corners,
+                    uvs: back_uvs,
+                    normal: normal.map(|n| -n),
+                    block_type,
+                    light,
+                    ao: NO_OCCLUSION,
+                },
+                registry,
+            );
+        }
+    }
+
+    fn push_quad(&mut self, quad: &Quad, registry: &BlockRegistry) {
+        let layer = registry.get_metadata(quad.block_type).get_render_layer();
+        self.get_layer_mut(layer).push_quad(quad);
+    }
+
+    pub fn get_layer(&self, layer: RenderLayer) -> &MeshBuffers {
+        match layer {
+            RenderLayer::Opaque => &self.opaque,
+            RenderLayer::Cutout => &self.cutout,
+            RenderLayer::Translucent => &self.translucent,
+        }
+    }
+
+    fn get_layer_mut(&mut self, layer: RenderLayer) -> &mut MeshBuffers {
+        match layer {
+            RenderLayer::Opaque => &mut self.opaque,
+            RenderLayer::Cutout => &mut self.cutout,
+            RenderLayer::Translucent => &mut self.translucent,
+        }
+    }
+
+    /** Reorders the translucent quads so they draw back to front when seen from the camera */
+    pub fn sort_translucent(&mut self, camera: [f32; 3]) {
+        self.translucent.sort_back_to_front(camera);
+    }
+
+    pub fn quad_count(&self) -> usize {
+        self.opaque.quad_count() + self.cutout.quad_count() + self.translucent.quad_count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.opaque.is_empty() && self.cutout.is_empty() && self.translucent.is_empty()
+    }
+}
+
+impl MeshBuffers {
+    fn push_quad(&mut self, quad: &Quad) {
+        let base = (self.positions.len() / 3) as u32;
+        for i in 0..4 {
+            self.positions.extend_from_slice(&quad.corners[i]);
+            self.normals.extend_from_slice(&quad.normal);
+            self.uvs.extend_from_slice(&quad.uvs[i]);
+            self.block_ids.push(u8::from(quad.block_type) as u32);
+            self.light.push(quad.light);
+            self.ao.push(quad.ao[i]);
+        }
+        // Split the quad along its brighter diagonal so the occlusion interpolates evenly
+        let [a0, a1, a2, a3] = quad.ao;
+        if a0 + a2 < a1 + a3 {
+            self.indices.extend_from_slice(&[
+                base + 1,
+                base + 2,
+                base + 3,
+                base + 1,
+                base + 3,
+                base,
+            ]);
+        } else {
+            self.indices
+                .extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+        }
+    }
+
+    /** The squared distance from the middle of a quad to a point */
+    fn quad_distance_squared(&self, quad: usize, point: [f32; 3]) -> f32 {
+        let mut center = [0.0; 3];
+        for index in &self.indices[quad * 6..quad * 6 + 6] {
+            let vertex = *index as usize * 3;
+            for (axis, value) in center.iter_mut().enumerate() {
+                *value += self.positions[vertex + axis] / 6.0;
+            }
+        }
+        (0..3)
+            .map(|axis| (center[axis] - point[axis]).powi(2))
+            .sum()
+    }
+
+    /** Only the indices are reordered, the vertices stay where they are */
+    pub fn sort_back_to_front(&mut self, camera: [f32; 3]) {
+        let mut quads = (0..self.quad_count())
+            .map(|quad| (self.quad_distance_squared(quad, camera), quad))
+            .collect::<Vec<(f32, usize)>>();
+        quads.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        self.indices = quads
+            .iter()
+            .flat_map(|(_, quad)| self.indices[quad * 6..quad * 6 + 6].to_vec())
+            .collect();
+    }
+
+    pub fn quad_count(&self) -> usize {
+        self.indices.len() / 6
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChunkGeometry;
+    use crate::{
+        block::{BlockData, BlockType, RenderLayer},
+        chunk::Chunk,
+        positions::{ChunkPos, WorldPos},
+        world::{world_block::WorldBlock, World},
+    };
+
+    fn make_world(blocks: &[(i32, i32, i32, BlockType)]) -> World {
+        let mut world = World::default();
+        world.insert_chunk(Chunk::new(ChunkPos::new(0, 0)));
+        for (x, y, z, block_type) in blocks {
+            world
+                .add_block(&WorldBlock {
+                    block_type: (*block_type).into(),
+                    extra_data: BlockData::None,
+                    world_pos: WorldPos::new(*x, *y, *z),
+                })
+                .unwrap();
+        }
+        world
+    }
+
+    fn geometry(world: &World) -> ChunkGeometry {
+        world.get_chunk_geometry(&ChunkPos::new(0, 0)).unwrap()
+    }
+
+    #[test]
+    fn single_block_has_six_quads() {
+        let world = make_world(&[(1, 2, 3, BlockType::Stone)]);
+        let geometry = geometry(&world).opaque;
+
+        assert_eq!(geometry.quad_count(), 6);
+        assert_eq!(geometry.positions.len(), 6 * 4 * 3);
+        assert_eq!(geometry.normals.len(), 6 * 4 * 3);
+        assert_eq!(geometry.uvs.len(), 6 * 4 * 2);
+        assert_eq!(geometry.block_ids.len(), 6 * 4);
+        assert!(geometry
+            .block_ids
+            .iter()
+            .all(|id| *id == BlockType::Stone as u32));
+
+        // Every vertex sits on the corner of the block
+        for vertex in geometry.positions.chunks(3) {
+            assert!(vertex[0] == 1.0 || vertex[0] == 2.0);
+            assert!(vertex[1] == 2.0 || vertex[1] == 3.0);
+            assert!(vertex[2] == 3.0 || vertex[2] == 4.0);
+        }
+    }
+
+    #[test]
+    fn merges_faces_of_the_same_type() {
+        let floor = (0..16)
+            .flat_map(|x| (0..16).map(move |z| (x, 0, z, BlockType::Stone)))
+            .collect::<Vec<_>>();
+        let world = make_world(&floor);
+        let geometry = geometry(&world).opaque;
+
+        assert_eq!(geometry.quad_count(), 6);
+        // The top face spans the whole chunk, so the uvs tile 16 times
+        assert!(geometry.uvs.contains(&16.0));
+    }
+
+    #[test]
+    fn does_not_merge_different_types() {
+        let world = make_world(&[(0, 0, 0, BlockType::Stone), (1, 0, 0, BlockType::Grass)]);
+        let geometry = geometry(&world);
+
+        // Each block keeps its own 5 faces, the faces between them are hidden
+        assert_eq!(geometry.quad_count(), 10);
+    }
+
+    #[test]
+    fn x_shapes_are_two_sided_crosses() {
+        let world = make_world(&[(4, 0, 4, BlockType::RedFlower)]);
+        let geometry = geometry(&world);
+
+        assert_eq!(geometry.cutout.quad_count(), 4);
+        assert!(geometry.opaque.is_empty());
+    }
+
+    #[test]
+    fn splits_faces_into_render_layers() {
+        let world = make_world(&[
+            (0, 0, 0, BlockType::Stone),
+            (1, 0, 0, BlockType::Leaf),
+            (2, 0, 0, BlockType::Water),
+        ]);
+        let geometry = geometry(&world);
+
+        let layer_ids = |layer: RenderLayer| {
+            let mut ids = geometry.get_layer(layer).block_ids.clone();
+            ids.dedup();
+            ids
+        };
+        assert_eq!(
+            layer_ids(RenderLayer::Opaque),
+            vec![BlockType::Stone as u32]
+        );
+        assert_eq!(layer_ids(RenderLayer::Cutout), vec![BlockType::Leaf as u32]);
+        assert_eq!(
+            layer_ids(RenderLayer::Translucent),
+            vec![BlockType::Water as u32]
+        );
+        // Stone shows through the leaf, the leaf shows through the water,
+        // but the water hides its face against the leaf
+        assert_eq!(geometry.opaque.quad_count(), 6);
+        assert_eq!(geometry.cutout.quad_count(), 5);
+        assert_eq!(geometry.translucent.quad_count(), 5);
+    }
+
+    #[test]
+    fn sorts_translucent_faces_back_to_front() {
+        let world = make_world(&[(0, 0, 0, BlockType::Water), (5, 0, 0, BlockType::Water)]);
+        let mut geometry = geometry(&world);
+
+        geometry.sort_translucent([-10.0, 0.5, 0.5]);
+
+        let buffers = &geometry.translucent;
+        let distances = (0..buffers.quad_count())
+            .map(|quad| buffers.quad_distance_squared(quad, [-10.0, 0.5, 0.5]))
+            .collect::<Vec<f32>>();
+        assert_eq!(distances.len(), 12);
+        assert!(distances.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn faces_are_lit_by_the_block_in_front_of_them() {
+        let mut floor = (0..16)
+            .flat_map(|x| (0..16).map(move |z| (x, 0, z, BlockType::Stone)))
+            .collect::<Vec<_>>();
+        // A roof over everything but one corner
+        floor.extend(
+            (0..16)
+                .flat_map(|x| (0..16).map(move |z| (x, 3, z, BlockType::Stone)))
+                .filter(|(x, _, z, _)| (*x, *z) != (0, 0)),
+        );
+        let mut chunk = Chunk::new(ChunkPos::new(0, 0));
+        for (x, y, z, block_type) in floor {
+            chunk.add_block(crate::block::ChunkBlock {
+                pos: crate::positions::InnerChunkPos::new(x, y, z),
+                block_type: block_type.into(),
+                extra_data: BlockData::None,
+            });
+        }
+        let mut world = World::default();
+        world.insert_chunk(chunk);
+        let geometry = geometry(&world).opaque;
+
+        let lights = &geometry.light;
+        // Full sky on the roof, dark in the far corner under it and lit under the hole
+        assert!(lights.contains(&0xF0));
+        assert!(lights.contains(&0x00));
+        // Faces only merge when their light matches, so the floor under the roof
+        // can't be one big quad any more
+        assert!(geometry.quad_count() > 12);
+    }
+
+    #[test]
+    fn occludes_corners_next_to_blocks() {
+        let lone_block = make_world(&[(5, 5, 5, BlockType::Stone)]);
+        assert!(geometry(&lone_block).opaque.ao.iter().all(|ao| *ao == 3));
+
+        let mut blocks = (0..3)
+            .flat_map(|x| (0..3).map(move |z| (x, 0, z, BlockType::Stone)))
+            .collect::<Vec<_>>();
+        blocks.push((1, 1, 1, BlockType::Stone));
+        let world = make_world(&blocks);
+        let geometry = geometry(&world).opaque;
+
+        // The floor darkens where it meets the block standing on it
+        assert!(geometry.ao.contains(&2));
+        assert!(geometry.ao.contains(&3));
+        assert_eq!(geometry.ao.len(), geometry.quad_count() * 4);
+    }
+
+    #[test]
+    fn occludes_corners_next_to_blocks_in_other_chunks() {
+        let mut world = make_world(&[(15, 0, 5, BlockType::Stone)]);
+        world.insert_chunk(Chunk::new(ChunkPos::new(1, 0)));
+        assert!(geometry(&world).opaque.ao.iter().all(|ao| *ao == 3));
+
+        world
+            .add_block(&WorldBlock {
+                block_type: BlockType::Stone.into(),
+                extra_data: BlockData::None,
+                world_pos: WorldPos::new(16, 1, 5),
+            })
+            .unwrap();
+        assert!(geometry(&world).opaque.ao.contains(&2));
+    }
+
+    #[test]
+    fn quads_face_their_normal() {
+        let world = make_world(&[(0, 0, 0, BlockType::Stone)]);
+        let geometry = geometry(&world).opaque;
+
+        for quad in 0..geometry.quad_count() {
+            let corner = |i: usize| {
+                let index = geometry.indices[quad * 6 + i] as usize * 3;
+                [
+                    geometry.positions[index],
+                    geometry.positions[index + 1],
+                    geometry.positions[index + 2],
+                ]
+            };
+            let (a, b, c) = (corner(0), corner(1), corner(2));
+            let ab = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+            let ac = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+            let cross = [
+                ab[1] * ac[2] - ab[2] * ac[1],
+                ab[2] * ac[0] - ab[0] * ac[2],
+                ab[0] * ac[1] - ab[1] * ac[0],
+            ];
+            let normal_index = geometry.indices[quad * 6] as usize * 3;
+            let normal = &geometry.normals[normal_index..normal_index + 3];
+            let dot = cross[0] * normal[0] + cross[1] * normal[1] + cross[2] * normal[2];
+            assert!(dot > 0.0);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use super::chunk_section::SECTION_MEM_SIZE;
+use serde::{Deserialize, Serialize};
+
+pub const MAX_LIGHT: u8 = 15;
+
+/** Sky light comes from above the world, block light comes from blocks that give off light */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LightChannel {
+    Sky,
+    Block,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct LightLevel {
+    pub sky: u8,
+    pub block: u8,
+}
+
+impl LightLevel {
+    /** Open air with nothing blocking the sky */
+    pub const FULL_SKY: LightLevel = LightLevel {
+        sky: MAX_LIGHT,
+        block: 0,
+    };
+
+    pub const DARK: LightLevel = LightLevel { sky: 0, block: 0 };
+
+    pub fn get(&self, channel: LightChannel) -> u8 {
+        match channel {
+            LightChannel::Sky => self.sky,
+            LightChannel::Block => self.block,
+        }
+    }
+
+    /** Sky light in the high nibble and block light in the low nibble */
+    pub fn pack(&self) -> u8 {
+        (self.sky << 4) | self.block
+    }
+
+    pub fn unpack(packed: u8) -> LightLevel {
+        LightLevel {
+            sky: packed >> 4,
+            block: packed & 0xF,
+        }
+    }
+}
+
+/** Light levels for every block in a chunk section, one packed byte per block */
+#[derive(Clone)]
+pub struct LightSection {
+    levels: Vec<u8>,
+}
+
+impl LightSection {
+    pub fn filled(level: LightLevel) -> LightSection {
+        LightSection {
+            levels: vec![level.pack(); SECTION_MEM_SIZE],
+        }
+    }
+
+    pub fn get(&self, index: usize) -> LightLevel {
+        LightLevel::unpack(self.levels[index])
+    }
+
+    pub fn set(&mut self, index: usize, channel: LightChannel, level: u8) {
+        let mut light = self.get(index);
+        match channel {
+            LightChannel::Sky => light.sky = level.min(MAX_LIGHT),
+            LightChannel::Block => light.block = level.min(MAX_LIGHT),
+        }
+        self.levels[index] = light.pack();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LightChannel, LightLevel, LightSection};
+
+    #[test]
+    fn packs_both_channels() {
+        let level = LightLevel { sky: 12, block: 3 };
+        assert_eq!(LightLevel::unpack(level.pack()), level);
+
+        let mut section = LightSection::filled(LightLevel::FULL_SKY);
+        section.set(10, LightChannel::Block, 7);
+        section.set(10, LightChannel::Sky, 2);
+        assert_eq!(section.get(10), LightLevel { sky: 2, block: 7 });
+        assert_eq!(section.get(11), LightLevel::FULL_SKY);
+    }
+}
@@ -43,6 +43,13 @@ impl ChunkMesh {
             .insert(world_pos.to_inner_chunk_pos().to_chunk_index(), directions);
     }
 
+    pub fn get_faces(&self, pos: &InnerChunkPos) -> Directions {
+        self.face_map
+            .get(&pos.to_chunk_index())
+            .copied()
+            .unwrap_or_else(Directions::empty)
+    }
+
     pub fn get(&self, world_pos: WorldPos) -> BlockMesh {
         let empty = Directions::empty();
         let mesh = self
@@ -0,0 +1,106 @@
+use super::{chunk_storage::PalettedBlocks, CHUNK_WIDTH, SECTION_HEIGHT};
+
+pub const SECTION_MEM_SIZE: usize = (SECTION_HEIGHT * CHUNK_WIDTH * CHUNK_WIDTH) as usize;
+
+/**
+ * A 16x16x16 slice of a chunk.
+ * Chunks only allocate the sections that have had a block placed in them.
+ */
+#[derive(Clone)]
+pub struct ChunkSection {
+    pub blocks: PalettedBlocks,
+}
+
+impl Default for ChunkSection {
+    fn default() -> Self {
+        ChunkSection {
+            blocks: PalettedBlocks::new(SECTION_MEM_SIZE),
+        }
+    }
+}
+
+/**
+ * Serializes sections as a list with null for every unallocated section
+ * and the flat list of block types for the rest.
+ * Sections with block data are written as `{ blocks, data }` instead,
+ * where data is a list of (section index, block data) pairs.
+ */
+pub mod sections {
+    use super::ChunkSection;
+    use crate::block::{BlockData, BlockType};
+    use crate::chunk::chunk_storage::{block_types, PalettedBlocks};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize)]
+    #[serde(untagged)]
+    enum SerSection<'a> {
+        Types(#[serde(with = "block_types")] &'a PalettedBlocks),
+        WithData {
+            #[serde(with = "block_types")]
+            blocks: &'a PalettedBlocks,
+            data: Vec<(u16, BlockData)>,
+        },
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum DeSection {
+        Types(#[serde(with = "block_types")] PalettedBlocks),
+        WithData {
+            #[serde(with = "block_types")]
+            blocks: PalettedBlocks,
+            data: Vec<(u16, BlockData)>,
+        },
+    }
+
+    fn to_ser_section(blocks: &PalettedBlocks) -> SerSection<'_> {
+        let data: Vec<(u16, BlockData)> = blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, block_data))| *block_data != BlockData::None)
+            .map(|(index, (_, block_data))| (index as u16, block_data))
+            .collect();
+
+        if data.is_empty() {
+            SerSection::Types(blocks)
+        } else {
+            SerSection::WithData { blocks, data }
+        }
+    }
+
+    fn from_de_section(section: DeSection) -> ChunkSection {
+        match section {
+            DeSection::Types(blocks) => ChunkSection { blocks },
+            DeSection::WithData { mut blocks, data } => {
+                for (index, block_data) in data {
+                    let block_type = blocks.get_block_type(index as usize);
+                    if block_type != BlockType::Void {
+                        blocks.set(index as usize, (block_type, block_data));
+                    }
+                }
+                ChunkSection { blocks }
+            }
+        }
+    }
+
+    pub fn serialize<S: Serializer>(
+        sections: &[Option<ChunkSection>],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            sections
+                .iter()
+                .map(|section| section.as_ref().map(|s| to_ser_section(&s.blocks))),
+        )
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Option<ChunkSection>>, D::Error> {
+        let sections = Vec::<Option<DeSection>>::deserialize(deserializer)?;
+        Ok(sections
+            .into_iter()
+            .map(|section| section.map(from_de_section))
+            .collect())
+    }
+}
@@ -0,0 +1,380 @@
+use crate::block::{BlockData, BlockId};
+
+/** Palettes that would need more bits than this are replaced with a plain array */
+const MAX_PALETTE_BITS: u32 = 8;
+
+pub type BlockEntry = (BlockId, BlockData);
+
+const VOID_ENTRY: BlockEntry = (BlockId::VOID, BlockData::None);
+
+#[derive(Clone)]
+enum Storage {
+    /**
+     * Each block stores an index into the palette, packed `bits` at a time into u64 words.
+     * Indices never straddle two words. With a single palette entry no words are needed at all.
+     */
+    Palette {
+        palette: Vec<BlockEntry>,
+        bits: u32,
+        words: Vec<u64>,
+    },
+    Direct(Vec<BlockEntry>),
+}
+
+/**
+ * Block storage for a chunk.
+ * Most chunks only use a handful of block types, so the blocks are stored as
+ * small indices into a per-chunk palette of (BlockId, BlockData) entries.
+ */
+#[derive(Clone)]
+pub struct PalettedBlocks {
+    len: usize,
+    max_palette_bits: u32,
+    storage: Storage,
+}
+
+fn words_needed(len: usize, bits: u32) -> usize {
+    if bits == 0 {
+        return 0;
+    }
+    let per_word = (64 / bits) as usize;
+    len.div_ceil(per_word)
+}
+
+fn bits_needed(palette_len: usize) -> u32 {
+    match palette_len {
+        0 | 1 => 0,
+        n => usize::BITS - (n - 1).leading_zeros(),
+    }
+}
+
+fn read_packed(words: &[u64], bits: u32, index: usize) -> usize {
+    if bits == 0 {
+        return 0;
+    }
+    let per_word = (64 / bits) as usize;
+    let shift = (index % per_word) as u32 * bits;
+    let mask = (1u64 << bits) - 1;
+    ((words[index / per_word] >> shift) & mask) as usize
+}
+
+fn write_packed(words: &mut [u64], bits: u32, index: usize, value: usize) {
+    if bits == 0 {
+        return;
+    }
+    let per_word = (64 / bits) as usize;
+    let shift = (index % per_word) as u32 * bits;
+    let mask = ((1u64 << bits) - 1) << shift;
+    let word = &mut words[index / per_word];
+    *word = (*word & !mask) | ((value as u64) << shift);
+}
+
+impl PalettedBlocks {
+    /** Makes storage for len blocks, all void */
+    pub fn new(len: usize) -> PalettedBlocks {
+        Self::with_max_palette_bits(len, MAX_PALETTE_BITS)
+    }
+
+    fn with_max_palette_bits(len: usize, max_palette_bits: u32) -> PalettedBlocks {
+        PalettedBlocks {
+            len,
+            max_palette_bits,
+            storage: Storage::Palette {
+                palette: vec![VOID_ENTRY],
+                bits: 0,
+                words: Vec::new(),
+            },
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /** How many distinct entries the palette holds, or None once storage has been upgraded to an array */
+    pub fn palette_len(&self) -> Option<usize> {
+        match &self.storage {
+            Storage::Palette { palette, .. } => Some(palette.len()),
+            Storage::Direct(_) => None,
+        }
+    }
+
+    pub fn get(&self, index: usize) -> BlockEntry {
+        if index >= self.len {
+            return VOID_ENTRY;
+        }
+        match &self.storage {
+            Storage::Palette {
+                palette,
+                bits,
+                words,
+            } => palette[read_packed(words, *bits, index)],
+            Storage::Direct(entries) => entries[index],
+        }
+    }
+
+    pub fn get_block_type(&self, index: usize) -> BlockId {
+        self.get(index).0
+    }
+
+    pub fn get_block_data(&self, index: usize) -> BlockData {
+        self.get(index).1
+    }
+
+    pub fn set(&mut self, index: usize, entry: BlockEntry) {
+        if index >= self.len {
+            return;
+        }
+
+        let added_entry = match &mut self.storage {
+            Storage::Direct(_) => false,
+            Storage::Palette { palette, .. } => {
+                let is_new = !palette.contains(&entry);
+                if is_new {
+                    palette.push(entry);
+                }
+                is_new
+            }
+        };
+        if added_entry {
+            self.grow_if_needed();
+        }
+
+        match &mut self.storage {
+            Storage::Direct(entries) => entries[index] = entry,
+            Storage::Palette {
+                palette,
+                bits,
+                words,
+            } => {
+                let palette_index = palette.iter().position(|e| *e == entry).unwrap_or(0);
+                write_packed(words, *bits, index, palette_index)
+            }
+        }
+    }
+
+    /**
+     * Repacks the indices when the palette no longer fits in the current bit width.
+     * Entries no block uses anymore are dropped first, so overwriting blocks doesn't grow the palette forever.
+     * Storage only becomes a plain array if the palette is still too big after that.
+     */
+    fn grow_if_needed(&mut self) {
+        let (palette_len, bits) = match &self.storage {
+            Storage::Palette { palette, bits, .. } => (palette.len(), *bits),
+            Storage::Direct(_) => return,
+        };
+
+        if bits_needed(palette_len) <= bits {
+            return;
+        }
+
+        let entries = self.iter().collect::<Vec<BlockEntry>>();
+
+        if let Storage::Palette {
+            palette,
+            bits,
+            words,
+        } = &mut self.storage
+        {
+            // Void stays first, and the entry that was just added is about to be used
+            let last = palette.len() - 1;
+            let mut used = vec![false; palette.len()];
+            used[0] = true;
+            used[last] = true;
+            for index in 0..self.len {
+                used[read_packed(words, *bits, index)] = true;
+            }
+            let mut compacted = palette
+                .iter()
+                .zip(used)
+                .filter(|(_, used)| *used)
+                .map(|(entry, _)| *entry)
+                .collect::<Vec<BlockEntry>>();
+
+            let new_bits = bits_needed(compacted.len());
+            if new_bits > self.max_palette_bits {
+                self.storage = Storage::Direct(entries);
+                return;
+            }
+
+            let mut new_words = vec![0; words_needed(self.len, new_bits)];
+            for (index, entry) in entries.iter().enumerate() {
+                let palette_index = compacted.iter().position(|e| e == entry).unwrap_or(0);
+                write_packed(&mut new_words, new_bits, index, palette_index);
+            }
+            std::mem::swap(palette, &mut compacted);
+            *bits = new_bits;
+            *words = new_words;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = BlockEntry> + '_ {
+        (0..self.len).map(move |index| self.get(index))
+    }
+
+    /** Bytes used by the block storage, not counting the struct itself */
+    pub fn heap_size(&self) -> usize {
+        match &self.storage {
+            Storage::Palette { palette, words, .. } => {
+                palette.len() * std::mem::size_of::<BlockEntry>()
+                    + words.len() * std::mem::size_of::<u64>()
+            }
+            Storage::Direct(entries) => entries.len() * std::mem::size_of::<BlockEntry>(),
+        }
+    }
+}
+
+/**
+ * Serializes the storage as a flat list of block types, which is how chunks
+ * have always been sent to JS.
+ */
+pub mod block_types {
+    use super::PalettedBlocks;
+    use crate::block::{BlockData, BlockId};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        blocks: &PalettedBlocks,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(blocks.iter().map(|(block_type, _)| block_type))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<PalettedBlocks, D::Error> {
+        let block_types = Vec::<BlockId>::deserialize(deserializer)?;
+        let mut blocks = PalettedBlocks::new(block_types.len());
+        for (index, block_type) in block_types.into_iter().enumerate() {
+            if block_type != BlockId::VOID {
+                blocks.set(index, (block_type, BlockData::None));
+            }
+        }
+        Ok(blocks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bits_needed, PalettedBlocks};
+    use crate::{
+        block::{BlockData, BlockType},
+        direction::Direction,
+    };
+
+    #[test]
+    fn calculates_bits_needed() {
+        assert_eq!(bits_needed(1), 0);
+        assert_eq!(bits_needed(2), 1);
+        assert_eq!(bits_needed(3), 2);
+        assert_eq!(bits_needed(4), 2);
+        assert_eq!(bits_needed(5), 3);
+        assert_eq!(bits_needed(256), 8);
+        assert_eq!(bits_needed(257), 9);
+    }
+
+    #[test]
+    fn empty_storage_is_tiny() {
+        let blocks = PalettedBlocks::new(4096);
+        assert_eq!(blocks.get(0), (BlockType::Void.into(), BlockData::None));
+        assert_eq!(blocks.get(4095), (BlockType::Void.into(), BlockData::None));
+        assert!(blocks.heap_size() < 64);
+    }
+
+    #[test]
+    fn stores_blocks_as_palette_grows() {
+        let mut blocks = PalettedBlocks::new(4096);
+        let types = [
+            BlockType::Stone,
+            BlockType::Grass,
+            BlockType::Wood,
+            BlockType::Leaf,
+            BlockType::Water,
+        ];
+        for index in 0..4096 {
+            blocks.set(index, (types[index % types.len()].into(), BlockData::None));
+        }
+        blocks.set(
+            7,
+            (BlockType::Image.into(), BlockData::Image(Direction::East)),
+        );
+
+        // Void is always the first entry
+        assert_eq!(blocks.palette_len(), Some(7));
+        for index in 0..4096 {
+            let expected = if index == 7 {
+                (BlockType::Image.into(), BlockData::Image(Direction::East))
+            } else {
+                (types[index % types.len()].into(), BlockData::None)
+            };
+            assert_eq!(blocks.get(index), expected);
+        }
+        // 3 bits per block instead of a full entry each
+        assert!(blocks.heap_size() < 4096 / 2);
+    }
+
+    #[test]
+    fn upgrades_to_direct_storage() {
+        let mut blocks = PalettedBlocks::with_max_palette_bits(1024, 2);
+        let types = [
+            BlockType::Stone,
+            BlockType::Grass,
+            BlockType::Wood,
+            BlockType::Leaf,
+            BlockType::Water,
+        ];
+        for index in 0..1024 {
+            blocks.set(index, (types[index % types.len()].into(), BlockData::None));
+        }
+
+        assert_eq!(blocks.palette_len(), None);
+        for index in 0..1024 {
+            assert_eq!(
+                blocks.get(index),
+                (types[index % types.len()].into(), BlockData::None)
+            );
+        }
+    }
+
+    #[test]
+    fn drops_unused_entries_instead_of_growing() {
+        let mut blocks = PalettedBlocks::with_max_palette_bits(64, 2);
+        let types = [
+            BlockType::Stone,
+            BlockType::Grass,
+            BlockType::Wood,
+            BlockType::Leaf,
+            BlockType::Water,
+            BlockType::Sand,
+            BlockType::Gravel,
+            BlockType::Planks,
+        ];
+        // Only two block types are ever in the section at once, but every round brings a new one
+        for round in 0..types.len() - 1 {
+            for index in 0..64 {
+                blocks.set(index, (types[round + index % 2].into(), BlockData::None));
+            }
+            assert!(blocks.palette_len().unwrap() <= 4);
+            for index in 0..64 {
+                assert_eq!(
+                    blocks.get_block_type(index),
+                    types[round + index % 2],
+                    "round {}",
+                    round
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn ignores_out_of_bounds() {
+        let mut blocks = PalettedBlocks::new(16);
+        blocks.set(16, (BlockType::Stone.into(), BlockData::None));
+        assert_eq!(blocks.get(16), (BlockType::Void.into(), BlockData::None));
+        assert_eq!(blocks.palette_len(), Some(1));
+    }
+}
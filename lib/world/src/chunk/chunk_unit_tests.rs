@@ -1,6 +1,9 @@
 use super::{Chunk, ChunkPos, InnerChunkPos};
 use crate::{
-    block::{BlockData, BlockType},
+    block::{
+        block_state::{BlockProperty, BlockState},
+        BlockData, BlockType,
+    },
     chunk::ChunkBlock,
     world::World,
 };
@@ -13,19 +16,19 @@ fn gets_all_blocks() {
 
     chunk.add_block(ChunkBlock {
         pos: InnerChunkPos::new(0, 0, 0),
-        block_type: BlockType::Cloud,
+        block_type: BlockType::Cloud.into(),
         extra_data: BlockData::None,
     });
 
     chunk.add_block(ChunkBlock {
         pos: InnerChunkPos::new(1, 2, 3),
-        block_type: BlockType::Stone,
+        block_type: BlockType::Stone.into(),
         extra_data: BlockData::None,
     });
 
     chunk.add_block(ChunkBlock {
         pos: InnerChunkPos::new(15, 0, 15),
-        block_type: BlockType::Stone,
+        block_type: BlockType::Stone.into(),
         extra_data: BlockData::None,
     });
 
@@ -52,7 +55,7 @@ fn stores_block() {
 
     let block = ChunkBlock {
         pos: inner_chunk_pos,
-        block_type: BlockType::Cloud,
+        block_type: BlockType::Cloud.into(),
         extra_data: BlockData::None,
     };
 
@@ -84,7 +87,7 @@ fn stores_first_block() {
 
     chunk.add_block(ChunkBlock {
         pos: inner_chunk_pos,
-        block_type: BlockType::Cloud,
+        block_type: BlockType::Cloud.into(),
         extra_data: BlockData::None,
     });
 
@@ -102,7 +105,7 @@ fn stores_last_block() {
 
     chunk.add_block(ChunkBlock {
         pos: inner_chunk_pos,
-        block_type: BlockType::Cloud,
+        block_type: BlockType::Cloud.into(),
         extra_data: BlockData::None,
     });
 
@@ -120,7 +123,7 @@ fn deletes_blocks() {
 
     chunk.add_block(ChunkBlock {
         pos: inner_chunk_pos,
-        block_type: BlockType::Cloud,
+        block_type: BlockType::Cloud.into(),
         extra_data: BlockData::None,
     });
 
@@ -130,3 +133,29 @@ fn deletes_blocks() {
 
     assert_eq!(block, BlockType::Void)
 }
+
+#[test]
+fn serializes_block_data() {
+    let mut chunk = Chunk::new(ChunkPos::new(1, 2));
+    let state = BlockState::default().with(BlockProperty::Level, 4).unwrap();
+
+    chunk.add_block(ChunkBlock {
+        pos: InnerChunkPos::new(3, 2, 1),
+        block_type: BlockType::Water.into(),
+        extra_data: BlockData::State(state),
+    });
+    chunk.add_block(ChunkBlock {
+        pos: InnerChunkPos::new(0, 40, 0),
+        block_type: BlockType::Stone.into(),
+        extra_data: BlockData::None,
+    });
+
+    let json = serde_json::to_string(&chunk).unwrap();
+    let decoded: Chunk = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded.get_all_blocks(), chunk.get_all_blocks());
+
+    // Sections without block data are still plain lists of block types
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert!(value["sections"][0]["data"].is_array());
+    assert!(value["sections"][2].is_array());
+}
@@ -150,6 +150,18 @@ impl Direction {
         Directions::create_for_direction(*self)
     }
 
+    /** The direction along the axis, outward being the positive end */
+    pub fn from_axis(axis: Axis, outward: bool) -> Direction {
+        match (axis, outward) {
+            (Axis::X, true) => Direction::East,
+            (Axis::X, false) => Direction::West,
+            (Axis::Y, true) => Direction::Up,
+            (Axis::Y, false) => Direction::Down,
+            (Axis::Z, true) => Direction::North,
+            (Axis::Z, false) => Direction::South,
+        }
+    }
+
     pub fn to_axis(&self) -> Axis {
         match self {
             Direction::North | Direction::South => Axis::Z,
@@ -0,0 +1,143 @@
+use crate::{
+    geometry::{rect3::Rect3, sweep::AxisContacts},
+    vec::Vec3,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+mod entity_duct;
+
+pub type EntityId = u32;
+
+/** Blocks per second per second */
+pub const DEFAULT_GRAVITY: f32 = 28.0;
+/** How much of its sideways speed an entity on the ground loses every second */
+pub const DEFAULT_FRICTION: f32 = 10.0;
+/** Pushes a bit harder than gravity, so entities float with a little of themselves out of the fluid */
+pub const DEFAULT_BUOYANCY: f32 = 1.2;
+/** How much of its speed a fully submerged entity loses every second */
+pub const DEFAULT_FLUID_DRAG: f32 = 4.0;
+
+fn default_gravity() -> f32 {
+    DEFAULT_GRAVITY
+}
+
+fn default_friction() -> f32 {
+    DEFAULT_FRICTION
+}
+
+fn default_buoyancy() -> f32 {
+    DEFAULT_BUOYANCY
+}
+
+fn default_fluid_drag() -> f32 {
+    DEFAULT_FLUID_DRAG
+}
+
+fn default_velocity() -> Vec3<f32> {
+    Vec3::new(0.0, 0.0, 0.0)
+}
+
+/** Something that moves through the world and collides with blocks, like a player or a fireball */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entity {
+    /** Given out by the store when the entity is spawned */
+    #[serde(default)]
+    pub id: EntityId,
+    pub bounds: Rect3,
+    /** Blocks per second */
+    #[serde(default = "default_velocity")]
+    pub velocity: Vec3<f32>,
+    #[serde(default)]
+    pub on_ground: bool,
+    /** 0 for things that fly straight, like fireballs */
+    #[serde(default = "default_gravity")]
+    pub gravity: f32,
+    #[serde(default = "default_friction")]
+    pub friction: f32,
+    /** How tall a block the entity can walk up onto without jumping */
+    #[serde(default)]
+    pub step_height: f32,
+    /** How hard fluid pushes up on the entity when it is fully under, as a multiple of its gravity */
+    #[serde(default = "default_buoyancy")]
+    pub buoyancy: f32,
+    #[serde(default = "default_fluid_drag")]
+    pub fluid_drag: f32,
+}
+
+impl Entity {
+    pub fn new(bounds: Rect3) -> Entity {
+        Entity {
+            id: 0,
+            bounds,
+            velocity: default_velocity(),
+            on_ground: false,
+            gravity: DEFAULT_GRAVITY,
+            friction: DEFAULT_FRICTION,
+            step_height: 0.0,
+            buoyancy: DEFAULT_BUOYANCY,
+            fluid_drag: DEFAULT_FLUID_DRAG,
+        }
+    }
+}
+
+/** What happened to an entity during a step */
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EntityStep {
+    pub id: EntityId,
+    pub contacts: AxisContacts,
+    /** How much of the entity was in fluid at the start of the step, 0 to 1 */
+    pub submersion: f32,
+}
+
+/** Every entity in a world, kept in id order so steps run the same way on the server and client */
+#[derive(Default)]
+pub struct EntityStore {
+    entities: BTreeMap<EntityId, Entity>,
+    next_id: EntityId,
+}
+
+impl EntityStore {
+    /** Adds the entity under a new id, which is returned */
+    pub fn spawn(&mut self, mut entity: Entity) -> EntityId {
+        self.next_id += 1;
+        entity.id = self.next_id;
+        self.entities.insert(entity.id, entity);
+        self.next_id
+    }
+
+    pub fn get(&self, id: EntityId) -> Option<&Entity> {
+        self.entities.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
+        self.entities.get_mut(&id)
+    }
+
+    /** Replaces the entity with the same id. Does nothing if it isn't in the store */
+    pub fn update(&mut self, entity: Entity) {
+        if let Some(stored) = self.entities.get_mut(&entity.id) {
+            *stored = entity;
+        }
+    }
+
+    pub fn remove(&mut self, id: EntityId) -> Option<Entity> {
+        self.entities.remove(&id)
+    }
+
+    pub fn ids(&self) -> Vec<EntityId> {
+        self.entities.keys().copied().collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Entity> {
+        self.entities.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
@@ -0,0 +1,39 @@
+use super::{Entity, EntityId};
+use crate::{vec::Vec3, world::World};
+use serde_wasm_bindgen::{from_value, to_value, Error};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+impl World {
+    /** Takes `{ bounds, velocity?, gravity?, friction? }` and returns the new entity's id */
+    pub fn spawn_entity_wasm(&mut self, entity: JsValue) -> Result<EntityId, Error> {
+        let entity: Entity = from_value(entity)?;
+        Ok(self.spawn_entity(entity))
+    }
+
+    pub fn get_entity_wasm(&self, id: EntityId) -> Result<JsValue, Error> {
+        to_value(&self.get_entity(id))
+    }
+
+    pub fn set_entity_velocity_wasm(
+        &mut self,
+        id: EntityId,
+        velocity: JsValue,
+    ) -> Result<(), Error> {
+        let velocity: Vec3<f32> = from_value(velocity)?;
+        let entity = self
+            .get_entity_mut(id)
+            .ok_or_else(|| Error::new(format!("No entity with id {}", id)))?;
+        entity.velocity = velocity;
+        Ok(())
+    }
+
+    pub fn remove_entity_wasm(&mut self, id: EntityId) -> bool {
+        self.remove_entity(id).is_some()
+    }
+
+    /** Returns the contacts of every entity stepped */
+    pub fn step_entities_wasm(&mut self, dt: f32) -> Result<JsValue, Error> {
+        to_value(&self.step_entities(dt))
+    }
+}
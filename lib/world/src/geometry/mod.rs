@@ -1,4 +1,6 @@
 pub mod line_segment;
 pub mod ray;
+pub mod raycast;
 pub mod rect3;
 pub mod rotation;
+pub mod sweep;
@@ -1,11 +1,10 @@
-use super::{line_segment::LineSegment, rotation::SphericalRotation};
+use super::{
+    raycast::{RaycastFilter, RaycastHit},
+    rotation::SphericalRotation,
+};
 use crate::{
-    chunk::chunk_mesh::BlockMesh,
-    direction::Direction,
-    plane::WorldPlane,
-    positions::FineWorldPos,
-    vec::Vec3,
-    world::{world_block::WorldBlock, World},
+    chunk::chunk_mesh::BlockMesh, plane::WorldPlane, positions::FineWorldPos, vec::Vec3,
+    world::World,
 };
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
@@ -20,22 +19,6 @@ pub struct Ray {
     pub rot: SphericalRotation,
 }
 
-#[derive(PartialEq, Debug, Serialize, Deserialize)]
-pub struct LookingAt {
-    /**
-     * The block a camera is pointing at
-    	*/
-    block: WorldBlock,
-    /**
-     * The face of the block that is being looked at
-     */
-    face: Direction,
-    /**
-     * How far the face is away from the camera
-     */
-    distance: f32,
-}
-
 impl Ray {
     pub fn move_forward_mut(&mut self, amount: f32) {
         let rot_vec: Vec3<f32> = self.rot.into();
@@ -86,25 +69,25 @@ impl Ray {
     }
 }
 
+/** How far away players can pick blocks */
+pub const POINTING_DISTANCE: f32 = 13.0;
+
 impl World {
-    pub fn get_pointed_at_block(&self, ray: Ray) -> Option<LookingAt> {
-        let line_segment = LineSegment {
-            start_pos: ray.pos,
-            end_pos: ray.move_forward(13.0).pos,
+    /** The block the camera is looking at, looking straight through fluids */
+    pub fn get_pointed_at_block(&self, ray: Ray) -> Option<RaycastHit> {
+        let filter = RaycastFilter {
+            ignore_fluids: true,
+            ignore_intangible: false,
         };
-
-        self.get_line_segment_intersection_info(line_segment)
-            .map(|info| LookingAt {
-                block: self.get_block(&info.world_plane.world_pos),
-                face: info.world_plane.direction,
-                distance: info.distance,
-            })
+        self.raycast(ray.pos, ray.rot.into(), POINTING_DISTANCE, |block| {
+            filter.can_hit(block)
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{LookingAt, Ray};
+    use super::Ray;
     use crate::{
         block::{BlockData, BlockType},
         chunk::{chunk_mesh::BlockMesh, Chunk},
@@ -209,21 +192,30 @@ mod tests {
         );
     }
 
-    fn finds_block_being_pointed_at(block: &WorldBlock, ray: Ray, expected: LookingAt) -> () {
+    fn finds_block_being_pointed_at(
+        block: &WorldBlock,
+        ray: Ray,
+        face: Direction,
+        distance: f32,
+        place_pos: WorldPos,
+    ) {
         let mut world = World::default();
         let chunk = Chunk::new(block.world_pos.to_chunk_pos());
         world.insert_chunk(chunk);
         world.add_block(&block).unwrap();
-        let actual = world.get_pointed_at_block(ray);
+        let actual = world.get_pointed_at_block(ray).unwrap();
 
-        assert_eq!(actual, Some(expected));
+        assert_eq!(actual.block, *block);
+        assert_eq!(actual.face, face);
+        assert!((actual.distance - distance).abs() < 0.0001);
+        assert_eq!(actual.place_pos, place_pos);
     }
 
     #[test]
     fn test_pointing_at() {
         let block = WorldBlock {
             world_pos: WorldPos::new(0, 0, 0),
-            block_type: BlockType::Stone,
+            block_type: BlockType::Stone.into(),
             extra_data: BlockData::None,
         };
 
@@ -233,16 +225,14 @@ mod tests {
                 pos: FineWorldPos::new(0.5, 1.5, 0.5),
                 rot: Direction::Down.into(),
             },
-            LookingAt {
-                block,
-                face: Direction::Up,
-                distance: 0.5,
-            },
+            Direction::Up,
+            0.5,
+            WorldPos::new(0, 1, 0),
         );
 
         let block = WorldBlock {
             world_pos: WorldPos::new(2, 0, 0),
-            block_type: BlockType::Stone,
+            block_type: BlockType::Stone.into(),
             extra_data: BlockData::None,
         };
 
@@ -252,11 +242,9 @@ mod tests {
                 pos: FineWorldPos::new(0.5, 0.5, 0.5),
                 rot: Direction::East.into(),
             },
-            LookingAt {
-                block,
-                face: Direction::West,
-                distance: 1.5,
-            },
+            Direction::West,
+            1.5,
+            WorldPos::new(1, 0, 0),
         )
     }
 }
@@ -0,0 +1,276 @@
+use crate::{
+    block::BlockType,
+    direction::{Axis, Direction},
+    positions::{FineWorldPos, WorldPos},
+    vec::Vec3,
+    world::{world_block::WorldBlock, World},
+};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/** What a raycast passes straight through, on top of void which it never hits */
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RaycastFilter {
+    pub ignore_fluids: bool,
+    pub ignore_intangible: bool,
+}
+
+impl RaycastFilter {
+    pub fn can_hit(&self, block: &WorldBlock) -> bool {
+        let metadata = block.get_metadata();
+        !(self.ignore_fluids && metadata.fluid || self.ignore_intangible && metadata.intangible)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct RaycastHit {
+    pub block: WorldBlock,
+    /** The face of the block the ray went in through */
+    pub face: Direction,
+    /** Where the ray touched the face */
+    pub point: FineWorldPos,
+    /** How far along the ray the point is */
+    pub distance: f32,
+    /** The block in front of the face, where a block placed against it would go */
+    pub place_pos: WorldPos,
+}
+
+const AXES: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+
+impl World {
+    /**
+     * Walks the blocks along the ray in order, one block boundary at a time (Amanatides and Woo),
+     * returning the first block that isn't void and that can_hit accepts.
+     * The block the ray starts in is skipped, so a camera inside leaves can still look out of them.
+     */
+    pub fn raycast<F>(
+        &self,
+        origin: FineWorldPos,
+        direction: Vec3<f32>,
+        max_distance: f32,
+        can_hit: F,
+    ) -> Option<RaycastHit>
+    where
+        F: Fn(&WorldBlock) -> bool,
+    {
+        // The walk only stops once it passes max_distance, so it has to be reachable
+        if !max_distance.is_finite() || max_distance <= 0.0 {
+            return None;
+        }
+        // An origin off at infinity has no cell to start the walk from
+        if ![origin.x, origin.y, origin.z].iter().all(|c| c.is_finite()) {
+            return None;
+        }
+        let length = direction.get_mag();
+        if length == 0.0 || !length.is_finite() {
+            return None;
+        }
+        let direction = direction.map(|component| component / length);
+
+        let mut cell = origin.map(|component| component.floor() as i32);
+        let mut step = Vec3::new(0, 0, 0);
+        // How far along the ray the next boundary on each axis is, and how far apart the boundaries are
+        let mut next_boundary = Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut boundary_spacing = Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        for axis in &AXES {
+            let component = direction.get_component_from_axis(*axis);
+            let start = origin.get_component_from_axis(*axis);
+            let start_cell = cell.get_component_from_axis(*axis) as f32;
+            if component > 0.0 {
+                step.set_component_from_axis(*axis, 1);
+                next_boundary
+                    .set_component_from_axis(*axis, (start_cell + 1.0 - start) / component);
+            } else if component < 0.0 {
+                step.set_component_from_axis(*axis, -1);
+                next_boundary.set_component_from_axis(*axis, (start - start_cell) / -component);
+            } else {
+                continue;
+            }
+            boundary_spacing.set_component_from_axis(*axis, 1.0 / component.abs());
+        }
+
+        loop {
+            let axis = AXES
+                .iter()
+                .copied()
+                .min_by(|a, b| {
+                    next_boundary
+                        .get_component_from_axis(*a)
+                        .total_cmp(&next_boundary.get_component_from_axis(*b))
+                })
+                .unwrap_or(Axis::X);
+            let distance = next_boundary.get_component_from_axis(axis);
+            if distance > max_distance {
+                return None;
+            }
+
+            let axis_step = step.get_component_from_axis(axis);
+            cell.set_component_from_axis(axis, cell.get_component_from_axis(axis) + axis_step);
+            next_boundary.set_component_from_axis(
+                axis,
+                distance + boundary_spacing.get_component_from_axis(axis),
+            );
+
+            // Moving up an axis goes into a block through its face pointing back down it
+            let face = Direction::from_axis(axis, axis_step < 0);
+            let block = self.get_block(&cell);
+            if block.block_type != BlockType::Void && can_hit(&block) {
+                return Some(RaycastHit {
+                    block,
+                    face,
+                    point: origin + direction * distance,
+                    distance,
+                    place_pos: cell.move_direction(&face),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RaycastFilter;
+    use crate::{
+        block::{BlockData, BlockType},
+        chunk::Chunk,
+        direction::Direction,
+        positions::{ChunkPos, FineWorldPos, WorldPos},
+        vec::Vec3,
+        world::{world_block::WorldBlock, World},
+    };
+
+    fn make_world(blocks: &[(BlockType, i32, i32, i32)]) -> World {
+        let mut world = World::default();
+        world.insert_chunk(Chunk::new(ChunkPos { x: 0, y: 0 }));
+        for (block_type, x, y, z) in blocks {
+            world
+                .add_block(&WorldBlock {
+                    block_type: (*block_type).into(),
+                    extra_data: BlockData::None,
+                    world_pos: WorldPos::new(*x, *y, *z),
+                })
+                .unwrap();
+        }
+        world
+    }
+
+    #[test]
+    fn hits_the_first_block_on_a_diagonal() {
+        let world = make_world(&[(BlockType::Stone, 3, 2, 3), (BlockType::Stone, 5, 2, 5)]);
+        let hit = world
+            .raycast(
+                FineWorldPos::new(0.5, 2.5, 0.2),
+                Vec3::new(1.0, 0.0, 1.0),
+                20.0,
+                |_| true,
+            )
+            .unwrap();
+
+        assert_eq!(hit.block.world_pos, WorldPos::new(3, 2, 3));
+        // It crosses x = 3 before z = 3, so it goes in through the south face
+        assert_eq!(hit.face, Direction::South);
+        assert_eq!(hit.place_pos, WorldPos::new(3, 2, 2));
+        assert!(hit.point.equal(&FineWorldPos::new(3.3, 2.5, 3.0)));
+        assert!((hit.distance - 2.8 * 2.0_f32.sqrt()).abs() < 0.001);
+
+        // Just out of reach
+        assert!(world
+            .raycast(
+                FineWorldPos::new(0.5, 2.5, 0.2),
+                Vec3::new(1.0, 0.0, 1.0),
+                3.5,
+                |_| true,
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn rays_past_a_corner_go_in_through_the_right_face() {
+        let world = make_world(&[(BlockType::Stone, 1, 0, 1)]);
+        let direction = Vec3::new(1.0, 0.0, 1.0);
+
+        // Crosses z = 1 just before x = 1
+        let hit = world
+            .raycast(FineWorldPos::new(0.0, 0.5, 0.05), direction, 10.0, |_| true)
+            .unwrap();
+        assert_eq!(hit.block.world_pos, WorldPos::new(1, 0, 1));
+        assert_eq!(hit.face, Direction::West);
+        assert_eq!(hit.place_pos, WorldPos::new(0, 0, 1));
+
+        // Crosses x = 1 just before z = 1
+        let hit = world
+            .raycast(FineWorldPos::new(0.0, 0.5, -0.05), direction, 10.0, |_| {
+                true
+            })
+            .unwrap();
+        assert_eq!(hit.block.world_pos, WorldPos::new(1, 0, 1));
+        assert_eq!(hit.face, Direction::South);
+        assert_eq!(hit.place_pos, WorldPos::new(1, 0, 0));
+    }
+
+    #[test]
+    fn rays_without_a_finite_reach_hit_nothing() {
+        let world = make_world(&[(BlockType::Stone, 1, 0, 1)]);
+        let origin = FineWorldPos::new(1.5, 3.5, 1.5);
+        let down = Vec3::new(0.0, -1.0, 0.0);
+
+        for max_distance in [f32::INFINITY, f32::NAN, 0.0, -5.0] {
+            assert!(world
+                .raycast(origin, down, max_distance, |_| true)
+                .is_none());
+        }
+        assert!(world.raycast(origin, down, 5.0, |_| true).is_some());
+    }
+
+    #[test]
+    fn rays_from_a_non_finite_origin_hit_nothing() {
+        let world = make_world(&[(BlockType::Stone, 1, 0, 1)]);
+        let down = Vec3::new(0.0, -1.0, 0.0);
+
+        for bad in [f32::INFINITY, f32::NEG_INFINITY, f32::NAN, -f32::NAN] {
+            for origin in [
+                FineWorldPos::new(bad, 3.5, 1.5),
+                FineWorldPos::new(1.5, bad, 1.5),
+                FineWorldPos::new(1.5, 3.5, bad),
+            ] {
+                assert!(world.raycast(origin, down, 5.0, |_| true).is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn filters_skip_fluids_and_intangible_blocks() {
+        let world = make_world(&[
+            (BlockType::Water, 1, 6, 1),
+            (BlockType::Torch, 1, 4, 1),
+            (BlockType::Stone, 1, 2, 1),
+        ]);
+        let origin = FineWorldPos::new(1.5, 8.5, 1.5);
+        let down = Vec3::new(0.0, -1.0, 0.0);
+
+        let hit = world.raycast(origin, down, 10.0, |_| true).unwrap();
+        assert_eq!(hit.block.block_type, BlockType::Water);
+
+        let filter = RaycastFilter {
+            ignore_fluids: true,
+            ignore_intangible: false,
+        };
+        let hit = world
+            .raycast(origin, down, 10.0, |block| filter.can_hit(block))
+            .unwrap();
+        assert_eq!(hit.block.block_type, BlockType::Torch);
+
+        let filter = RaycastFilter {
+            ignore_fluids: true,
+            ignore_intangible: true,
+        };
+        let hit = world
+            .raycast(origin, down, 10.0, |block| filter.can_hit(block))
+            .unwrap();
+        assert_eq!(hit.block.block_type, BlockType::Stone);
+        assert_eq!(hit.face, Direction::Up);
+        assert_eq!(hit.place_pos, WorldPos::new(1, 3, 1));
+        assert_eq!(hit.distance, 5.5);
+    }
+}
@@ -1,9 +1,8 @@
-use super::line_segment::{LineSegment, LineSegmentIntersectionInfo};
+use super::line_segment::LineSegment;
 use crate::positions::WorldPos;
 use crate::{positions::FineWorldPos, vec::Vec3, world::World};
 use serde::{Deserialize, Serialize};
 use serde_wasm_bindgen::{from_value, to_value};
-use std::cmp::Ordering;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsValue;
 
@@ -13,8 +12,6 @@ pub struct Rect3 {
     pub dim: Vec3<f32>,
 }
 
-static DISTANCE_EPSILON: f32 = 0.03;
-
 impl Rect3 {
     pub fn get_all_points(&self) -> [FineWorldPos; 8] {
         let x = self.pos.x;
@@ -96,89 +93,9 @@ impl Rect3 {
 }
 
 impl World {
-    fn get_moving_rect3_intersection_info(
-        &self,
-        rect: &Rect3,
-        end_pos: FineWorldPos,
-    ) -> Option<LineSegmentIntersectionInfo> {
-        println!(
-            "Getting intersection info for moving rect: {:?}, end_pos: {:?}",
-            rect, end_pos
-        );
-
-        let diff = end_pos - rect.pos;
-
-        println!("diff: {:?}", diff);
-
-        let line_segments = rect.get_all_points().map(|point| LineSegment {
-            start_pos: point,
-            end_pos: point + diff,
-        });
-
-        for segment in &line_segments {
-            println!("segment: {:?}", segment)
-        }
-
-        let intersection = line_segments
-            .iter()
-            .filter_map(|seg| self.get_line_segment_intersection_info(*seg))
-            .min_by(|a, b| {
-                a.distance
-                    .partial_cmp(&b.distance)
-                    .unwrap_or(Ordering::Equal)
-            });
-        intersection
-    }
-
+    /** Moves the box towards end_pos, stopping it against solid blocks on each axis */
     pub fn move_rect3(&self, rect: &Rect3, end_pos: FineWorldPos) -> FineWorldPos {
-        let new_pos_from_info = |info: LineSegmentIntersectionInfo, end_pos: FineWorldPos| {
-            let hit_axis = info.world_plane.direction.to_axis();
-            let mut new_pos = end_pos.clone();
-            let outward = info.world_plane.direction.is_outward();
-            let rect_dim_dir = rect.dim.get_component_from_axis(hit_axis);
-
-            let eplison_diff = if outward {
-                DISTANCE_EPSILON
-            } else {
-                -(rect_dim_dir + DISTANCE_EPSILON)
-            };
-
-            let hit_plane_pos = info.world_plane.get_relative_y() as f32;
-
-            let new_axis_pos = eplison_diff + hit_plane_pos;
-
-            // println!(
-            //     "outward: {}, eplison_diff: {}, hit_plane_pos: {}, new_axis_pos: {}",
-            //     outward, eplison_diff, hit_plane_pos, new_axis_pos
-            // );
-
-            new_pos.set_component_from_axis(hit_axis, new_axis_pos);
-            new_pos
-        };
-
-        let mut current_end_pos = end_pos;
-
-        for _ in 0..3 {
-            let intersection = self.get_moving_rect3_intersection_info(rect, current_end_pos);
-
-            if let Some(info) = intersection {
-                // unsafe {
-                //     web_sys::console::log_2(&"intersection".into(), &to_value(&info).unwrap())
-                // }
-                println!("intersection: {:?}", info);
-
-                current_end_pos = new_pos_from_info(info, current_end_pos);
-                // unsafe {
-                //     web_sys::console::log_2(&"new_pos".into(), &to_value(&current_end_pos).unwrap())
-                // }
-                println!("new_pos: {:?}", current_end_pos);
-            } else {
-                // If no intersection is found, break the loop
-                break;
-            }
-        }
-
-        current_end_pos
+        self.sweep_rect3(rect, end_pos - rect.pos, 0.0).pos
     }
 
     pub fn get_rect3_intersecting_blocks(&self, rect: &Rect3) -> Vec<WorldPos> {
@@ -212,6 +129,29 @@ impl World {
             .unwrap_or(JsValue::NULL)
     }
 
+    /** Returns the swept position, the faces that hit blocks and if the box stepped up */
+    pub fn sweep_rect3_wasm(
+        &self,
+        rect: JsValue,
+        displacement: JsValue,
+        step_height: f32,
+    ) -> JsValue {
+        from_value(rect)
+            .and_then(|rect: Rect3| {
+                from_value(displacement).and_then(|displacement: Vec3<f32>| {
+                    to_value(&self.sweep_rect3(&rect, displacement, step_height))
+                })
+            })
+            .unwrap_or(JsValue::NULL)
+    }
+
+    /** How much of the box is in fluid, 0 to 1 */
+    pub fn get_fluid_submersion_wasm(&self, rect: JsValue) -> Result<f32, JsValue> {
+        from_value(rect)
+            .map(|rect: Rect3| self.get_fluid_submersion(&rect))
+            .map_err(JsValue::from)
+    }
+
     pub fn get_rect3_intersecting_blocks_wasm(&self, rect: JsValue) -> JsValue {
         from_value(rect)
             .and_then(|rect: Rect3| {
@@ -233,8 +173,6 @@ pub mod tests {
         world::{world_block::WorldBlock, World},
     };
 
-    use super::DISTANCE_EPSILON;
-
     fn test_try_moving_block(
         block: WorldBlock,
         rect: Rect3,
@@ -288,7 +226,7 @@ pub mod tests {
     fn test_not_moving() {
         test_try_moving_block(
             WorldBlock {
-                block_type: BlockType::Leaf,
+                block_type: BlockType::Leaf.into(),
                 extra_data: BlockData::None,
                 world_pos: WorldPos::new(0, 0, 0),
             },
@@ -317,7 +255,7 @@ pub mod tests {
     fn try_move_down_into_block() {
         test_try_moving_block(
             WorldBlock {
-                block_type: BlockType::Leaf,
+                block_type: BlockType::Leaf.into(),
                 extra_data: BlockData::None,
                 world_pos: WorldPos::new(0, 0, 0),
             },
@@ -336,7 +274,7 @@ pub mod tests {
             },
             FineWorldPos {
                 x: 0.5,
-                y: 1.0 + DISTANCE_EPSILON,
+                y: 1.0,
                 z: 0.5,
             },
         );
@@ -346,7 +284,7 @@ pub mod tests {
     fn try_move_into_block_neg_z() {
         test_try_moving_block(
             WorldBlock {
-                block_type: BlockType::Leaf,
+                block_type: BlockType::Leaf.into(),
                 extra_data: BlockData::None,
                 world_pos: WorldPos::new(1, 1, 1),
             },
@@ -366,12 +304,12 @@ pub mod tests {
             FineWorldPos {
                 x: 0.5,
                 y: 1.3,
-                z: 2.0 + DISTANCE_EPSILON,
+                z: 2.0,
             },
         );
         test_try_moving_block(
             WorldBlock {
-                block_type: BlockType::Leaf,
+                block_type: BlockType::Leaf.into(),
                 extra_data: BlockData::None,
                 world_pos: WorldPos::new(-3, 1, -3),
             },
@@ -391,7 +329,7 @@ pub mod tests {
             FineWorldPos {
                 x: -3.5,
                 y: 1.3,
-                z: -2.0 + DISTANCE_EPSILON,
+                z: -2.0,
             },
         );
     }
@@ -400,7 +338,7 @@ pub mod tests {
     fn try_move_into_block_pos_z() {
         test_try_moving_block(
             WorldBlock {
-                block_type: BlockType::Leaf,
+                block_type: BlockType::Leaf.into(),
                 extra_data: BlockData::None,
                 world_pos: WorldPos::new(0, 1, 0),
             },
@@ -420,7 +358,7 @@ pub mod tests {
             FineWorldPos {
                 x: 0.5,
                 y: 1.5,
-                z: -0.8 - DISTANCE_EPSILON,
+                z: -0.8,
             },
         );
     }
@@ -429,7 +367,7 @@ pub mod tests {
     fn try_move_into_east_block() {
         test_try_moving_block(
             WorldBlock {
-                block_type: BlockType::Leaf,
+                block_type: BlockType::Leaf.into(),
                 extra_data: BlockData::None,
                 world_pos: WorldPos::new(1, 1, 0),
             },
@@ -447,14 +385,14 @@ pub mod tests {
                 z: -0.1,
             },
             FineWorldPos {
-                x: 0.0 - DISTANCE_EPSILON,
+                x: 0.0,
                 y: 1.5,
                 z: -0.1,
             },
         );
         test_try_moving_block(
             WorldBlock {
-                block_type: BlockType::Leaf,
+                block_type: BlockType::Leaf.into(),
                 extra_data: BlockData::None,
                 world_pos: WorldPos::new(5, 1, 0),
             },
@@ -472,7 +410,7 @@ pub mod tests {
                 z: 0.019,
             },
             FineWorldPos {
-                x: 4.0 - DISTANCE_EPSILON,
+                x: 4.0,
                 y: 1.03,
                 z: 0.019,
             },
@@ -481,64 +419,64 @@ pub mod tests {
 
     #[test]
     fn try_move_into_block_neg_x() {
-        // test_try_moving_block(
-        //     WorldBlock {
-        //         block_type: BlockType::Leaf,
-        //         extra_data: BlockData::None,
-        //         world_pos: WorldPos::new(-10, 1, -7),
-        //     },
-        //     Rect3 {
-        //         pos: FineWorldPos {
-        //             x: -11.1,
-        //             y: 1.5,
-        //             z: -6.5,
-        //         },
-        //         dim: Vec3::new(1.0, 1.0, 1.0),
-        //     },
-        //     FineWorldPos {
-        //         x: -10.9,
-        //         y: 1.5,
-        //         z: -6.5,
-        //     },
-        //     FineWorldPos {
-        //         x: -11.0 - DISTANCE_EPSILON,
-        //         y: 1.5,
-        //         z: 0.5,
-        //     },
-        // );
-
-        // test_try_moving_block(
-        //     WorldBlock {
-        //         block_type: BlockType::Gold,
-        //         extra_data: BlockData::None,
-        //         world_pos: WorldPos::new(-8, 1, 3),
-        //     },
-        //     Rect3 {
-        //         pos: FineWorldPos {
-        //             x: -6.6,
-        //             y: 1.5,
-        //             z: 3.5,
-        //         },
-        //         dim: Vec3::new(1.0, 1.0, 1.0),
-        //     },
-        //     FineWorldPos {
-        //         x: -7.1,
-        //         y: 1.5,
-        //         z: 3.5,
-        //     },
-        //     FineWorldPos {
-        //         x: -7.0 + DISTANCE_EPSILON,
-        //         y: 1.5,
-        //         z: 3.5,
-        //     },
-        // );
+        test_try_moving_block(
+            WorldBlock {
+                block_type: BlockType::Leaf.into(),
+                extra_data: BlockData::None,
+                world_pos: WorldPos::new(-10, 1, -7),
+            },
+            Rect3 {
+                pos: FineWorldPos {
+                    x: -11.1,
+                    y: 1.5,
+                    z: -6.5,
+                },
+                dim: Vec3::new(1.0, 1.0, 1.0),
+            },
+            FineWorldPos {
+                x: -10.9,
+                y: 1.5,
+                z: -6.5,
+            },
+            FineWorldPos {
+                x: -11.0,
+                y: 1.5,
+                z: -6.5,
+            },
+        );
+
+        test_try_moving_block(
+            WorldBlock {
+                block_type: BlockType::Gold.into(),
+                extra_data: BlockData::None,
+                world_pos: WorldPos::new(-8, 1, 3),
+            },
+            Rect3 {
+                pos: FineWorldPos {
+                    x: -6.6,
+                    y: 1.5,
+                    z: 3.5,
+                },
+                dim: Vec3::new(1.0, 1.0, 1.0),
+            },
+            FineWorldPos {
+                x: -7.1,
+                y: 1.5,
+                z: 3.5,
+            },
+            FineWorldPos {
+                x: -7.0,
+                y: 1.5,
+                z: 3.5,
+            },
+        );
     }
 
     #[test]
     fn moving_ontop_of_blocks() {
         test_try_moving_block(
             WorldBlock {
-                block_type: BlockType::Leaf,
+                block_type: BlockType::Leaf.into(),
                 extra_data: BlockData::None,
                 world_pos: WorldPos::new(0, 0, 0),
             },
@@ -568,12 +506,12 @@ pub mod tests {
         test_try_moving_blocks(
             vec![
                 WorldBlock {
-                    block_type: BlockType::Leaf,
+                    block_type: BlockType::Leaf.into(),
                     extra_data: BlockData::None,
                     world_pos: WorldPos::new(-3, 1, -2),
                 },
                 WorldBlock {
-                    block_type: BlockType::Leaf,
+                    block_type: BlockType::Leaf.into(),
                     extra_data: BlockData::None,
                     world_pos: WorldPos::new(-2, 1, -3),
                 },
@@ -592,9 +530,9 @@ pub mod tests {
                 z: -2.5,
             },
             FineWorldPos {
-                x: -2.0 + DISTANCE_EPSILON,
+                x: -2.0,
                 y: 1.3,
-                z: -2.0 + DISTANCE_EPSILON,
+                z: -2.0,
             },
         )
     }
@@ -604,12 +542,12 @@ pub mod tests {
         test_try_moving_blocks(
             vec![
                 WorldBlock {
-                    block_type: BlockType::Leaf,
+                    block_type: BlockType::Leaf.into(),
                     extra_data: BlockData::None,
                     world_pos: WorldPos::new(3, 1, 2),
                 },
                 WorldBlock {
-                    block_type: BlockType::Leaf,
+                    block_type: BlockType::Leaf.into(),
                     extra_data: BlockData::None,
                     world_pos: WorldPos::new(2, 1, 3),
                 },
@@ -628,9 +566,9 @@ pub mod tests {
                 z: 2.3,
             },
             FineWorldPos {
-                x: 2.2 - DISTANCE_EPSILON,
+                x: 2.2,
                 y: 1.3,
-                z: 2.2 - DISTANCE_EPSILON,
+                z: 2.2,
             },
         )
     }
@@ -639,7 +577,7 @@ pub mod tests {
     fn jumping_off_block() {
         test_try_moving_block(
             WorldBlock {
-                block_type: BlockType::Leaf,
+                block_type: BlockType::Leaf.into(),
                 extra_data: BlockData::None,
                 world_pos: WorldPos::new(0, 0, 0),
             },
@@ -681,7 +619,7 @@ pub mod tests {
     fn get_rect3_intersecting_blocks_no_intersection() {
         test_get_rect3_intersecting_blocks(
             WorldBlock {
-                block_type: BlockType::Leaf,
+                block_type: BlockType::Leaf.into(),
                 extra_data: BlockData::None,
                 world_pos: WorldPos::new(0, 0, 0),
             },
@@ -701,7 +639,7 @@ pub mod tests {
     fn get_rect3_intersecting_blocks_intersection() {
         test_get_rect3_intersecting_blocks(
             WorldBlock {
-                block_type: BlockType::Leaf,
+                block_type: BlockType::Leaf.into(),
                 extra_data: BlockData::None,
                 world_pos: WorldPos::new(0, 0, 0),
             },
@@ -0,0 +1,371 @@
+use super::rect3::Rect3;
+use crate::{
+    direction::{Axis, Direction},
+    positions::{FineWorldPos, WorldPos},
+    vec::Vec3,
+    world::World,
+};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+/** Boxes resolve their movement on y first so they land before sliding along the ground */
+pub const AXIS_ORDER: [Axis; 3] = [Axis::Y, Axis::X, Axis::Z];
+/** Faces closer than this count as touching rather than overlapping */
+const TOUCH_EPSILON: f32 = 0.0001;
+
+/**
+ * The side of a box that ran into a block on each axis during a move, if any.
+ * Landing on the ground is a Down contact on y.
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AxisContacts {
+    pub x: Option<Direction>,
+    pub y: Option<Direction>,
+    pub z: Option<Direction>,
+}
+
+impl AxisContacts {
+    pub fn get(&self, axis: Axis) -> Option<Direction> {
+        match axis {
+            Axis::X => self.x,
+            Axis::Y => self.y,
+            Axis::Z => self.z,
+        }
+    }
+
+    pub fn set(&mut self, axis: Axis, contact: Option<Direction>) {
+        match axis {
+            Axis::X => self.x = contact,
+            Axis::Y => self.y = contact,
+            Axis::Z => self.z = contact,
+        }
+    }
+
+    pub fn any(&self) -> bool {
+        self.x.is_some() || self.y.is_some() || self.z.is_some()
+    }
+}
+
+/** Where a swept box ended up */
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Sweep {
+    pub pos: FineWorldPos,
+    pub contacts: AxisContacts,
+    /** If the box climbed up onto a block to get where it is */
+    pub stepped_up: bool,
+}
+
+fn other_axes(axis: Axis) -> (Axis, Axis) {
+    match axis {
+        Axis::X => (Axis::Y, Axis::Z),
+        Axis::Y => (Axis::X, Axis::Z),
+        Axis::Z => (Axis::X, Axis::Y),
+    }
+}
+
+/** The block coordinates a span overlaps, leaving out blocks it only touches */
+fn overlapped_cells(min: f32, max: f32) -> RangeInclusive<i32> {
+    ((min + TOUCH_EPSILON).floor() as i32)..=((max - TOUCH_EPSILON).floor() as i32)
+}
+
+fn horizontal_distance(from: FineWorldPos, to: FineWorldPos) -> f32 {
+    (to.x - from.x).powi(2) + (to.z - from.z).powi(2)
+}
+
+impl World {
+    /** Boxes pass through void, intangible blocks like torches and fluids */
+    pub fn is_block_solid(&self, world_pos: &WorldPos) -> bool {
+        let metadata = self.get_block(world_pos).get_metadata();
+        !metadata.intangible && !metadata.fluid
+    }
+
+    /**
+     * How much of the box is inside fluid blocks, from 0 for dry to 1 for fully under.
+     * Fluid blocks count as full blocks whatever their level.
+     */
+    pub fn get_fluid_submersion(&self, rect: &Rect3) -> f32 {
+        let volume = rect.dim.x * rect.dim.y * rect.dim.z;
+        if volume <= 0.0 {
+            return 0.0;
+        }
+        let min = rect.pos;
+        let max = rect.pos + rect.dim;
+        // How much of the box's span along the axis falls inside the block at index
+        let overlap = |axis: Axis, index: i32| {
+            let start = min.get_component_from_axis(axis).max(index as f32);
+            let end = max.get_component_from_axis(axis).min(index as f32 + 1.0);
+            (end - start).max(0.0)
+        };
+
+        let mut submerged = 0.0;
+        for x in overlapped_cells(min.x, max.x) {
+            for y in overlapped_cells(min.y, max.y) {
+                for z in overlapped_cells(min.z, max.z) {
+                    if self.get_block(&WorldPos::new(x, y, z)).get_metadata().fluid {
+                        submerged +=
+                            overlap(Axis::X, x) * overlap(Axis::Y, y) * overlap(Axis::Z, z);
+                    }
+                }
+            }
+        }
+        (submerged / volume).min(1.0)
+    }
+
+    /** If any block in the slice of cells at index along the axis is solid */
+    fn is_slice_solid(&self, rect: &Rect3, axis: Axis, index: i32) -> bool {
+        let (a, b) = other_axes(axis);
+        let a_min = rect.pos.get_component_from_axis(a);
+        let b_min = rect.pos.get_component_from_axis(b);
+        let a_cells = overlapped_cells(a_min, a_min + rect.dim.get_component_from_axis(a));
+        let b_cells = overlapped_cells(b_min, b_min + rect.dim.get_component_from_axis(b));
+
+        let mut world_pos = WorldPos::new(0, 0, 0);
+        world_pos.set_component_from_axis(axis, index);
+        for a_index in a_cells {
+            world_pos.set_component_from_axis(a, a_index);
+            for b_index in b_cells.clone() {
+                world_pos.set_component_from_axis(b, b_index);
+                if self.is_block_solid(&world_pos) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /**
+     * How far the box can move along the axis before its leading face touches a solid block.
+     * Walks every slice of blocks the face passes through, so fast boxes can't skip over thin walls.
+     * Blocks the box already overlaps don't stop it, so it can always move out of them.
+     */
+    pub fn sweep_axis(&self, rect: &Rect3, axis: Axis, distance: f32) -> f32 {
+        // There are no slices to walk on the way to infinity, so the box stays put
+        if !distance.is_finite() {
+            return 0.0;
+        }
+        let min = rect.pos.get_component_from_axis(axis);
+        let max = min + rect.dim.get_component_from_axis(axis);
+        if distance > 0.0 {
+            let first = (max - TOUCH_EPSILON).ceil() as i32;
+            let last = (max + distance).ceil() as i32 - 1;
+            for index in first..=last {
+                if self.is_slice_solid(rect, axis, index) {
+                    return (index as f32 - max).clamp(0.0, distance);
+                }
+            }
+        } else if distance < 0.0 {
+            let first = (min + TOUCH_EPSILON).floor() as i32 - 1;
+            let last = (min + distance).floor() as i32;
+            for index in (last..=first).rev() {
+                if self.is_slice_solid(rect, axis, index) {
+                    return ((index + 1) as f32 - min).clamp(distance, 0.0);
+                }
+            }
+        }
+        distance
+    }
+
+    /** Moves the box one axis at a time in AXIS_ORDER, recording the sides that got stopped */
+    fn sweep_by_axis(&self, rect: &Rect3, displacement: Vec3<f32>) -> Sweep {
+        let mut pos = rect.pos;
+        let mut contacts = AxisContacts::default();
+        for axis in &AXIS_ORDER {
+            let distance = displacement.get_component_from_axis(*axis);
+            if distance == 0.0 {
+                continue;
+            }
+            let moved = self.sweep_axis(&Rect3 { pos, dim: rect.dim }, *axis, distance);
+            if (moved - distance).abs() > TOUCH_EPSILON {
+                contacts.set(*axis, Some(Direction::from_axis(*axis, distance > 0.0)));
+            }
+            pos.set_component_from_axis(*axis, pos.get_component_from_axis(*axis) + moved);
+        }
+        Sweep {
+            pos,
+            contacts,
+            stepped_up: false,
+        }
+    }
+
+    /**
+     * Moves the box by the displacement, stopping it against solid blocks on each axis.
+     * When a box that isn't moving up gets stopped sideways, it tries again from up to step_height higher
+     * and then settles back down, which lets players walk up slabs and stairs.
+     * The step is only taken if it gets the box further.
+     */
+    pub fn sweep_rect3(&self, rect: &Rect3, displacement: Vec3<f32>, step_height: f32) -> Sweep {
+        let sweep = self.sweep_by_axis(rect, displacement);
+        let blocked_sideways = sweep.contacts.x.is_some() || sweep.contacts.z.is_some();
+        if step_height <= 0.0 || displacement.y > 0.0 || !blocked_sideways {
+            return sweep;
+        }
+
+        let rise = self.sweep_axis(rect, Axis::Y, step_height);
+        let raised = Rect3 {
+            pos: rect.pos + Vec3::new(0.0, rise, 0.0),
+            dim: rect.dim,
+        };
+        let mut stepped =
+            self.sweep_by_axis(&raised, Vec3::new(displacement.x, 0.0, displacement.z));
+        let settled = Rect3 {
+            pos: stepped.pos,
+            dim: rect.dim,
+        };
+        let drop = self.sweep_axis(&settled, Axis::Y, -rise);
+        stepped.pos.y += drop;
+        if (drop + rise).abs() > TOUCH_EPSILON {
+            stepped.contacts.y = Some(Direction::Down);
+        }
+        stepped.stepped_up = stepped.pos.y > rect.pos.y + TOUCH_EPSILON;
+
+        if stepped.stepped_up
+            && horizontal_distance(rect.pos, stepped.pos)
+                > horizontal_distance(rect.pos, sweep.pos) + TOUCH_EPSILON
+        {
+            stepped
+        } else {
+            sweep
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AxisContacts;
+    use crate::{
+        block::{BlockData, BlockType},
+        chunk::Chunk,
+        direction::{Axis, Direction},
+        geometry::rect3::Rect3,
+        positions::{ChunkPos, FineWorldPos, WorldPos},
+        vec::Vec3,
+        world::{world_block::WorldBlock, World},
+    };
+
+    fn make_world(blocks: &[(BlockType, i32, i32, i32)]) -> World {
+        let mut world = World::default();
+        world.insert_chunk(Chunk::new(ChunkPos { x: 0, y: 0 }));
+        for (block_type, x, y, z) in blocks {
+            world
+                .add_block(&WorldBlock {
+                    block_type: (*block_type).into(),
+                    extra_data: BlockData::None,
+                    world_pos: WorldPos::new(*x, *y, *z),
+                })
+                .unwrap();
+        }
+        world
+    }
+
+    fn player_at(x: f32, y: f32, z: f32) -> Rect3 {
+        Rect3 {
+            pos: FineWorldPos::new(x, y, z),
+            dim: Vec3::new(0.6, 1.8, 0.6),
+        }
+    }
+
+    #[test]
+    fn fast_boxes_dont_tunnel_through_thin_walls() {
+        let world = make_world(&[(BlockType::Stone, 6, 2, 1)]);
+        let sweep = world.sweep_rect3(&player_at(1.2, 1.5, 1.2), Vec3::new(20.0, 0.0, 0.0), 0.0);
+        assert!(sweep.pos.equal(&FineWorldPos::new(5.4, 1.5, 1.2)));
+        assert_eq!(
+            sweep.contacts,
+            AxisContacts {
+                x: Some(Direction::East),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn boxes_dont_move_by_distances_that_are_not_finite() {
+        let world = make_world(&[(BlockType::Stone, 6, 2, 1)]);
+        let player = player_at(1.2, 1.5, 1.2);
+        for distance in [f32::INFINITY, f32::NEG_INFINITY, f32::NAN] {
+            assert_eq!(world.sweep_axis(&player, Axis::X, distance), 0.0);
+
+            let sweep = world.sweep_rect3(&player, Vec3::new(distance, 0.0, 0.0), 0.0);
+            assert!(sweep.pos.equal(&player.pos));
+        }
+    }
+
+    #[test]
+    fn catches_edges_and_corners() {
+        // The box only overlaps the block's column by a sliver
+        let world = make_world(&[(BlockType::Stone, 2, 0, 2)]);
+        let sweep = world.sweep_rect3(&player_at(1.45, 3.0, 2.95), Vec3::new(0.0, -4.0, 0.0), 0.0);
+        assert!(sweep.pos.equal(&FineWorldPos::new(1.45, 1.0, 2.95)));
+        assert_eq!(sweep.contacts.y, Some(Direction::Down));
+
+        // Sliding along the top of the block isn't a contact
+        let sweep = world.sweep_rect3(&player_at(1.45, 1.0, 2.95), Vec3::new(-0.5, 0.0, -0.5), 0.0);
+        assert!(!sweep.contacts.any());
+        assert_eq!(
+            world.sweep_axis(&player_at(2.0, 1.0, 2.0), Axis::Y, -1.0),
+            0.0
+        );
+    }
+
+    #[test]
+    fn passes_through_fluids_and_intangible_blocks() {
+        let world = make_world(&[
+            (BlockType::Stone, 1, 0, 1),
+            (BlockType::Water, 1, 1, 1),
+            (BlockType::Torch, 1, 2, 1),
+        ]);
+        let sweep = world.sweep_rect3(&player_at(1.2, 5.0, 1.2), Vec3::new(0.0, -5.0, 0.0), 0.0);
+        assert!(sweep.pos.equal(&FineWorldPos::new(1.2, 1.0, 1.2)));
+    }
+
+    #[test]
+    fn measures_how_much_of_a_box_is_in_fluid() {
+        let world = make_world(&[
+            (BlockType::Water, 1, 1, 1),
+            (BlockType::Water, 1, 2, 1),
+            (BlockType::Water, 2, 1, 1),
+        ]);
+        let submersion = |x: f32, y: f32, z: f32| {
+            world.get_fluid_submersion(&Rect3 {
+                pos: FineWorldPos::new(x, y, z),
+                dim: Vec3::new(1.0, 2.0, 1.0),
+            })
+        };
+
+        assert_eq!(submersion(1.0, 1.0, 1.0), 1.0);
+        assert_eq!(submersion(1.0, 2.0, 1.0), 0.5);
+        assert_eq!(submersion(1.5, 1.0, 1.0), 0.75);
+        assert_eq!(submersion(1.0, 1.0, 1.5), 0.5);
+        assert_eq!(submersion(5.0, 1.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn steps_up_onto_low_blocks() {
+        let world = make_world(&[
+            (BlockType::Stone, 1, 0, 1),
+            (BlockType::Stone, 2, 0, 1),
+            (BlockType::Stone, 2, 1, 1),
+            (BlockType::Stone, 3, 1, 1),
+            (BlockType::Stone, 3, 2, 1),
+            (BlockType::Stone, 3, 3, 1),
+        ]);
+        let rect = player_at(1.2, 1.0, 1.2);
+
+        let sweep = world.sweep_rect3(&rect, Vec3::new(0.5, 0.0, 0.0), 1.0);
+        assert!(sweep.stepped_up);
+        assert!(sweep.pos.equal(&FineWorldPos::new(1.7, 2.0, 1.2)));
+        assert_eq!(sweep.contacts.x, None);
+
+        // Without a step height it walks into the block
+        let sweep = world.sweep_rect3(&rect, Vec3::new(0.5, 0.0, 0.0), 0.0);
+        assert!(!sweep.stepped_up);
+        assert!(sweep.pos.equal(&FineWorldPos::new(1.4, 1.0, 1.2)));
+        assert_eq!(sweep.contacts.x, Some(Direction::East));
+
+        // Walls taller than the step stop it
+        let rect = player_at(2.2, 2.0, 1.2);
+        let sweep = world.sweep_rect3(&rect, Vec3::new(0.5, 0.0, 0.0), 1.0);
+        assert!(!sweep.stepped_up);
+        assert!(sweep.pos.equal(&FineWorldPos::new(2.4, 2.0, 1.2)));
+    }
+}
@@ -1,9 +1,11 @@
 pub mod block;
 pub mod chunk;
 pub mod direction;
+pub mod entity;
 pub mod geometry;
 pub mod plane;
 pub mod positions;
+pub mod region;
 mod utils;
 pub mod vec;
 pub mod world;
@@ -1,5 +1,5 @@
 use crate::{
-    chunk::CHUNK_WIDTH,
+    chunk::{CHUNK_WIDTH, SECTION_HEIGHT},
     vec::{Vec2, Vec3},
 };
 
@@ -12,20 +12,41 @@ pub type ChunkPos = Vec2<i16>;
 pub type FineWorldPos = Vec3<f32>;
 
 impl InnerChunkPos {
+    /** x gets 4 bits, y gets 8 bits and z gets 4 bits */
     pub fn to_chunk_index(&self) -> usize {
-        let x_part = (self.x as usize) << (4 + 6);
+        let x_part = (self.x as usize) << (4 + 8);
         let y_part = (self.y as usize) << 4;
         let z_part = self.z as usize;
         x_part + y_part + z_part
     }
 
     pub fn make_from_chunk_index(index: usize) -> InnerChunkPos {
-        let x_part = (index >> (4 + 6)) as u8;
-        let y_part = ((index & 0b1111110000) >> 4) as u8;
+        let x_part = (index >> (4 + 8)) as u8;
+        let y_part = ((index & 0b1111_1111_0000) >> 4) as u8;
         let z_part = (index & 0b1111) as u8;
         InnerChunkPos::new(x_part, y_part, z_part)
     }
 
+    /** Which vertical section of the chunk this position is in */
+    pub fn get_section(&self) -> usize {
+        self.y as usize / SECTION_HEIGHT as usize
+    }
+
+    /** The index of this position inside of its section */
+    pub fn to_section_index(&self) -> usize {
+        let x_part = (self.x as usize) << (4 + 4);
+        let y_part = (self.y as usize % SECTION_HEIGHT as usize) << 4;
+        let z_part = self.z as usize;
+        x_part + y_part + z_part
+    }
+
+    pub fn make_from_section_index(section_y: usize, index: usize) -> InnerChunkPos {
+        let x_part = (index >> (4 + 4)) as u8;
+        let y_part = ((index & 0b1111_0000) >> 4) + section_y * SECTION_HEIGHT as usize;
+        let z_part = (index & 0b1111) as u8;
+        InnerChunkPos::new(x_part, y_part as u8, z_part)
+    }
+
     pub fn to_world_pos(&self, chunk_pos: &ChunkPos) -> WorldPos {
         chunk_pos
             .scalar_mul(CHUNK_WIDTH)
@@ -36,8 +57,9 @@ impl InnerChunkPos {
 }
 
 impl WorldPos {
-    pub fn is_valid(&self) -> bool {
-        self.y >= 0 && self.y < 256
+    /** Positions are valid when they are inside of a world that is world_height tall */
+    pub fn is_valid(&self, world_height: i16) -> bool {
+        self.y >= 0 && self.y < world_height as i32
     }
 
     pub fn to_inner_chunk_pos(&self) -> InnerChunkPos {
@@ -7,7 +7,8 @@ fn index_conversion() {
         assert_eq!(InnerChunkPos::make_from_chunk_index(index), inner_chunk_pos);
     }
 
-    do_test(1024 + 32 + 3, InnerChunkPos::new(1, 2, 3));
+    do_test(4096 + 32 + 3, InnerChunkPos::new(1, 2, 3));
+    do_test(4096 * 15 + 255 * 16 + 15, InnerChunkPos::new(15, 255, 15));
     do_test(0, InnerChunkPos::new(0, 0, 0));
 }
 
@@ -21,6 +22,34 @@ fn inner_chunk_pos_to_chunk_index() {
 
     do_test(InnerChunkPos::new(0, 0, 0));
     do_test(InnerChunkPos::new(15, 15, 15));
+    do_test(InnerChunkPos::new(3, 200, 9));
+}
+
+#[test]
+fn inner_chunk_pos_to_section_index() {
+    fn do_test(inner_chunk_pos: InnerChunkPos, section_y: usize) {
+        assert_eq!(inner_chunk_pos.get_section(), section_y);
+        let index = inner_chunk_pos.to_section_index();
+        assert!(index < 4096);
+        assert_eq!(
+            InnerChunkPos::make_from_section_index(section_y, index),
+            inner_chunk_pos
+        );
+    }
+
+    do_test(InnerChunkPos::new(0, 0, 0), 0);
+    do_test(InnerChunkPos::new(15, 15, 15), 0);
+    do_test(InnerChunkPos::new(1, 16, 3), 1);
+    do_test(InnerChunkPos::new(7, 255, 2), 15);
+}
+
+#[test]
+fn world_pos_is_valid() {
+    assert!(WorldPos::new(0, 0, 0).is_valid(64));
+    assert!(WorldPos::new(0, 63, 0).is_valid(64));
+    assert!(!WorldPos::new(0, 64, 0).is_valid(64));
+    assert!(WorldPos::new(0, 64, 0).is_valid(128));
+    assert!(!WorldPos::new(0, -1, 0).is_valid(128));
 }
 
 #[test]
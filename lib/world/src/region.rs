@@ -0,0 +1,571 @@
+use crate::{
+    chunk::{chunk_binary::ChunkDecodeError, Chunk},
+    positions::ChunkPos,
+    vec::Vec2,
+};
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+};
+
+mod region_duct;
+
+/** How many chunks wide (in both x and z) one region file is */
+pub const REGION_WIDTH: i16 = 32;
+
+const REGION_CHUNK_COUNT: usize = (REGION_WIDTH as usize) * (REGION_WIDTH as usize);
+
+const MAGIC: &[u8; 4] = b"TCRG";
+const REGION_FORMAT_VERSION: u8 = 1;
+
+/** Chunk slots are allocated in multiples of this so small edits can be rewritten in place */
+const SECTOR_SIZE: u32 = 512;
+
+/**
+ * No chunk takes more than this many bytes to store, even one with every section full of
+ * different blocks, so longer entries can only come from a corrupt file.
+ */
+const MAX_CHUNK_LENGTH: u32 = 1 << 20;
+
+const ENTRY_SIZE: usize = 12;
+const HEADER_SIZE: u64 = (4 + 1 + 2 + 2 + ENTRY_SIZE * REGION_CHUNK_COUNT) as u64;
+
+pub type RegionPos = Vec2<i16>;
+
+impl ChunkPos {
+    pub fn to_region_pos(&self) -> RegionPos {
+        RegionPos {
+            x: self.x.div_euclid(REGION_WIDTH),
+            y: self.y.div_euclid(REGION_WIDTH),
+        }
+    }
+
+    /** The index of this chunk in its region's offset table */
+    fn region_index(&self) -> usize {
+        let x = self.x.rem_euclid(REGION_WIDTH) as usize;
+        let y = self.y.rem_euclid(REGION_WIDTH) as usize;
+        x * REGION_WIDTH as usize + y
+    }
+}
+
+#[derive(Debug)]
+pub enum RegionError {
+    Io(io::Error),
+    Decode(ChunkDecodeError),
+    BadMagic,
+    UnsupportedVersion(u8),
+    ChunkNotInRegion(ChunkPos),
+    /** The world doesn't have the chunk loaded, so there is nothing to save */
+    ChunkNotLoaded(ChunkPos),
+    /** The offset table points the chunk outside the file or says it is too long to be a chunk */
+    CorruptEntry(ChunkPos),
+}
+
+impl std::error::Error for RegionError {}
+
+impl fmt::Display for RegionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegionError::Io(e) => write!(f, "Region io error: {}", e),
+            RegionError::Decode(e) => write!(f, "Region chunk error: {}", e),
+            RegionError::BadMagic => write!(f, "Not a region file"),
+            RegionError::UnsupportedVersion(v) => {
+                write!(f, "Unsupported region format version {}", v)
+            }
+            RegionError::ChunkNotInRegion(pos) => {
+                write!(f, "Chunk {} is not in this region", pos.to_index())
+            }
+            RegionError::ChunkNotLoaded(pos) => {
+                write!(f, "Chunk {} is not loaded in the world", pos.to_index())
+            }
+            RegionError::CorruptEntry(pos) => {
+                write!(f, "Chunk {} has a corrupt region entry", pos.to_index())
+            }
+        }
+    }
+}
+
+impl From<io::Error> for RegionError {
+    fn from(e: io::Error) -> Self {
+        RegionError::Io(e)
+    }
+}
+
+impl From<ChunkDecodeError> for RegionError {
+    fn from(e: ChunkDecodeError) -> Self {
+        RegionError::Decode(e)
+    }
+}
+
+/** Somewhere chunks can be saved, like a single region or a whole directory of them */
+pub trait ChunkWriter {
+    fn write_chunk(&mut self, chunk: &Chunk) -> Result<(), RegionError>;
+}
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+struct RegionEntry {
+    /** Byte offset of the chunk from the start of the file. 0 when the chunk isn't stored */
+    offset: u32,
+    length: u32,
+    /** How many bytes are reserved for the chunk at offset */
+    capacity: u32,
+}
+
+impl RegionEntry {
+    fn to_bytes(self) -> [u8; ENTRY_SIZE] {
+        let mut out = [0; ENTRY_SIZE];
+        out[0..4].copy_from_slice(&self.offset.to_le_bytes());
+        out[4..8].copy_from_slice(&self.length.to_le_bytes());
+        out[8..12].copy_from_slice(&self.capacity.to_le_bytes());
+        out
+    }
+
+    fn from_bytes(bytes: &[u8; ENTRY_SIZE]) -> RegionEntry {
+        let u32_at =
+            |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        RegionEntry {
+            offset: u32_at(0),
+            length: u32_at(4),
+            capacity: u32_at(8),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.offset == 0
+    }
+}
+
+/**
+ * A group of REGION_WIDTH x REGION_WIDTH chunks stored in one seekable blob.
+ *
+ * The file starts with a header holding an offset table with one entry per chunk,
+ * followed by the chunks in the binary chunk format. Single chunks can be read and
+ * written without touching the rest of the file.
+ */
+pub struct RegionFile<S: Read + Write + Seek> {
+    storage: S,
+    pub position: RegionPos,
+    entries: Vec<RegionEntry>,
+}
+
+impl<S: Read + Write + Seek> RegionFile<S> {
+    /** Writes an empty region header to storage */
+    pub fn create(mut storage: S, position: RegionPos) -> Result<Self, RegionError> {
+        let entries = vec![RegionEntry::default(); REGION_CHUNK_COUNT];
+
+        let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+        header.extend_from_slice(MAGIC);
+        header.push(REGION_FORMAT_VERSION);
+        header.extend_from_slice(&position.x.to_le_bytes());
+        header.extend_from_slice(&position.y.to_le_bytes());
+        for entry in &entries {
+            header.extend_from_slice(&entry.to_bytes());
+        }
+
+        storage.seek(SeekFrom::Start(0))?;
+        storage.write_all(&header)?;
+
+        Ok(RegionFile {
+            storage,
+            position,
+            entries,
+        })
+    }
+
+    /** Reads the header of an existing region */
+    pub fn open(mut storage: S) -> Result<Self, RegionError> {
+        storage.seek(SeekFrom::Start(0))?;
+
+        let mut magic = [0; 4];
+        storage.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(RegionError::BadMagic);
+        }
+
+        let mut version = [0; 1];
+        storage.read_exact(&mut version)?;
+        if version[0] != REGION_FORMAT_VERSION {
+            return Err(RegionError::UnsupportedVersion(version[0]));
+        }
+
+        let mut pos = [0; 4];
+        storage.read_exact(&mut pos)?;
+        let position = RegionPos {
+            x: i16::from_le_bytes([pos[0], pos[1]]),
+            y: i16::from_le_bytes([pos[2], pos[3]]),
+        };
+
+        let mut entries = Vec::with_capacity(REGION_CHUNK_COUNT);
+        for _ in 0..REGION_CHUNK_COUNT {
+            let mut entry = [0; ENTRY_SIZE];
+            storage.read_exact(&mut entry)?;
+            entries.push(RegionEntry::from_bytes(&entry));
+        }
+
+        Ok(RegionFile {
+            storage,
+            position,
+            entries,
+        })
+    }
+
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+
+    fn entry_index(&self, chunk_pos: &ChunkPos) -> Result<usize, RegionError> {
+        if chunk_pos.to_region_pos() != self.position {
+            return Err(RegionError::ChunkNotInRegion(*chunk_pos));
+        }
+        Ok(chunk_pos.region_index())
+    }
+
+    pub fn has_chunk(&self, chunk_pos: &ChunkPos) -> bool {
+        self.entry_index(chunk_pos)
+            .map(|index| !self.entries[index].is_empty())
+            .unwrap_or(false)
+    }
+
+    /** All the chunks stored in this region */
+    pub fn get_chunk_positions(&self) -> Vec<ChunkPos> {
+        let origin = self.position.scalar_mul(REGION_WIDTH);
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| !entry.is_empty())
+            .map(|(index, _)| ChunkPos {
+                x: origin.x + (index / REGION_WIDTH as usize) as i16,
+                y: origin.y + (index % REGION_WIDTH as usize) as i16,
+            })
+            .collect()
+    }
+
+    pub fn read_chunk(&mut self, chunk_pos: &ChunkPos) -> Result<Option<Chunk>, RegionError> {
+        let entry = self.entries[self.entry_index(chunk_pos)?];
+        if entry.is_empty() {
+            return Ok(None);
+        }
+
+        // Check the entry before trusting its length with an allocation
+        let storage_length = self.storage.seek(SeekFrom::End(0))?;
+        let end = entry.offset as u64 + entry.length as u64;
+        if entry.length > MAX_CHUNK_LENGTH || end > storage_length {
+            return Err(RegionError::CorruptEntry(*chunk_pos));
+        }
+
+        let mut bytes = vec![0; entry.length as usize];
+        self.storage.seek(SeekFrom::Start(entry.offset as u64))?;
+        self.storage.read_exact(&mut bytes)?;
+
+        Ok(Some(Chunk::from_bytes(&bytes)?))
+    }
+
+    /**
+     * Writes the chunk into its slot.
+     * The chunk is rewritten in place when it still fits, otherwise it is moved to the end of the file.
+     */
+    pub fn write_chunk(&mut self, chunk: &Chunk) -> Result<(), RegionError> {
+        let index = self.entry_index(&chunk.position)?;
+        let bytes = chunk.to_bytes();
+        let length = bytes.len() as u32;
+
+        let mut entry = self.entries[index];
+        if entry.is_empty() || entry.capacity < length {
+            let end = self.storage.seek(SeekFrom::End(0))?.max(HEADER_SIZE);
+            entry.offset = end as u32;
+            entry.capacity = length.div_ceil(SECTOR_SIZE) * SECTOR_SIZE;
+        }
+        entry.length = length;
+
+        self.storage.seek(SeekFrom::Start(entry.offset as u64))?;
+        self.storage.write_all(&bytes)?;
+        // Pad out the reserved space so the next appended chunk starts after it
+        let padding = (entry.capacity - length) as usize;
+        self.storage.write_all(&vec![0; padding])?;
+
+        self.write_entry(index, entry)
+    }
+
+    fn write_entry(&mut self, index: usize, entry: RegionEntry) -> Result<(), RegionError> {
+        self.entries[index] = entry;
+        let entry_offset = HEADER_SIZE - (ENTRY_SIZE * (REGION_CHUNK_COUNT - index)) as u64;
+        self.storage.seek(SeekFrom::Start(entry_offset))?;
+        self.storage.write_all(&entry.to_bytes())?;
+        Ok(())
+    }
+}
+
+impl<S: Read + Write + Seek> ChunkWriter for RegionFile<S> {
+    fn write_chunk(&mut self, chunk: &Chunk) -> Result<(), RegionError> {
+        RegionFile::write_chunk(self, chunk)
+    }
+}
+
+/**
+ * A directory of region files, one per region, opened as they are needed.
+ */
+pub struct RegionStore {
+    dir: PathBuf,
+    regions: HashMap<i32, RegionFile<fs::File>>,
+}
+
+impl RegionStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<RegionStore, RegionError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(RegionStore {
+            dir,
+            regions: HashMap::new(),
+        })
+    }
+
+    fn region_path(&self, region_pos: &RegionPos) -> PathBuf {
+        self.dir
+            .join(format!("r.{}.{}.region", region_pos.x, region_pos.y))
+    }
+
+    fn get_region(
+        &mut self,
+        region_pos: &RegionPos,
+        create: bool,
+    ) -> Result<Option<&mut RegionFile<fs::File>>, RegionError> {
+        let key = region_pos.to_world_index();
+        if !self.regions.contains_key(&key) {
+            let path = self.region_path(region_pos);
+            let region = if path.exists() {
+                let file = fs::OpenOptions::new().read(true).write(true).open(path)?;
+                RegionFile::open(file)?
+            } else if create {
+                let file = fs::OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open(path)?;
+                RegionFile::create(file, *region_pos)?
+            } else {
+                return Ok(None);
+            };
+            self.regions.insert(key, region);
+        }
+        Ok(self.regions.get_mut(&key))
+    }
+
+    pub fn read_chunk(&mut self, chunk_pos: &ChunkPos) -> Result<Option<Chunk>, RegionError> {
+        match self.get_region(&chunk_pos.to_region_pos(), false)? {
+            Some(region) => region.read_chunk(chunk_pos),
+            None => Ok(None),
+        }
+    }
+
+    pub fn write_chunk(&mut self, chunk: &Chunk) -> Result<(), RegionError> {
+        self.get_region(&chunk.position.to_region_pos(), true)?
+            .expect("Region is always created")
+            .write_chunk(chunk)
+    }
+}
+
+impl ChunkWriter for RegionStore {
+    fn write_chunk(&mut self, chunk: &Chunk) -> Result<(), RegionError> {
+        RegionStore::write_chunk(self, chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ChunkWriter, RegionEntry, RegionError, RegionFile, RegionPos, RegionStore,
+        MAX_CHUNK_LENGTH, REGION_WIDTH,
+    };
+    use crate::{
+        block::{BlockData, BlockType, ChunkBlock},
+        chunk::Chunk,
+        positions::{ChunkPos, InnerChunkPos, WorldPos},
+        world::{world_block::WorldBlock, World},
+    };
+    use std::io::Cursor;
+
+    fn make_chunk(chunk_pos: ChunkPos, block_count: u8) -> Chunk {
+        let mut chunk = Chunk::new(chunk_pos);
+        for i in 0..block_count {
+            chunk.add_block(ChunkBlock {
+                pos: InnerChunkPos::new(i % 16, i / 16, ((i % 16) * 7) % 16),
+                block_type: if i % 2 == 0 {
+                    BlockType::Stone.into()
+                } else {
+                    BlockType::Gold.into()
+                },
+                extra_data: BlockData::None,
+            });
+        }
+        chunk
+    }
+
+    #[test]
+    fn chunk_pos_to_region_pos() {
+        assert_eq!(ChunkPos::new(0, 0).to_region_pos(), RegionPos::new(0, 0));
+        assert_eq!(ChunkPos::new(31, 31).to_region_pos(), RegionPos::new(0, 0));
+        assert_eq!(ChunkPos::new(32, -1).to_region_pos(), RegionPos::new(1, -1));
+        assert_eq!(
+            ChunkPos::new(-32, -33).to_region_pos(),
+            RegionPos::new(-1, -2)
+        );
+    }
+
+    #[test]
+    fn reads_and_writes_single_chunks() {
+        let mut region =
+            RegionFile::create(Cursor::new(Vec::new()), RegionPos::new(-1, 0)).unwrap();
+
+        let a = make_chunk(ChunkPos::new(-1, 0), 3);
+        let b = make_chunk(ChunkPos::new(-REGION_WIDTH, REGION_WIDTH - 1), 10);
+        region.write_chunk(&a).unwrap();
+        region.write_chunk(&b).unwrap();
+
+        assert!(region.has_chunk(&a.position));
+        assert!(!region.has_chunk(&ChunkPos::new(-2, 0)));
+        assert!(region.read_chunk(&ChunkPos::new(-2, 0)).unwrap().is_none());
+        assert!(region
+            .write_chunk(&make_chunk(ChunkPos::new(0, 0), 1))
+            .is_err());
+
+        // Reopening reads the offset table back
+        let mut region = RegionFile::open(region.into_inner()).unwrap();
+        assert_eq!(region.get_chunk_positions().len(), 2);
+        let read_a = region.read_chunk(&a.position).unwrap().unwrap();
+        let read_b = region.read_chunk(&b.position).unwrap().unwrap();
+        assert_eq!(read_a.get_all_blocks(), a.get_all_blocks());
+        assert_eq!(read_b.get_all_blocks(), b.get_all_blocks());
+    }
+
+    #[test]
+    fn rejects_entries_that_do_not_fit_in_the_file() {
+        let mut region = RegionFile::create(Cursor::new(Vec::new()), RegionPos::new(0, 0)).unwrap();
+        let chunk_pos = ChunkPos::new(2, 3);
+        region.write_chunk(&make_chunk(chunk_pos, 5)).unwrap();
+        let index = region.entry_index(&chunk_pos).unwrap();
+        let entry = region.entries[index];
+
+        for length in [u32::MAX, MAX_CHUNK_LENGTH - 1] {
+            region
+                .write_entry(index, RegionEntry { length, ..entry })
+                .unwrap();
+            let mut reopened = RegionFile::open(region.into_inner()).unwrap();
+            assert!(matches!(
+                reopened.read_chunk(&chunk_pos),
+                Err(RegionError::CorruptEntry(pos)) if pos == chunk_pos
+            ));
+            region = reopened;
+        }
+    }
+
+    #[test]
+    fn rewrites_chunks_in_place_when_they_fit() {
+        let mut region = RegionFile::create(Cursor::new(Vec::new()), RegionPos::new(0, 0)).unwrap();
+        region
+            .write_chunk(&make_chunk(ChunkPos::new(1, 1), 200))
+            .unwrap();
+        let size = region.storage.get_ref().len();
+
+        let smaller = make_chunk(ChunkPos::new(1, 1), 4);
+        region.write_chunk(&smaller).unwrap();
+        assert_eq!(region.storage.get_ref().len(), size);
+
+        let read = region.read_chunk(&smaller.position).unwrap().unwrap();
+        assert_eq!(read.get_all_blocks(), smaller.get_all_blocks());
+    }
+
+    #[test]
+    fn saves_only_changed_chunks() {
+        let dir =
+            std::env::temp_dir().join(format!("tylercraft-region-test-{}", std::process::id()));
+        let mut store = RegionStore::new(&dir).unwrap();
+
+        let mut world = World::default();
+        world.insert_chunk(make_chunk(ChunkPos::new(0, 0), 5));
+        world.insert_chunk(make_chunk(ChunkPos::new(40, 0), 5));
+        assert_eq!(world.save_to_store(&mut store).unwrap(), 2);
+        assert_eq!(world.save_to_store(&mut store).unwrap(), 0);
+
+        world
+            .add_block(&WorldBlock {
+                block_type: BlockType::Cloud.into(),
+                extra_data: BlockData::None,
+                world_pos: WorldPos::new(3, 30, 3),
+            })
+            .unwrap();
+        assert_eq!(world.save_to_store(&mut store).unwrap(), 1);
+
+        let mut loaded = World::default();
+        let mut store = RegionStore::new(&dir).unwrap();
+        assert!(loaded
+            .load_chunk_from_store(&mut store, &ChunkPos::new(0, 0))
+            .unwrap()
+            .is_some());
+        assert!(loaded
+            .load_chunk_from_store(&mut store, &ChunkPos::new(1, 0))
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            loaded.get_block(&WorldPos::new(3, 30, 3)).block_type,
+            BlockType::Cloud
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /** Saves chunks into memory, failing on the chunks it was told to */
+    struct FlakyWriter {
+        failing: Vec<ChunkPos>,
+        written: Vec<ChunkPos>,
+    }
+
+    impl ChunkWriter for FlakyWriter {
+        fn write_chunk(&mut self, chunk: &Chunk) -> Result<(), RegionError> {
+            if self.failing.contains(&chunk.position) {
+                return Err(RegionError::Io(std::io::Error::other("disk full")));
+            }
+            self.written.push(chunk.position);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn chunks_that_fail_to_save_stay_unsaved() {
+        let mut world = World::default();
+        for x in 0..4 {
+            world.insert_chunk(make_chunk(ChunkPos::new(x, 0), 5));
+        }
+
+        let mut writer = FlakyWriter {
+            failing: vec![ChunkPos::new(0, 0), ChunkPos::new(2, 0)],
+            written: Vec::new(),
+        };
+        assert!(world.save_to_store(&mut writer).is_err());
+        assert_eq!(writer.written.len(), 2);
+        // The failed chunks are tried again, and only them
+        assert!(world.save_to_store(&mut writer).is_err());
+        assert_eq!(writer.written.len(), 2);
+
+        writer.failing.clear();
+        assert_eq!(world.save_to_store(&mut writer).unwrap(), 2);
+        assert_eq!(world.save_to_store(&mut writer).unwrap(), 0);
+        writer.written.sort_by_key(|pos| pos.x);
+        assert_eq!(
+            writer.written.iter().map(|pos| pos.x).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn saving_a_chunk_that_is_not_loaded_says_so() {
+        let mut world = World::default();
+        let mut region = RegionFile::create(Cursor::new(Vec::new()), RegionPos::new(0, 0)).unwrap();
+        assert!(matches!(
+            world.save_chunk_to_region(&mut region, &ChunkPos::new(1, 1)),
+            Err(RegionError::ChunkNotLoaded(_))
+        ));
+    }
+}
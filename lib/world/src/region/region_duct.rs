@@ -0,0 +1,83 @@
+use super::{RegionFile, RegionPos};
+use crate::{chunk::Chunk, positions::ChunkPos, world::World};
+use js_sys::Uint8Array;
+use serde_wasm_bindgen::{to_value, Error};
+use std::io::Cursor;
+use wasm_bindgen::prelude::*;
+
+/**
+ * A region file held in memory, so JS can decide where the bytes are stored.
+ */
+#[wasm_bindgen]
+pub struct RegionBuffer {
+    region: RegionFile<Cursor<Vec<u8>>>,
+}
+
+fn convert_error<T>(error: T) -> Error
+where
+    T: std::error::Error,
+{
+    Error::new(format!("{}", error))
+}
+
+#[wasm_bindgen]
+impl RegionBuffer {
+    pub fn new_wasm(x: i16, y: i16) -> Result<RegionBuffer, Error> {
+        RegionFile::create(Cursor::new(Vec::new()), RegionPos { x, y })
+            .map(|region| RegionBuffer { region })
+            .map_err(convert_error)
+    }
+
+    pub fn from_bytes_wasm(bytes: Vec<u8>) -> Result<RegionBuffer, Error> {
+        RegionFile::open(Cursor::new(bytes))
+            .map(|region| RegionBuffer { region })
+            .map_err(convert_error)
+    }
+
+    pub fn to_bytes_wasm(&self) -> Uint8Array {
+        Uint8Array::from(&self.region.storage.get_ref()[..])
+    }
+
+    pub fn get_chunk_positions_wasm(&self) -> Result<JsValue, Error> {
+        to_value(&self.region.get_chunk_positions())
+    }
+
+    pub fn has_chunk_wasm(&self, x: i16, y: i16) -> bool {
+        self.region.has_chunk(&ChunkPos { x, y })
+    }
+
+    pub fn read_chunk_wasm(&mut self, x: i16, y: i16) -> Result<Option<Chunk>, Error> {
+        self.region
+            .read_chunk(&ChunkPos { x, y })
+            .map_err(convert_error)
+    }
+
+    pub fn write_chunk_wasm(&mut self, chunk: &Chunk) -> Result<(), Error> {
+        self.region.write_chunk(chunk).map_err(convert_error)
+    }
+}
+
+#[wasm_bindgen]
+impl World {
+    /** Returns the state diff, or null if the region doesn't hold the chunk */
+    pub fn load_chunk_from_region_wasm(
+        &mut self,
+        region: &mut RegionBuffer,
+        x: i16,
+        y: i16,
+    ) -> Result<JsValue, Error> {
+        self.load_chunk_from_region(&mut region.region, &ChunkPos { x, y })
+            .map_err(convert_error)
+            .and_then(|diff| to_value(&diff))
+    }
+
+    pub fn save_chunk_to_region_wasm(
+        &mut self,
+        region: &mut RegionBuffer,
+        x: i16,
+        y: i16,
+    ) -> Result<(), Error> {
+        self.save_chunk_to_region(&mut region.region, &ChunkPos { x, y })
+            .map_err(convert_error)
+    }
+}
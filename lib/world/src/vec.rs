@@ -86,7 +86,7 @@ impl<T> Vec2<T> {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Vec3<T> {
     pub x: T,
     pub y: T,
@@ -1,9 +1,12 @@
 use self::world_block::WorldBlock;
+use self::world_tick::TickScheduler;
+use crate::block::BlockType;
 use crate::chunk::chunk_mesh::ChunkMesh;
-use crate::chunk::Chunk;
+use crate::chunk::{Chunk, DEFAULT_WORLD_HEIGHT, MAX_WORLD_HEIGHT, SECTION_HEIGHT};
 use crate::direction::{Direction, Directions};
+use crate::entity::EntityStore;
 use crate::positions::{ChunkPos, WorldPos};
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::{self, fmt};
 use wasm_bindgen::prelude::*;
@@ -11,7 +14,15 @@ use wasm_bindgen::prelude::*;
 pub mod world_block;
 mod world_chunk;
 mod world_duct;
+mod world_entity;
+pub mod world_explosion;
+pub mod world_fluid;
+mod world_gravity;
+pub mod world_leaves;
+mod world_light;
 mod world_mesh;
+mod world_region;
+pub mod world_tick;
 extern crate web_sys;
 
 #[wasm_bindgen]
@@ -43,41 +54,160 @@ impl fmt::Display for ChunkIndexOutOfBoundsError {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InvalidWorldHeightError(pub i16);
+
+impl std::error::Error for InvalidWorldHeightError {}
+
+impl fmt::Display for InvalidWorldHeightError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "World height {} must be a positive multiple of {} no bigger than {}",
+            self.0, SECTION_HEIGHT, MAX_WORLD_HEIGHT
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
 pub struct WorldStateDiff {
     /** A list of chunk ids that were changed */
     pub chunk_ids: HashSet<String>,
+    /** Blocks that fell, in the order they moved */
+    #[serde(default)]
+    pub moved_blocks: Vec<BlockMove>,
 }
 
-#[derive(Default, Serialize, Deserialize)]
+impl WorldStateDiff {
+    pub fn extend(&mut self, other: WorldStateDiff) {
+        self.chunk_ids.extend(other.chunk_ids);
+        self.moved_blocks.extend(other.moved_blocks);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct BlockMove {
+    pub from: WorldPos,
+    pub to: WorldPos,
+}
+
+fn default_world_height() -> i16 {
+    DEFAULT_WORLD_HEIGHT
+}
+
+/** Worlds have to be a whole number of chunk sections tall, and fit in an inner chunk position */
+fn check_height(height: i16) -> Result<i16, InvalidWorldHeightError> {
+    if height <= 0 || height > MAX_WORLD_HEIGHT || height % SECTION_HEIGHT != 0 {
+        return Err(InvalidWorldHeightError(height));
+    }
+    Ok(height)
+}
+
+fn deserialize_height<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i16, D::Error> {
+    let height = i16::deserialize(deserializer)?;
+    check_height(height).map_err(de::Error::custom)
+}
+
+#[derive(Serialize, Deserialize)]
 #[wasm_bindgen]
 pub struct World {
     chunks: HashMap<i32, Chunk>,
+    /** Blocks can be placed from y = 0 up to (but not including) this height */
+    #[serde(
+        default = "default_world_height",
+        deserialize_with = "deserialize_height"
+    )]
+    height: i16,
     #[serde(skip)]
     chunk_meshes: HashMap<i32, ChunkMesh>,
+    /** Chunks that changed since they were last saved to a region store */
+    #[serde(skip)]
+    unsaved_chunks: HashSet<i32>,
+    #[serde(skip)]
+    ticks: TickScheduler,
+    #[serde(skip)]
+    entities: EntityStore,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        World {
+            chunks: HashMap::new(),
+            height: DEFAULT_WORLD_HEIGHT,
+            chunk_meshes: HashMap::new(),
+            unsaved_chunks: HashSet::new(),
+            ticks: TickScheduler::default(),
+            entities: EntityStore::default(),
+        }
+    }
 }
 
 impl World {
+    /** Makes an empty world. The height has to be a whole number of chunk sections */
+    pub fn new(height: i16) -> Result<World, InvalidWorldHeightError> {
+        Ok(World {
+            height: check_height(height)?,
+            ..World::default()
+        })
+    }
+
+    pub fn get_height(&self) -> i16 {
+        self.height
+    }
+
     /** Block Logic */
 
     /** Add a block to the world at a certain position and with certain data.
      * Handles adding the block to the correct chunk
      * Also recalculates chunk's mesh (visible faces) for chunks adjacent to the block
+     * Positions outside of the world's height are never loaded
+     * Fluid and falling block checks around the block are scheduled for later ticks
      */
     pub fn add_block(
         &mut self,
         world_block: &WorldBlock,
     ) -> Result<WorldStateDiff, ChunkNotLoadedError> {
-        let chunk = self.get_mut_chunk(&world_block.world_pos.to_chunk_pos())?;
-        let chunk_block = world_block.to_chunk_block();
-        chunk.add_block(chunk_block);
+        let lit_chunks = self.write_block(world_block)?;
+        let mut diff = self.update_chunks_around_block(&world_block.world_pos);
+        diff.chunk_ids.extend(self.get_chunk_ids(&lit_chunks));
+        Ok(diff)
+    }
+
+    /**
+     * Stores a block and updates the light and fluids around it, but leaves the meshes alone.
+     * Returns the chunks whose light changed.
+     */
+    fn write_block(
+        &mut self,
+        world_block: &WorldBlock,
+    ) -> Result<HashSet<i32>, ChunkNotLoadedError> {
+        let world_pos = world_block.world_pos;
+        if !world_pos.is_valid(self.height) {
+            return Err(ChunkNotLoadedError);
+        }
+        let old_block_type = self.get_block(&world_pos).block_type;
+        let chunk = self.get_mut_chunk(&world_pos.to_chunk_pos())?;
+        if world_block.block_type == BlockType::Void {
+            chunk.remove_block(&world_pos.to_inner_chunk_pos());
+        } else {
+            chunk.add_block(world_block.to_chunk_block());
+        }
+        self.unsaved_chunks
+            .insert(world_pos.to_chunk_pos().to_world_index());
 
-        Ok(self.update_chunks_around_block(&world_block.world_pos))
+        let lit_chunks = self.update_light_at(&world_pos, old_block_type);
+        self.schedule_fluid_updates(&world_pos);
+        self.schedule_fall_checks(&world_pos);
+        self.schedule_leaf_updates(&world_pos);
+        Ok(lit_chunks)
     }
 
     /** Returns void block when the chunk isn't loaded */
     /** ERROR, this isn't consistent. Idk if this is still relevant */
     pub fn get_block(&self, world_pos: &WorldPos) -> WorldBlock {
+        if !world_pos.is_valid(self.height) {
+            return WorldBlock::empty(*world_pos);
+        }
         let chunk = self.get_chunk(&world_pos.to_chunk_pos());
 
         chunk.map_or(WorldBlock::empty(*world_pos), |chunk| {
@@ -93,7 +223,7 @@ impl World {
         let mut adjacent_blocks = HashMap::new();
         for direction in Directions::all() {
             let adjacent_pos = world_pos.move_direction(&direction);
-            if !adjacent_pos.is_valid() {
+            if !adjacent_pos.is_valid(self.height) {
                 continue;
             }
             let block = self.get_block(&adjacent_pos);
@@ -107,6 +237,9 @@ impl World {
      * Does not necessarily mean the block is visible.
      */
     pub fn is_block_loaded(&self, block_world_pos: &WorldPos) -> bool {
+        if !block_world_pos.is_valid(self.height) {
+            return false;
+        }
         let chunk = self.get_chunk_from_world_pos(&block_world_pos);
         chunk.is_ok()
     }
@@ -115,9 +248,7 @@ impl World {
         &mut self,
         world_pos: &WorldPos,
     ) -> Result<WorldStateDiff, ChunkNotLoadedError> {
-        let chunk = self.get_mut_chunk(&world_pos.to_chunk_pos())?;
-        chunk.remove_block(&world_pos.to_inner_chunk_pos());
-        Ok(self.update_chunks_around_block(world_pos))
+        self.add_block(&WorldBlock::empty(*world_pos))
     }
 }
 
@@ -134,7 +265,7 @@ mod tests {
         world.insert_chunk(chunk);
         let world_pos = WorldPos::new(0, 0, 0);
         let block = WorldBlock {
-            block_type: BlockType::Cloud,
+            block_type: BlockType::Cloud.into(),
             extra_data: BlockData::None,
             world_pos,
         };
@@ -145,4 +276,59 @@ mod tests {
         // Five because there is no block below me
         assert_eq!(adjacent_blocks.len(), 5);
     }
+
+    #[test]
+    fn validates_world_height() {
+        assert!(World::new(16).is_ok());
+        assert!(World::new(256).is_ok());
+        assert!(World::new(0).is_err());
+        assert!(World::new(40).is_err());
+        assert!(World::new(272).is_err());
+    }
+
+    #[test]
+    fn validates_world_height_when_deserializing() {
+        let world: World = serde_json::from_str(r#"{ "chunks": {}, "height": 128 }"#).unwrap();
+        assert_eq!(world.get_height(), 128);
+        let world: World = serde_json::from_str(r#"{ "chunks": {} }"#).unwrap();
+        assert_eq!(world.get_height(), DEFAULT_WORLD_HEIGHT);
+
+        for height in [0, -16, 40, 1000] {
+            let json = format!(r#"{{ "chunks": {{}}, "height": {} }}"#, height);
+            assert!(serde_json::from_str::<World>(&json).is_err());
+        }
+    }
+
+    #[test]
+    fn places_blocks_up_to_world_height() {
+        let mut world = World::new(256).unwrap();
+        world.insert_chunk(Chunk::new(ChunkPos { x: 0, y: 0 }));
+
+        for y in [0, 63, 64, 200, 255] {
+            let block = WorldBlock {
+                block_type: BlockType::Stone.into(),
+                extra_data: BlockData::None,
+                world_pos: WorldPos::new(1, y, 2),
+            };
+            world.add_block(&block).unwrap();
+        }
+        for y in [0, 63, 64, 200, 255] {
+            assert_eq!(
+                world.get_block(&WorldPos::new(1, y, 2)).block_type,
+                BlockType::Stone
+            );
+        }
+        assert_eq!(
+            world.get_block(&WorldPos::new(1, 65, 2)).block_type,
+            BlockType::Void
+        );
+
+        let too_high = WorldBlock {
+            block_type: BlockType::Stone.into(),
+            extra_data: BlockData::None,
+            world_pos: WorldPos::new(1, 256, 2),
+        };
+        assert!(world.add_block(&too_high).is_err());
+        assert!(!world.is_block_loaded(&WorldPos::new(1, 256, 2)));
+    }
 }
@@ -1,5 +1,5 @@
 use crate::{
-    block::{BlockData, BlockMetaData, BlockShape, BlockType, ChunkBlock},
+    block::{BlockData, BlockId, BlockMetaData, BlockShape, ChunkBlock},
     direction::{Direction, Directions},
     positions::WorldPos,
 };
@@ -8,7 +8,7 @@ use std::collections::HashMap;
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
 pub struct WorldBlock {
-    pub block_type: BlockType,
+    pub block_type: BlockId,
     pub extra_data: BlockData,
     pub world_pos: WorldPos,
 }
@@ -16,7 +16,7 @@ pub struct WorldBlock {
 impl WorldBlock {
     pub fn empty(world_pos: WorldPos) -> WorldBlock {
         WorldBlock {
-            block_type: BlockType::Void,
+            block_type: BlockId::VOID,
             extra_data: BlockData::None,
             world_pos,
         }
@@ -34,30 +34,34 @@ impl WorldBlock {
      * Returns true if the current world block would be seen through the adjacent block
      */
     fn is_block_face_visible(&self, adjacent_block: &WorldBlock) -> bool {
-        if adjacent_block.block_type == BlockType::Void {
+        if adjacent_block.block_type == BlockId::VOID {
             return true;
         }
 
         let block_data = self.get_metadata();
         let adjacent_block_data = adjacent_block.get_metadata();
 
-        if adjacent_block_data.transparent {
-            return true;
+        if block_data.fluid {
+            // Fluids only show against other shapes, a fluid face against a cube
+            // would sit in the same plane as the cube's face and z-fight with it.
+            return !adjacent_block_data.fluid && adjacent_block_data.shape != BlockShape::Cube;
         }
 
-        if block_data.fluid && adjacent_block_data.fluid {
-            return false;
+        if adjacent_block_data.fluid {
+            return true;
         }
 
-        if !block_data.fluid && adjacent_block_data.fluid {
-            return true;
+        if adjacent_block_data.transparent {
+            // Two of the same see through cube (like leaves) would draw the same face twice
+            return !(self.block_type == adjacent_block.block_type
+                && block_data.shape == BlockShape::Cube);
         }
 
-        return false;
+        false
     }
 
     pub fn get_visible_faces(&self, adjacent_blocks: HashMap<Direction, WorldBlock>) -> Directions {
-        if self.block_type == BlockType::Void {
+        if self.block_type == BlockId::VOID {
             return Directions::empty();
         }
 
@@ -81,7 +85,7 @@ impl WorldBlock {
         }
     }
 
-    pub fn get_metadata(&self) -> &BlockMetaData {
+    pub fn get_metadata(&self) -> BlockMetaData {
         BlockMetaData::get_for_type(self.block_type)
     }
 }
@@ -99,13 +103,13 @@ mod tests {
     #[test]
     fn is_block_face_visible() {
         let world_block = WorldBlock {
-            block_type: BlockType::Cloud,
+            block_type: BlockType::Cloud.into(),
             extra_data: BlockData::None,
             world_pos: WorldPos { x: 0, y: 0, z: 0 },
         };
 
         let adjacent_world_block = WorldBlock {
-            block_type: BlockType::Cloud,
+            block_type: BlockType::Cloud.into(),
             extra_data: BlockData::None,
             world_pos: WorldPos { x: 0, y: 0, z: 0 },
         };
@@ -115,7 +119,7 @@ mod tests {
         assert_eq!(is_visible, false);
 
         let adjacent_world_block = WorldBlock {
-            block_type: BlockType::Void,
+            block_type: BlockType::Void.into(),
             extra_data: BlockData::None,
             world_pos: WorldPos { x: 0, y: 0, z: 0 },
         };
@@ -128,7 +132,7 @@ mod tests {
     #[test]
     fn gets_visible_faces() {
         let world_block = WorldBlock {
-            block_type: BlockType::Cloud,
+            block_type: BlockType::Cloud.into(),
             extra_data: BlockData::None,
             world_pos: WorldPos { x: 0, y: 0, z: 0 },
         };
@@ -144,7 +148,7 @@ mod tests {
         adjacent_blocks.insert(
             Direction::East,
             WorldBlock {
-                block_type: BlockType::Cloud,
+                block_type: BlockType::Cloud.into(),
                 extra_data: BlockData::None,
                 world_pos: WorldPos { x: 0, y: 0, z: 0 },
             },
@@ -155,4 +159,26 @@ mod tests {
         assert_eq!(faces.into_iter().len(), 5);
         assert_eq!(faces.has_direction(Direction::East), false);
     }
+
+    #[test]
+    fn hides_coplanar_faces_between_see_through_blocks() {
+        let block = |block_type: BlockType| WorldBlock {
+            block_type: block_type.into(),
+            extra_data: BlockData::None,
+            world_pos: WorldPos { x: 0, y: 0, z: 0 },
+        };
+
+        let water = block(BlockType::Water);
+        let leaf = block(BlockType::Leaf);
+        let flower = block(BlockType::RedFlower);
+
+        // Leaves draw their face against water, water doesn't draw one back
+        assert!(leaf.is_block_face_visible(&water));
+        assert!(!water.is_block_face_visible(&leaf));
+        assert!(!water.is_block_face_visible(&water));
+        assert!(water.is_block_face_visible(&flower));
+
+        assert!(!leaf.is_block_face_visible(&leaf));
+        assert!(block(BlockType::Stone).is_block_face_visible(&leaf));
+    }
 }
@@ -33,7 +33,10 @@ impl World {
             .ok_or(ChunkNotLoadedError)
     }
 
-    pub fn insert_chunk(&mut self, chunk: Chunk) -> WorldStateDiff {
+    pub fn insert_chunk(&mut self, mut chunk: Chunk) -> WorldStateDiff {
+        // Chunks from a taller world can't keep blocks this world can't reach
+        chunk.truncate_to_height(self.height);
+
         // Update adjacent chunk meshes
         let updated_chunk_ids: HashSet<String> = chunk
             .position
@@ -55,12 +58,18 @@ impl World {
 
         self.chunks.insert(chunk_index, chunk);
         self.chunk_meshes.insert(chunk_index, chunk_mesh);
+        self.unsaved_chunks.insert(chunk_index);
 
         // TODO don't unwrap this error
         self.update_chunk_mesh(&chunk_pos).unwrap();
 
+        let lit_chunks = self.light_chunk(&chunk_pos);
+        let mut chunk_ids = updated_chunk_ids;
+        chunk_ids.extend(self.get_chunk_ids(&lit_chunks));
+
         WorldStateDiff {
-            chunk_ids: updated_chunk_ids,
+            chunk_ids,
+            ..Default::default()
         }
     }
 
@@ -91,7 +100,7 @@ mod tests {
         let mut chunk = Chunk::new(chunk_pos);
 
         let chunk_block = ChunkBlock {
-            block_type: BlockType::Cloud,
+            block_type: BlockType::Cloud.into(),
             extra_data: BlockData::None,
             pos: InnerChunkPos::new(0, 0, 1),
         };
@@ -107,6 +116,36 @@ mod tests {
         assert_eq!(same_block.block_type, BlockType::Cloud);
     }
 
+    #[test]
+    fn trims_chunks_taller_than_the_world() {
+        let mut world = World::default();
+        let chunk_pos = ChunkPos::new(0, 0);
+
+        let mut chunk = Chunk::new(chunk_pos);
+        for y in [0, 63, 64, 255] {
+            chunk.add_block(ChunkBlock {
+                block_type: BlockType::Stone.into(),
+                extra_data: BlockData::None,
+                pos: InnerChunkPos::new(1, y, 2),
+            });
+        }
+        assert_eq!(chunk.get_section_count(), 16);
+
+        world.insert_chunk(chunk);
+
+        let chunk = world.get_chunk(&chunk_pos).unwrap();
+        assert_eq!(chunk.get_section_count(), 4);
+        assert_eq!(chunk.get_light_section_count(), 4);
+        assert_eq!(
+            world.get_block(&WorldPos::new(1, 63, 2)).block_type,
+            BlockType::Stone
+        );
+        assert_eq!(
+            world.get_block(&WorldPos::new(1, 64, 2)).block_type,
+            BlockType::Void
+        );
+    }
+
     #[test]
     fn adds_blocks() {
         let mut world = World::default();
@@ -119,7 +158,7 @@ mod tests {
         world.insert_chunk(chunk);
 
         let world_block = WorldBlock {
-            block_type: BlockType::Cloud,
+            block_type: BlockType::Cloud.into(),
             extra_data: BlockData::None,
             world_pos: block_pos,
         };
@@ -136,7 +175,7 @@ mod tests {
         let block_pos = WorldPos::new(16, 0, 0);
 
         let world_block = WorldBlock {
-            block_type: BlockType::Gold,
+            block_type: BlockType::Gold.into(),
             extra_data: BlockData::None,
             world_pos: block_pos,
         };
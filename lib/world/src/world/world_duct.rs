@@ -1,10 +1,13 @@
 use super::World;
 use crate::{
-    chunk::Chunk,
+    chunk::{chunk_geometry::ChunkGeometry, Chunk},
     direction::Directions,
-    geometry::ray::Ray,
-    world::{world_block::WorldBlock, ChunkPos, WorldPos},
+    geometry::{ray::Ray, raycast::RaycastFilter},
+    positions::FineWorldPos,
+    vec::Vec3,
+    world::{world_block::WorldBlock, world_tick::TickKind, ChunkPos, WorldPos},
 };
+use js_sys::Uint8Array;
 use serde_wasm_bindgen::{from_value, to_value, Error};
 use wasm_bindgen::prelude::*;
 
@@ -21,6 +24,14 @@ impl World {
         World::default()
     }
 
+    pub fn new_with_height_wasm(height: i16) -> Result<World, Error> {
+        World::new(height).map_err(Self::convert_error)
+    }
+
+    pub fn get_height_wasm(&self) -> i16 {
+        self.height
+    }
+
     pub fn serialize_wasm(&self) -> Result<JsValue, Error> {
         to_value(&self)
     }
@@ -61,6 +72,28 @@ impl World {
         })
     }
 
+    /** Returns the first block hit, with its face, the exact point and where a block placed on it would go */
+    pub fn raycast_wasm(
+        &self,
+        origin: JsValue,
+        direction: JsValue,
+        max_distance: f32,
+        filter: RaycastFilter,
+    ) -> Result<JsValue, Error> {
+        let origin: FineWorldPos = from_value(origin)?;
+        let direction: Vec3<f32> = from_value(direction)?;
+        let hit = self.raycast(origin, direction, max_distance, |block| {
+            filter.can_hit(block)
+        });
+        to_value(&hit)
+    }
+
+    /** Returns `{ diff, destroyed_blocks }` */
+    pub fn explode_wasm(&mut self, x: f32, y: f32, z: f32, power: f32) -> Result<JsValue, Error> {
+        let explosion = self.explode(FineWorldPos::new(x, y, z), power);
+        to_value(&explosion)
+    }
+
     pub fn get_block_wasm(&self, val: JsValue) -> Result<JsValue, Error> {
         from_value(val).and_then(|pos: WorldPos| {
             let block = self.get_block(&pos);
@@ -84,6 +117,15 @@ impl World {
         })
     }
 
+    pub fn get_chunk_geometry_wasm(&self, x: i16, y: i16) -> Result<ChunkGeometry, Error> {
+        self.get_chunk_geometry(&ChunkPos { x, y })
+            .map_err(Self::convert_error)
+    }
+
+    pub fn get_light_wasm(&self, x: i32, y: i32, z: i32) -> Result<JsValue, Error> {
+        to_value(&self.get_light(&WorldPos { x, y, z }))
+    }
+
     pub fn is_block_loaded_wasm(&self, val: JsValue) -> Result<JsValue, Error> {
         from_value(val).and_then(|pos| {
             let is_loaded = self.is_block_loaded(&pos);
@@ -97,6 +139,19 @@ impl World {
             .and_then(|diff| to_value(&diff))
     }
 
+    /** Advances the world by dt seconds, returns the combined diff of every tick that ran */
+    pub fn tick_wasm(&mut self, dt: f32) -> Result<JsValue, Error> {
+        to_value(&self.tick(dt))
+    }
+
+    pub fn get_current_tick_wasm(&self) -> u64 {
+        self.get_current_tick()
+    }
+
+    pub fn schedule_tick_wasm(&mut self, x: i32, y: i32, z: i32, kind: TickKind, delay: u64) {
+        self.schedule_tick(WorldPos { x, y, z }, kind, delay);
+    }
+
     pub fn load_chunk_wasm(&mut self, x: i16, y: i16) -> () {
         self.load_chunk(&ChunkPos { x, y });
     }
@@ -108,6 +163,17 @@ impl World {
         })
     }
 
+    pub fn insert_chunk_bytes_wasm(&mut self, bytes: &[u8]) -> Result<JsValue, Error> {
+        let chunk = Chunk::from_bytes(bytes).map_err(Self::convert_error)?;
+        to_value(&self.insert_chunk(chunk))
+    }
+
+    pub fn get_chunk_bytes_wasm(&self, x: i16, y: i16) -> Result<Uint8Array, Error> {
+        self.get_chunk(&ChunkPos { x, y })
+            .map(|chunk| Uint8Array::from(&chunk.to_bytes()[..]))
+            .map_err(Self::convert_error)
+    }
+
     pub fn has_chunk_wasm(&self, value: JsValue) -> bool {
         from_value(value)
             .map(|pos: ChunkPos| self.has_chunk(&pos))
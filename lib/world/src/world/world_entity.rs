@@ -0,0 +1,240 @@
+use super::World;
+use crate::{
+    direction::{Axis, Direction},
+    entity::{Entity, EntityId, EntityStep, EntityStore},
+    geometry::{rect3::Rect3, sweep::AXIS_ORDER},
+};
+
+/** How far below an entity to look for ground it is resting on */
+const GROUND_PROBE_DISTANCE: f32 = 0.01;
+
+impl World {
+    pub fn get_entities(&self) -> &EntityStore {
+        &self.entities
+    }
+
+    pub fn spawn_entity(&mut self, entity: Entity) -> EntityId {
+        self.entities.spawn(entity)
+    }
+
+    pub fn get_entity(&self, id: EntityId) -> Option<&Entity> {
+        self.entities.get(id)
+    }
+
+    pub fn get_entity_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
+        self.entities.get_mut(id)
+    }
+
+    pub fn remove_entity(&mut self, id: EntityId) -> Option<Entity> {
+        self.entities.remove(id)
+    }
+
+    /** If the box is resting on a solid block */
+    fn is_on_ground(&self, rect: &Rect3) -> bool {
+        self.sweep_axis(rect, Axis::Y, -GROUND_PROBE_DISTANCE) > -GROUND_PROBE_DISTANCE
+    }
+
+    /**
+     * Applies gravity, moves the entity by its velocity and stops it against anything it hits.
+     * Entities resting on dry ground don't pick up speed from gravity, and only they can step up onto blocks.
+     * In fluid, buoyancy pushes back against gravity and drag slows the entity down, both by how much of it is under.
+     */
+    fn step_entity(&self, entity: &mut Entity, dt: f32) -> EntityStep {
+        let submersion = self.get_fluid_submersion(&entity.bounds);
+        if !entity.on_ground || submersion > 0.0 {
+            entity.velocity.y -= entity.gravity * dt;
+        }
+        if submersion > 0.0 {
+            entity.velocity.y += entity.gravity * entity.buoyancy * submersion * dt;
+            let keep = (1.0 - entity.fluid_drag * submersion * dt).max(0.0);
+            entity.velocity = entity.velocity * keep;
+        }
+
+        let step_height = if entity.on_ground {
+            entity.step_height
+        } else {
+            0.0
+        };
+        let sweep = self.sweep_rect3(&entity.bounds, entity.velocity * dt, step_height);
+        let contacts = sweep.contacts;
+        entity.bounds.pos = sweep.pos;
+        for axis in &AXIS_ORDER {
+            if contacts.get(*axis).is_some() {
+                entity.velocity.set_component_from_axis(*axis, 0.0);
+            }
+        }
+
+        entity.on_ground = contacts.y == Some(Direction::Down)
+            || (entity.velocity.y <= 0.0 && self.is_on_ground(&entity.bounds));
+        if entity.on_ground {
+            let keep = (1.0 - entity.friction * dt).max(0.0);
+            entity.velocity.x *= keep;
+            entity.velocity.z *= keep;
+        }
+        EntityStep {
+            id: entity.id,
+            contacts,
+            submersion,
+        }
+    }
+
+    /** Steps every entity forward dt seconds, in id order */
+    pub fn step_entities(&mut self, dt: f32) -> Vec<EntityStep> {
+        let mut steps = Vec::new();
+        for id in self.entities.ids() {
+            let mut entity = match self.entities.get(id) {
+                Some(entity) => entity.clone(),
+                None => continue,
+            };
+            steps.push(self.step_entity(&mut entity, dt));
+            self.entities.update(entity);
+        }
+        steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        block::{BlockData, BlockType},
+        chunk::Chunk,
+        direction::Direction,
+        entity::Entity,
+        geometry::rect3::Rect3,
+        positions::{ChunkPos, FineWorldPos, WorldPos},
+        vec::Vec3,
+        world::{world_block::WorldBlock, World},
+    };
+
+    fn make_world() -> World {
+        let mut world = World::default();
+        world.insert_chunk(Chunk::new(ChunkPos { x: 0, y: 0 }));
+        world
+    }
+
+    fn add_stone(world: &mut World, x: i32, y: i32, z: i32) {
+        world
+            .add_block(&WorldBlock {
+                block_type: BlockType::Stone.into(),
+                extra_data: BlockData::None,
+                world_pos: WorldPos::new(x, y, z),
+            })
+            .unwrap();
+    }
+
+    fn player_at(x: f32, y: f32, z: f32) -> Entity {
+        Entity::new(Rect3 {
+            pos: FineWorldPos::new(x, y, z),
+            dim: Vec3::new(0.6, 1.8, 0.6),
+        })
+    }
+
+    #[test]
+    fn falls_and_lands_on_blocks() {
+        let mut world = make_world();
+        add_stone(&mut world, 0, 0, 0);
+        let id = world.spawn_entity(player_at(0.2, 3.0, 0.2));
+
+        let mut landed = false;
+        for _ in 0..60 {
+            let steps = world.step_entities(1.0 / 60.0);
+            landed |= steps[0].contacts.y == Some(Direction::Down);
+        }
+        let entity = world.get_entity(id).unwrap();
+        assert!(landed);
+        assert!(entity.on_ground);
+        assert_eq!(entity.velocity.y, 0.0);
+        assert_eq!(entity.bounds.pos.y, 1.0);
+    }
+
+    #[test]
+    fn friction_only_slows_entities_on_the_ground() {
+        let mut world = make_world();
+        for x in 0..10 {
+            add_stone(&mut world, x, 0, 0);
+        }
+        let mut walker = player_at(0.2, 1.05, 0.2);
+        walker.velocity.x = 4.0;
+        let walker = world.spawn_entity(walker);
+
+        let mut flyer = player_at(0.2, 8.0, 4.2);
+        flyer.velocity.x = 4.0;
+        flyer.gravity = 0.0;
+        let flyer = world.spawn_entity(flyer);
+
+        for _ in 0..30 {
+            world.step_entities(1.0 / 60.0);
+        }
+        let walker = world.get_entity(walker).unwrap();
+        assert!(walker.on_ground);
+        assert!(walker.velocity.x < 0.1);
+        assert!(walker.bounds.pos.x > 0.4);
+
+        let flyer = world.get_entity(flyer).unwrap();
+        assert!(!flyer.on_ground);
+        assert_eq!(flyer.velocity.x, 4.0);
+        assert!(FineWorldPos::new(2.2, 8.0, 4.2).equal(&flyer.bounds.pos));
+    }
+
+    #[test]
+    fn entities_float_in_fluid() {
+        let mut world = make_world();
+        for x in 0..3 {
+            for z in 0..3 {
+                add_stone(&mut world, x, 0, z);
+                for y in 1..5 {
+                    world
+                        .add_block(&WorldBlock {
+                            block_type: BlockType::Water.into(),
+                            extra_data: BlockData::None,
+                            world_pos: WorldPos::new(x, y, z),
+                        })
+                        .unwrap();
+                }
+            }
+        }
+        let id = world.spawn_entity(player_at(1.2, 7.0, 1.2));
+
+        let mut deepest = f32::MAX;
+        let mut fastest_sink: f32 = 0.0;
+        let mut submersion = 0.0;
+        for _ in 0..600 {
+            submersion = world.step_entities(1.0 / 60.0)[0].submersion;
+            let entity = world.get_entity(id).unwrap();
+            deepest = deepest.min(entity.bounds.pos.y);
+            if entity.bounds.pos.y < 3.0 {
+                fastest_sink = fastest_sink.min(entity.velocity.y);
+            }
+        }
+
+        let entity = world.get_entity(id).unwrap();
+        // Drag slows it down before it reaches the bottom
+        assert!(deepest > 1.0);
+        // Falling that far through air it would be doing about 15 blocks a second
+        assert!(fastest_sink > -9.0);
+        // Then it bobs up and settles with its head out of the water
+        assert!(entity.velocity.y.abs() < 0.5);
+        assert!(submersion > 0.6 && submersion < 1.0);
+        assert!(!entity.on_ground);
+    }
+
+    #[test]
+    fn walls_stop_entities_and_report_the_side_hit() {
+        let mut world = make_world();
+        add_stone(&mut world, 3, 5, 0);
+        let mut entity = player_at(1.0, 5.0, 0.2);
+        entity.gravity = 0.0;
+        entity.velocity.x = 6.0;
+        let id = world.spawn_entity(entity);
+
+        let steps = world.step_entities(0.5);
+        assert_eq!(steps[0].contacts.x, Some(Direction::East));
+        assert_eq!(steps[0].contacts.y, None);
+        let entity = world.get_entity(id).unwrap();
+        assert_eq!(entity.velocity.x, 0.0);
+        assert_eq!(entity.bounds.pos.x + entity.bounds.dim.x, 3.0);
+
+        assert!(world.remove_entity(id).is_some());
+        assert!(world.get_entities().is_empty());
+    }
+}
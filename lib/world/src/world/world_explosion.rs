@@ -0,0 +1,207 @@
+use super::{world_block::WorldBlock, World, WorldStateDiff};
+use crate::{
+    block::BlockType,
+    positions::{FineWorldPos, WorldPos},
+    vec::Vec3,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/** Rays go out through every point on the surface of a cube this many points wide */
+const RAY_GRID_SIZE: i32 = 16;
+/** How far a ray moves between the blocks it samples */
+const RAY_STEP: f32 = 0.3;
+/** Power a ray loses for every step, even through air */
+const AIR_FALLOFF: f32 = 0.225;
+/**
+ * Stronger explosions are treated as this strong. Every ray steps until its power runs out,
+ * so the power has to be capped to keep the work bounded. At this power rays reach about 20 blocks through air.
+ */
+pub const MAX_EXPLOSION_POWER: f32 = 16.0;
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Explosion {
+    pub diff: WorldStateDiff,
+    /** Every block the explosion destroyed, as they were before it */
+    pub destroyed_blocks: Vec<WorldBlock>,
+}
+
+/** Unit directions from the center of an explosion out through the surface of the ray grid */
+fn ray_directions() -> Vec<Vec3<f32>> {
+    let last = RAY_GRID_SIZE - 1;
+    let mut directions = Vec::new();
+    for x in 0..RAY_GRID_SIZE {
+        for y in 0..RAY_GRID_SIZE {
+            for z in 0..RAY_GRID_SIZE {
+                let on_surface = [x, y, z].iter().any(|i| *i == 0 || *i == last);
+                if !on_surface {
+                    continue;
+                }
+                let direction = Vec3::new(x, y, z).map(|i| i as f32 / last as f32 * 2.0 - 1.0);
+                let length = direction.get_mag();
+                directions.push(direction.map(|component| component / length));
+            }
+        }
+    }
+    directions
+}
+
+impl World {
+    /**
+     * Works out which blocks an explosion of the given power at center breaks.
+     * Rays go out in every direction losing power as they travel, and each block they pass through
+     * takes its blast resistance off them. A block breaks if a ray reaches it with power to spare.
+     * Powers above MAX_EXPLOSION_POWER are capped, and powers that aren't finite break nothing.
+     */
+    pub fn get_blast_positions(&self, center: FineWorldPos, power: f32) -> Vec<WorldPos> {
+        let mut seen = HashSet::new();
+        let mut positions = Vec::new();
+        if !power.is_finite() || power <= 0.0 {
+            return positions;
+        }
+        let power = power.min(MAX_EXPLOSION_POWER);
+
+        for direction in ray_directions() {
+            let mut intensity = power;
+            let mut pos = center;
+            while intensity > 0.0 {
+                let world_pos = pos.map(|component| component.floor() as i32);
+                let block = self.get_block(&world_pos);
+                if block.block_type != BlockType::Void {
+                    intensity -= (block.get_metadata().blast_resistance + RAY_STEP) * RAY_STEP;
+                    if intensity > 0.0 && seen.insert(world_pos) {
+                        positions.push(world_pos);
+                    }
+                }
+                pos = pos + direction * RAY_STEP;
+                intensity -= AIR_FALLOFF;
+            }
+        }
+        positions
+    }
+
+    /**
+     * Breaks every block caught in the explosion at once.
+     * Lighting, ticks and meshes are only updated around the broken blocks, and it all comes back as one diff.
+     */
+    pub fn explode(&mut self, center: FineWorldPos, power: f32) -> Explosion {
+        let mut lit_chunks = HashSet::new();
+        let mut destroyed_positions = Vec::new();
+        let mut destroyed_blocks = Vec::new();
+        for world_pos in self.get_blast_positions(center, power) {
+            let block = self.get_block(&world_pos);
+            if let Ok(lit) = self.write_block(&WorldBlock::empty(world_pos)) {
+                lit_chunks.extend(lit);
+                destroyed_positions.push(world_pos);
+                destroyed_blocks.push(block);
+            }
+        }
+
+        let mut chunk_ids = self.update_meshes_around_blocks(&destroyed_positions);
+        chunk_ids.extend(self.get_chunk_ids(&lit_chunks));
+        Explosion {
+            diff: WorldStateDiff {
+                chunk_ids,
+                ..Default::default()
+            },
+            destroyed_blocks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ray_directions, MAX_EXPLOSION_POWER};
+    use crate::{
+        block::{BlockData, BlockType},
+        chunk::Chunk,
+        positions::{ChunkPos, FineWorldPos, WorldPos},
+        world::{world_block::WorldBlock, World},
+    };
+
+    /** A chunk filled solid with the block type from y = 0 to 15 */
+    fn make_world(block_type: BlockType) -> World {
+        let mut world = World::default();
+        let mut chunk = Chunk::new(ChunkPos { x: 0, y: 0 });
+        for x in 0..16 {
+            for y in 0..16 {
+                for z in 0..16 {
+                    chunk.add_block(
+                        WorldBlock {
+                            block_type: block_type.into(),
+                            extra_data: BlockData::None,
+                            world_pos: WorldPos::new(x, y, z),
+                        }
+                        .to_chunk_block(),
+                    );
+                }
+            }
+        }
+        world.insert_chunk(chunk);
+        world
+    }
+
+    #[test]
+    fn rays_cover_every_direction() {
+        let directions = ray_directions();
+        assert_eq!(directions.len(), 16 * 16 * 16 - 14 * 14 * 14);
+        for direction in &directions {
+            assert!((direction.get_mag() - 1.0).abs() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn caps_the_power_and_ignores_powers_that_are_not_finite() {
+        let world = make_world(BlockType::Sand);
+        let center = FineWorldPos::new(8.5, 8.5, 8.5);
+
+        for power in [f32::INFINITY, f32::NEG_INFINITY, f32::NAN] {
+            assert!(world.get_blast_positions(center, power).is_empty());
+        }
+
+        let strongest = world.get_blast_positions(center, MAX_EXPLOSION_POWER);
+        assert!(!strongest.is_empty());
+        assert_eq!(world.get_blast_positions(center, 1e9), strongest);
+    }
+
+    #[test]
+    fn breaks_blocks_around_the_center_in_one_batch() {
+        let mut world = make_world(BlockType::Stone);
+        let center = FineWorldPos::new(8.5, 8.5, 8.5);
+        let explosion = world.explode(center, 4.0);
+
+        assert!(!explosion.destroyed_blocks.is_empty());
+        assert_eq!(explosion.diff.chunk_ids.len(), 1);
+        for block in &explosion.destroyed_blocks {
+            assert_eq!(block.block_type, BlockType::Stone);
+            assert_eq!(
+                world.get_block(&block.world_pos).block_type,
+                BlockType::Void
+            );
+            let distance = center.distance_to(block.world_pos.map(|i| i as f32 + 0.5));
+            assert!(distance < 4.0, "{:?} is too far to break", block.world_pos);
+        }
+        assert_eq!(
+            world.get_block(&WorldPos::new(8, 8, 8)).block_type,
+            BlockType::Void
+        );
+        assert_eq!(
+            world.get_block(&WorldPos::new(8, 8, 13)).block_type,
+            BlockType::Stone
+        );
+
+        assert!(world.explode(center, 0.0).destroyed_blocks.is_empty());
+    }
+
+    #[test]
+    fn weaker_blocks_break_further_out() {
+        let center = FineWorldPos::new(8.5, 8.5, 8.5);
+        let stone = make_world(BlockType::Stone).get_blast_positions(center, 4.0);
+        let leaves = make_world(BlockType::Leaf).get_blast_positions(center, 4.0);
+        assert!(leaves.len() > stone.len() * 2);
+
+        // Water soaks up the whole blast
+        let water = make_world(BlockType::Water).get_blast_positions(center, 4.0);
+        assert!(water.is_empty());
+    }
+}
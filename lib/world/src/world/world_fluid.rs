@@ -0,0 +1,261 @@
+use super::{
+    world_block::WorldBlock,
+    world_tick::{TickKind, FLUID_TICK_DELAY},
+    World, WorldStateDiff,
+};
+use crate::{
+    block::{
+        block_state::{BlockProperty, BlockState},
+        BlockData, BlockId,
+    },
+    direction::{Direction, Directions},
+    positions::WorldPos,
+};
+use std::collections::HashSet;
+
+/** How far flowing fluid spreads sideways from a source */
+pub const MAX_FLOW_DISTANCE: u8 = 7;
+/** Fluid that is pouring down from the block above. It spreads like a source when it lands */
+pub const FALLING_LEVEL: u8 = 8;
+
+/**
+ * A fluid block's level, stored in its block state.
+ * 0 is a source, 1 to 7 is how far the fluid has flowed from a source and 8 is falling.
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Fluid {
+    block_type: BlockId,
+    level: u8,
+}
+
+impl Fluid {
+    fn is_source(&self) -> bool {
+        self.level == 0
+    }
+
+    /** The level the fluid gives to the blocks beside it */
+    fn spread_level(&self) -> u8 {
+        match self.level {
+            FALLING_LEVEL => 1,
+            level => level + 1,
+        }
+    }
+
+    fn to_world_block(self, world_pos: WorldPos) -> WorldBlock {
+        let state = BlockState::default()
+            .with(BlockProperty::Level, self.level)
+            .unwrap_or_default();
+        WorldBlock {
+            block_type: self.block_type,
+            extra_data: BlockData::from_state(state),
+            world_pos,
+        }
+    }
+}
+
+impl World {
+    fn get_fluid(&self, world_pos: &WorldPos) -> Option<Fluid> {
+        let block = self.get_block(world_pos);
+        block.get_metadata().fluid.then(|| Fluid {
+            block_type: block.block_type,
+            level: block.extra_data.get_state().get(BlockProperty::Level),
+        })
+    }
+
+    /** Fluid only flows into empty space in loaded chunks */
+    fn can_flow_into(&self, world_pos: &WorldPos) -> bool {
+        self.is_block_loaded(world_pos) && self.get_block(world_pos).block_type == BlockId::VOID
+    }
+
+    /**
+     * What fluid should be at the position given its neighbours.
+     * Fluid above makes falling fluid, otherwise the lowest level beside it spreads in.
+     * Flowing fluid only spreads sideways once it has nothing to fall into.
+     */
+    fn get_fed_fluid(&self, world_pos: &WorldPos) -> Option<Fluid> {
+        if let Some(above) = self.get_fluid(&world_pos.move_direction(&Direction::Up)) {
+            return Some(Fluid {
+                block_type: above.block_type,
+                level: FALLING_LEVEL,
+            });
+        }
+
+        Directions::flat()
+            .into_iter()
+            .filter_map(|direction| {
+                let neighbour_pos = world_pos.move_direction(&direction);
+                let neighbour = self.get_fluid(&neighbour_pos)?;
+                let below_neighbour = neighbour_pos.move_direction(&Direction::Down);
+                (neighbour.is_source() || !self.can_flow_into(&below_neighbour)).then(|| Fluid {
+                    block_type: neighbour.block_type,
+                    level: neighbour.spread_level(),
+                })
+            })
+            .filter(|fluid| fluid.level <= MAX_FLOW_DISTANCE)
+            .min_by_key(|fluid| fluid.level)
+    }
+
+    /** Schedules fluid ticks for the position and its neighbours if any of them hold fluid */
+    pub(super) fn schedule_fluid_updates(&mut self, world_pos: &WorldPos) {
+        let positions = world_pos.get_cross_vecs();
+        if positions.iter().any(|pos| self.get_fluid(pos).is_some()) {
+            for pos in positions {
+                self.schedule_tick(pos, TickKind::Fluid, FLUID_TICK_DELAY);
+            }
+        }
+    }
+
+    /**
+     * Moves the fluid at each position by one block, when their fluid ticks come due.
+     * All the positions are worked out from the world as it was before the tick,
+     * then applied, which schedules their neighbours for a later tick.
+     * Only the faces around the changed blocks are remeshed since a tick can touch a lot of blocks.
+     */
+    pub(super) fn update_fluids(&mut self, pending: Vec<WorldPos>) -> WorldStateDiff {
+        let changes: Vec<(WorldPos, Option<Fluid>)> = pending
+            .into_iter()
+            .filter(|world_pos| self.is_block_loaded(world_pos))
+            .filter_map(|world_pos| {
+                let current = self.get_fluid(&world_pos);
+                match current {
+                    // Sources stay until something replaces them
+                    Some(fluid) if fluid.is_source() => None,
+                    None if !self.can_flow_into(&world_pos) => None,
+                    _ => {
+                        let fed = self.get_fed_fluid(&world_pos);
+                        (fed != current).then_some((world_pos, fed))
+                    }
+                }
+            })
+            .collect();
+
+        let mut lit_chunks = HashSet::new();
+        let mut changed_positions = Vec::new();
+        for (world_pos, fluid) in changes {
+            let world_block = match fluid {
+                Some(fluid) => fluid.to_world_block(world_pos),
+                None => WorldBlock::empty(world_pos),
+            };
+            if let Ok(lit) = self.write_block(&world_block) {
+                lit_chunks.extend(lit);
+                changed_positions.push(world_pos);
+            }
+        }
+
+        let mut chunk_ids = self.update_meshes_around_blocks(&changed_positions);
+        chunk_ids.extend(self.get_chunk_ids(&lit_chunks));
+        WorldStateDiff {
+            chunk_ids,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FALLING_LEVEL, MAX_FLOW_DISTANCE};
+    use crate::world::world_tick::FLUID_TICK_DELAY;
+    use crate::{
+        block::{block_state::BlockProperty, BlockData, BlockType},
+        chunk::Chunk,
+        positions::{ChunkPos, WorldPos},
+        world::{world_block::WorldBlock, World},
+    };
+
+    fn block(block_type: BlockType, x: i32, y: i32, z: i32) -> WorldBlock {
+        WorldBlock {
+            block_type: block_type.into(),
+            extra_data: BlockData::None,
+            world_pos: WorldPos::new(x, y, z),
+        }
+    }
+
+    fn make_world() -> World {
+        let mut world = World::default();
+        for x in -1..=1 {
+            for y in -1..=1 {
+                world.insert_chunk(Chunk::new(ChunkPos { x, y }));
+            }
+        }
+        world
+    }
+
+    fn settle(world: &mut World) {
+        for _ in 0..1000 {
+            if !world.has_scheduled_ticks() {
+                return;
+            }
+            world.run_tick();
+        }
+        panic!("Fluids never settled");
+    }
+
+    fn level_at(world: &World, x: i32, y: i32, z: i32) -> Option<u8> {
+        let block = world.get_block(&WorldPos::new(x, y, z));
+        (block.block_type == BlockType::Water)
+            .then(|| block.extra_data.get_state().get(BlockProperty::Level))
+    }
+
+    #[test]
+    fn spreads_sideways_across_chunks() {
+        let mut world = make_world();
+        world.add_block(&block(BlockType::Water, 14, 0, 3)).unwrap();
+
+        for _ in 0..FLUID_TICK_DELAY {
+            world.run_tick();
+        }
+        assert_eq!(level_at(&world, 15, 0, 3), Some(1));
+        assert_eq!(level_at(&world, 16, 0, 3), None);
+
+        settle(&mut world);
+        assert_eq!(level_at(&world, 14, 0, 3), Some(0));
+        assert_eq!(level_at(&world, 16, 0, 3), Some(2));
+        assert_eq!(
+            level_at(&world, 14 + MAX_FLOW_DISTANCE as i32, 0, 3),
+            Some(MAX_FLOW_DISTANCE)
+        );
+        assert_eq!(level_at(&world, 15 + MAX_FLOW_DISTANCE as i32, 0, 3), None);
+        assert_eq!(level_at(&world, 13, 0, 4), Some(2));
+    }
+
+    #[test]
+    fn falls_then_spreads() {
+        let mut world = make_world();
+        world.add_block(&block(BlockType::Stone, 4, 2, 4)).unwrap();
+        world.add_block(&block(BlockType::Water, 4, 5, 4)).unwrap();
+        settle(&mut world);
+
+        assert_eq!(level_at(&world, 4, 4, 4), Some(FALLING_LEVEL));
+        assert_eq!(level_at(&world, 4, 3, 4), Some(FALLING_LEVEL));
+        // The source spreads sideways at its own height
+        assert_eq!(level_at(&world, 5, 5, 4), Some(1));
+        // Flowing water next to the source pours down instead of spreading further
+        assert_eq!(level_at(&world, 5, 4, 4), Some(FALLING_LEVEL));
+    }
+
+    #[test]
+    fn flows_into_holes_and_drains() {
+        let mut world = make_world();
+        for x in 0..6 {
+            world.add_block(&block(BlockType::Stone, x, 1, 0)).unwrap();
+        }
+        world.add_block(&block(BlockType::Water, 0, 2, 0)).unwrap();
+        settle(&mut world);
+        assert_eq!(level_at(&world, 3, 2, 0), Some(3));
+
+        // Digging out the floor lets the water pour down
+        world.remove_block(&WorldPos::new(3, 1, 0)).unwrap();
+        settle(&mut world);
+        assert_eq!(level_at(&world, 3, 1, 0), Some(FALLING_LEVEL));
+        assert_eq!(level_at(&world, 3, 0, 0), Some(FALLING_LEVEL));
+
+        // Removing the source dries everything up
+        world.remove_block(&WorldPos::new(0, 2, 0)).unwrap();
+        settle(&mut world);
+        for x in 0..6 {
+            for y in 0..3 {
+                assert_eq!(level_at(&world, x, y, 0), None, "water left at {} {}", x, y);
+            }
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use super::{
+    world_block::WorldBlock,
+    world_tick::{TickKind, FALL_TICK_DELAY},
+    BlockMove, World, WorldStateDiff,
+};
+use crate::{block::BlockType, direction::Direction, positions::WorldPos};
+use std::collections::HashSet;
+
+impl World {
+    /** Falling blocks replace void and fluids */
+    fn can_fall_into(&self, world_pos: &WorldPos) -> bool {
+        if !self.is_block_loaded(world_pos) {
+            return false;
+        }
+        let block = self.get_block(world_pos);
+        block.block_type == BlockType::Void || block.get_metadata().fluid
+    }
+
+    /** Where a block at world_pos would land, or None if it is already resting on something */
+    fn find_landing(&self, world_pos: &WorldPos) -> Option<WorldPos> {
+        let mut landing = *world_pos;
+        loop {
+            let below = landing.move_direction(&Direction::Down);
+            if !self.can_fall_into(&below) {
+                break;
+            }
+            landing = below;
+        }
+        (landing != *world_pos).then_some(landing)
+    }
+
+    /** Schedules a fall check for the block and the block resting on it if either is gravitable */
+    pub(super) fn schedule_fall_checks(&mut self, world_pos: &WorldPos) {
+        for pos in &[*world_pos, world_pos.move_direction(&Direction::Up)] {
+            if self.get_block(pos).get_metadata().gravitable {
+                self.schedule_tick(*pos, TickKind::Fall, FALL_TICK_DELAY);
+            }
+        }
+    }
+
+    /**
+     * Checks the positions for gravitable blocks with nothing under them and drops them until they land.
+     * When a block falls the block above it is checked too, so whole columns of sand come down together.
+     * Runs when the fall checks scheduled by add_block and remove_block come due.
+     */
+    pub(super) fn drop_blocks(&mut self, mut to_check: Vec<WorldPos>) -> WorldStateDiff {
+        let mut moved_blocks = Vec::new();
+        let mut changed_positions = Vec::new();
+        let mut lit_chunks = HashSet::new();
+
+        while let Some(world_pos) = to_check.pop() {
+            let block = self.get_block(&world_pos);
+            if !block.get_metadata().gravitable {
+                continue;
+            }
+            let landing = match self.find_landing(&world_pos) {
+                Some(landing) => landing,
+                None => continue,
+            };
+
+            let moved = WorldBlock {
+                world_pos: landing,
+                ..block
+            };
+            // Both chunks are loaded, find_landing only looks at loaded blocks
+            if let Ok(lit) = self.write_block(&WorldBlock::empty(world_pos)) {
+                lit_chunks.extend(lit);
+            }
+            if let Ok(lit) = self.write_block(&moved) {
+                lit_chunks.extend(lit);
+            }
+            changed_positions.push(world_pos);
+            changed_positions.push(landing);
+            moved_blocks.push(BlockMove {
+                from: world_pos,
+                to: landing,
+            });
+
+            to_check.push(world_pos.move_direction(&Direction::Up));
+        }
+
+        // Remesh once after everything has landed instead of after every move
+        let mut chunk_ids = self.update_meshes_around_blocks(&changed_positions);
+        chunk_ids.extend(self.get_chunk_ids(&lit_chunks));
+        WorldStateDiff {
+            chunk_ids,
+            moved_blocks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        block::{BlockData, BlockType},
+        chunk::Chunk,
+        direction::{Direction, Directions},
+        positions::{ChunkPos, WorldPos},
+        world::{
+            world_block::WorldBlock, world_tick::FALL_TICK_DELAY, BlockMove, World, WorldStateDiff,
+        },
+    };
+
+    fn run_ticks(world: &mut World, ticks: u64) -> WorldStateDiff {
+        let mut diff = WorldStateDiff::default();
+        for _ in 0..ticks {
+            diff.extend(world.run_tick());
+        }
+        diff
+    }
+
+    fn block(block_type: BlockType, x: i32, y: i32, z: i32) -> WorldBlock {
+        WorldBlock {
+            block_type: block_type.into(),
+            extra_data: BlockData::None,
+            world_pos: WorldPos::new(x, y, z),
+        }
+    }
+
+    fn make_world() -> World {
+        let mut world = World::default();
+        world.insert_chunk(Chunk::new(ChunkPos { x: 0, y: 0 }));
+        world
+    }
+
+    #[test]
+    fn sand_falls_until_it_lands() {
+        let mut world = make_world();
+        world.add_block(&block(BlockType::Stone, 2, 3, 2)).unwrap();
+
+        let diff = world.add_block(&block(BlockType::Sand, 2, 10, 2)).unwrap();
+        assert!(diff.moved_blocks.is_empty());
+        assert_eq!(
+            world.get_block(&WorldPos::new(2, 10, 2)).block_type,
+            BlockType::Sand
+        );
+
+        let diff = run_ticks(&mut world, FALL_TICK_DELAY);
+        assert_eq!(
+            diff.moved_blocks,
+            vec![BlockMove {
+                from: WorldPos::new(2, 10, 2),
+                to: WorldPos::new(2, 4, 2),
+            }]
+        );
+        assert_eq!(
+            world.get_block(&WorldPos::new(2, 4, 2)).block_type,
+            BlockType::Sand
+        );
+        assert_eq!(
+            world.get_block(&WorldPos::new(2, 10, 2)).block_type,
+            BlockType::Void
+        );
+
+        // Resting on the world floor
+        world.add_block(&block(BlockType::Gravel, 5, 8, 5)).unwrap();
+        run_ticks(&mut world, FALL_TICK_DELAY);
+        assert_eq!(
+            world.get_block(&WorldPos::new(5, 0, 5)).block_type,
+            BlockType::Gravel
+        );
+    }
+
+    #[test]
+    fn remeshes_around_blocks_once_they_land() {
+        let mut world = make_world();
+        world.add_block(&block(BlockType::Stone, 2, 3, 2)).unwrap();
+        world.add_block(&block(BlockType::Sand, 2, 10, 2)).unwrap();
+
+        let diff = run_ticks(&mut world, FALL_TICK_DELAY);
+        assert!(diff.chunk_ids.contains(&ChunkPos { x: 0, y: 0 }.to_index()));
+
+        let mut resting = Directions::all();
+        resting.remove_direction(Direction::Down);
+        assert_eq!(
+            world
+                .get_mesh_at_pos(WorldPos::new(2, 4, 2))
+                .unwrap()
+                .directions,
+            resting
+        );
+        assert_eq!(
+            world
+                .get_mesh_at_pos(WorldPos::new(2, 10, 2))
+                .unwrap()
+                .directions,
+            Directions::empty()
+        );
+        assert!(!world
+            .get_mesh_at_pos(WorldPos::new(2, 3, 2))
+            .unwrap()
+            .directions
+            .has_direction(Direction::Up));
+    }
+
+    #[test]
+    fn columns_fall_when_support_is_removed() {
+        let mut world = make_world();
+        world.add_block(&block(BlockType::Stone, 1, 4, 1)).unwrap();
+        for y in 5..8 {
+            world.add_block(&block(BlockType::Sand, 1, y, 1)).unwrap();
+        }
+        // A non gravitable block on top stays put
+        world.add_block(&block(BlockType::Stone, 1, 8, 1)).unwrap();
+        run_ticks(&mut world, FALL_TICK_DELAY);
+
+        world.remove_block(&WorldPos::new(1, 4, 1)).unwrap();
+        let diff = run_ticks(&mut world, FALL_TICK_DELAY);
+
+        assert_eq!(diff.moved_blocks.len(), 3);
+        for y in 0..3 {
+            assert_eq!(
+                world.get_block(&WorldPos::new(1, y, 1)).block_type,
+                BlockType::Sand
+            );
+        }
+        for y in 3..8 {
+            assert_eq!(
+                world.get_block(&WorldPos::new(1, y, 1)).block_type,
+                BlockType::Void
+            );
+        }
+        assert_eq!(
+            world.get_block(&WorldPos::new(1, 8, 1)).block_type,
+            BlockType::Stone
+        );
+    }
+
+    #[test]
+    fn sand_sinks_through_water() {
+        let mut world = make_world();
+        world.add_block(&block(BlockType::Stone, 3, 0, 3)).unwrap();
+        world.add_block(&block(BlockType::Water, 3, 1, 3)).unwrap();
+        world.add_block(&block(BlockType::Water, 3, 2, 3)).unwrap();
+
+        world.add_block(&block(BlockType::Sand, 3, 3, 3)).unwrap();
+        run_ticks(&mut world, FALL_TICK_DELAY);
+
+        assert_eq!(
+            world.get_block(&WorldPos::new(3, 1, 3)).block_type,
+            BlockType::Sand
+        );
+        assert_eq!(
+            world.get_block(&WorldPos::new(3, 3, 3)).block_type,
+            BlockType::Void
+        );
+    }
+}
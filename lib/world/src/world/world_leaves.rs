@@ -0,0 +1,270 @@
+use super::{
+    world_block::WorldBlock,
+    world_tick::{TickKind, LEAF_TICK_DELAY},
+    World, WorldStateDiff,
+};
+use crate::{
+    block::{block_state::BlockProperty, BlockData, BlockType},
+    direction::{Direction, Directions},
+    positions::WorldPos,
+};
+use std::collections::HashSet;
+
+/** Leaves this many blocks or further from a log decay. Leaves touching a log are 1 away */
+pub const LEAF_DECAY_DISTANCE: u8 = 7;
+/** One in this many decaying leaves drops a flower on the ground below */
+const FLOWER_DROP_CHANCE: i16 = 20;
+
+impl World {
+    /**
+     * Walks out through connected leaves looking for the nearest log.
+     * Gives up at LEAF_DECAY_DISTANCE, the leaves are too far from a log to survive.
+     */
+    fn find_leaf_distance(&self, world_pos: &WorldPos) -> u8 {
+        let mut visited = HashSet::new();
+        visited.insert(*world_pos);
+        let mut frontier = vec![*world_pos];
+
+        for distance in 1..LEAF_DECAY_DISTANCE {
+            let mut next = Vec::new();
+            for pos in frontier {
+                for direction in Directions::all() {
+                    let neighbour_pos = pos.move_direction(&direction);
+                    if !visited.insert(neighbour_pos) {
+                        continue;
+                    }
+                    let block_type = self.get_block(&neighbour_pos).block_type;
+                    if block_type == BlockType::Wood {
+                        return distance;
+                    }
+                    if block_type == BlockType::Leaf {
+                        next.push(neighbour_pos);
+                    }
+                }
+            }
+            frontier = next;
+        }
+        LEAF_DECAY_DISTANCE
+    }
+
+    /** Schedules distance updates for any leaves at or next to the position */
+    pub(super) fn schedule_leaf_updates(&mut self, world_pos: &WorldPos) {
+        for pos in world_pos.get_cross_vecs() {
+            if self.get_block(&pos).block_type == BlockType::Leaf {
+                self.schedule_tick(pos, TickKind::LeafDistance, LEAF_TICK_DELAY);
+            }
+        }
+    }
+
+    /** Stores the distance in the leaf's block state, keeping its other properties */
+    fn set_leaf_distance(&mut self, leaf: WorldBlock, distance: u8) {
+        let state = leaf.extra_data.get_state();
+        if let Ok(state) = state.with(BlockProperty::Distance, distance) {
+            // Leaves look the same at any distance so there is nothing to remesh
+            self.write_block(&WorldBlock {
+                extra_data: BlockData::from_state(state),
+                ..leaf
+            })
+            .ok();
+        }
+    }
+
+    /**
+     * Recalculates the distance of the leaves at each position when their ticks come due.
+     * A changed distance schedules the leaves around it, so cutting a trunk spreads through the whole canopy.
+     */
+    pub(super) fn update_leaf_distances(&mut self, pending: Vec<WorldPos>) {
+        for world_pos in pending {
+            let leaf = self.get_block(&world_pos);
+            if leaf.block_type != BlockType::Leaf {
+                continue;
+            }
+            let distance = self.find_leaf_distance(&world_pos);
+            if leaf.extra_data.get_state().get(BlockProperty::Distance) != distance {
+                self.set_leaf_distance(leaf, distance);
+            }
+        }
+    }
+
+    /**
+     * Leaves too far from a log decay when they get a random tick, unless a player placed them.
+     * Generated leaves haven't worked out their distance yet, so they do that first.
+     */
+    pub(super) fn random_tick_leaf(&mut self, world_pos: &WorldPos) -> WorldStateDiff {
+        let leaf = self.get_block(world_pos);
+        let state = leaf.extra_data.get_state();
+        if state.is_persistent() {
+            return WorldStateDiff::default();
+        }
+
+        let distance = match state.get(BlockProperty::Distance) {
+            0 => {
+                let distance = self.find_leaf_distance(world_pos);
+                if distance < LEAF_DECAY_DISTANCE {
+                    self.set_leaf_distance(leaf, distance);
+                }
+                distance
+            }
+            distance => distance,
+        };
+        if distance < LEAF_DECAY_DISTANCE {
+            return WorldStateDiff::default();
+        }
+        self.decay_leaf(world_pos)
+    }
+
+    fn decay_leaf(&mut self, world_pos: &WorldPos) -> WorldStateDiff {
+        let mut diff = match self.remove_block(world_pos) {
+            Ok(diff) => diff,
+            Err(_) => return WorldStateDiff::default(),
+        };
+        if self.random_below(FLOWER_DROP_CHANCE) == 0 {
+            if let Some(flower_pos) = self.find_flower_spot(world_pos) {
+                let flower = WorldBlock {
+                    block_type: BlockType::RedFlower.into(),
+                    extra_data: BlockData::None,
+                    world_pos: flower_pos,
+                };
+                if let Ok(placed) = self.add_block(&flower) {
+                    diff.extend(placed);
+                }
+            }
+        }
+        diff
+    }
+
+    /** The empty block on top of the first grass under the position, if the drop lands on grass */
+    fn find_flower_spot(&self, world_pos: &WorldPos) -> Option<WorldPos> {
+        let mut pos = world_pos.move_direction(&Direction::Down);
+        while self.is_block_loaded(&pos) && self.get_block(&pos).block_type == BlockType::Void {
+            pos = pos.move_direction(&Direction::Down);
+        }
+        (self.get_block(&pos).block_type == BlockType::Grass)
+            .then(|| pos.move_direction(&Direction::Up))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LEAF_DECAY_DISTANCE;
+    use crate::{
+        block::{
+            block_state::{BlockProperty, BlockState},
+            BlockData, BlockType,
+        },
+        chunk::Chunk,
+        positions::{ChunkPos, WorldPos},
+        world::{world_block::WorldBlock, World},
+    };
+
+    fn block(block_type: BlockType, x: i32, y: i32, z: i32) -> WorldBlock {
+        WorldBlock {
+            block_type: block_type.into(),
+            extra_data: BlockData::None,
+            world_pos: WorldPos::new(x, y, z),
+        }
+    }
+
+    fn settle(world: &mut World) {
+        for _ in 0..100 {
+            if !world.has_scheduled_ticks() {
+                return;
+            }
+            world.run_tick();
+        }
+        panic!("Leaf distances never settled");
+    }
+
+    fn distance_at(world: &World, x: i32) -> u8 {
+        world
+            .get_block(&WorldPos::new(x, 5, 0))
+            .extra_data
+            .get_state()
+            .get(BlockProperty::Distance)
+    }
+
+    /** A log at x 0 with a branch of leaves running out along x */
+    fn make_branch(leaves: i32) -> World {
+        let mut world = World::default();
+        world.insert_chunk(Chunk::new(ChunkPos { x: 0, y: 0 }));
+        world.add_block(&block(BlockType::Wood, 0, 5, 0)).unwrap();
+        for x in 1..=leaves {
+            world.add_block(&block(BlockType::Leaf, x, 5, 0)).unwrap();
+        }
+        settle(&mut world);
+        world
+    }
+
+    #[test]
+    fn leaves_track_distance_to_logs() {
+        let mut world = make_branch(8);
+        for x in 1..LEAF_DECAY_DISTANCE as i32 {
+            assert_eq!(distance_at(&world, x), x as u8);
+        }
+        assert_eq!(distance_at(&world, 7), LEAF_DECAY_DISTANCE);
+        assert_eq!(distance_at(&world, 8), LEAF_DECAY_DISTANCE);
+
+        // Cutting the log leaves the whole branch too far from a log
+        world.remove_block(&WorldPos::new(0, 5, 0)).unwrap();
+        settle(&mut world);
+        for x in 1..=8 {
+            assert_eq!(distance_at(&world, x), LEAF_DECAY_DISTANCE);
+        }
+    }
+
+    #[test]
+    fn only_leaves_far_from_logs_decay() {
+        let mut world = make_branch(8);
+        world.random_tick_block(&WorldPos::new(3, 5, 0));
+        world.random_tick_block(&WorldPos::new(8, 5, 0));
+        assert_eq!(
+            world.get_block(&WorldPos::new(3, 5, 0)).block_type,
+            BlockType::Leaf
+        );
+        assert_eq!(
+            world.get_block(&WorldPos::new(8, 5, 0)).block_type,
+            BlockType::Void
+        );
+
+        // Generated leaves have no distance yet, they work it out on their first random tick
+        let chunk = world.get_mut_chunk(&ChunkPos { x: 0, y: 0 }).unwrap();
+        chunk.add_block(block(BlockType::Leaf, 2, 6, 0).to_chunk_block());
+        chunk.add_block(block(BlockType::Leaf, 10, 5, 0).to_chunk_block());
+        world.random_tick_block(&WorldPos::new(2, 6, 0));
+        world.random_tick_block(&WorldPos::new(10, 5, 0));
+        assert_eq!(
+            world
+                .get_block(&WorldPos::new(2, 6, 0))
+                .extra_data
+                .get_state()
+                .get(BlockProperty::Distance),
+            3
+        );
+        assert_eq!(
+            world.get_block(&WorldPos::new(10, 5, 0)).block_type,
+            BlockType::Void
+        );
+    }
+
+    #[test]
+    fn persistent_leaves_never_decay() {
+        let mut world = World::default();
+        world.insert_chunk(Chunk::new(ChunkPos { x: 0, y: 0 }));
+        let state = BlockState::default()
+            .with(BlockProperty::Persistent, 1)
+            .unwrap();
+        world
+            .add_block(&WorldBlock {
+                extra_data: BlockData::from_state(state),
+                ..block(BlockType::Leaf, 4, 5, 4)
+            })
+            .unwrap();
+        settle(&mut world);
+
+        world.random_tick_block(&WorldPos::new(4, 5, 4));
+        assert_eq!(
+            world.get_block(&WorldPos::new(4, 5, 4)).block_type,
+            BlockType::Leaf
+        );
+    }
+}
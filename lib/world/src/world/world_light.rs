@@ -0,0 +1,475 @@
+use super::World;
+use crate::{
+    block::{BlockId, BlockMetaData},
+    chunk::{
+        chunk_light::{LightChannel, LightLevel, MAX_LIGHT},
+        CHUNK_WIDTH, SECTION_HEIGHT,
+    },
+    direction::{Direction, EVERY_DIRECTION},
+    positions::{ChunkPos, InnerChunkPos, WorldPos},
+};
+use std::collections::{HashSet, VecDeque};
+
+const CHANNELS: [LightChannel; 2] = [LightChannel::Sky, LightChannel::Block];
+
+/** The light a neighbor in `direction` gets from a block lit at `level` */
+fn spread_level(channel: LightChannel, direction: Direction, level: u8, filter: u8) -> u8 {
+    // Sunlight falling straight down doesn't fade until it passes through something
+    if channel == LightChannel::Sky
+        && direction == Direction::Down
+        && level == MAX_LIGHT
+        && filter == 0
+    {
+        return MAX_LIGHT;
+    }
+    level.saturating_sub(1 + filter)
+}
+
+impl World {
+    /**
+     * Everything above the world is lit by the sky and everything below it is dark.
+     * Unloaded chunks are treated as open sky.
+     */
+    pub fn get_light(&self, world_pos: &WorldPos) -> LightLevel {
+        if world_pos.y >= self.height as i32 {
+            return LightLevel::FULL_SKY;
+        }
+        if world_pos.y < 0 {
+            return LightLevel::DARK;
+        }
+        self.get_chunk(&world_pos.to_chunk_pos())
+            .map_or(LightLevel::FULL_SKY, |chunk| {
+                chunk.get_light(&world_pos.to_inner_chunk_pos())
+            })
+    }
+
+    fn get_light_channel(&self, world_pos: &WorldPos, channel: LightChannel) -> u8 {
+        self.get_light(world_pos).get(channel)
+    }
+
+    /** Light can only be stored inside the world in loaded chunks */
+    fn can_hold_light(&self, world_pos: &WorldPos) -> bool {
+        world_pos.is_valid(self.height) && self.has_chunk(&world_pos.to_chunk_pos())
+    }
+
+    fn set_light_channel(
+        &mut self,
+        world_pos: &WorldPos,
+        channel: LightChannel,
+        level: u8,
+        changed: &mut HashSet<i32>,
+    ) {
+        if !world_pos.is_valid(self.height) {
+            return;
+        }
+        let chunk_pos = world_pos.to_chunk_pos();
+        if let Ok(chunk) = self.get_mut_chunk(&chunk_pos) {
+            chunk.set_light(&world_pos.to_inner_chunk_pos(), channel, level);
+            changed.insert(chunk_pos.to_world_index());
+        }
+    }
+
+    fn get_block_metadata(&self, world_pos: &WorldPos) -> BlockMetaData {
+        BlockMetaData::get_for_type(self.get_block(world_pos).block_type)
+    }
+
+    fn get_light_emission(&self, world_pos: &WorldPos, channel: LightChannel) -> u8 {
+        match channel {
+            LightChannel::Sky => 0,
+            LightChannel::Block => self.get_block_metadata(world_pos).light_emission,
+        }
+    }
+
+    /** Flood fills light outwards from every position in the queue */
+    fn propagate_light(
+        &mut self,
+        channel: LightChannel,
+        mut queue: VecDeque<WorldPos>,
+        changed: &mut HashSet<i32>,
+    ) {
+        while let Some(world_pos) = queue.pop_front() {
+            // Unloaded chunks don't have real light to give
+            if world_pos.is_valid(self.height) && !self.can_hold_light(&world_pos) {
+                continue;
+            }
+            let level = self.get_light_channel(&world_pos, channel);
+            if level == 0 {
+                continue;
+            }
+
+            for direction in EVERY_DIRECTION {
+                let neighbor = world_pos.move_direction(&direction);
+                if !self.can_hold_light(&neighbor) {
+                    continue;
+                }
+                let filter = self.get_block_metadata(&neighbor).light_filter;
+                let new_level = spread_level(channel, direction, level, filter);
+                if new_level > self.get_light_channel(&neighbor, channel) {
+                    self.set_light_channel(&neighbor, channel, new_level, changed);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+    }
+
+    /**
+     * Darkens everything that was lit by the positions in the queue, which have already been darkened.
+     * Returns the positions on the edge of the dark area that are lit by something else,
+     * their light needs to be spread back in.
+     */
+    fn remove_light(
+        &mut self,
+        channel: LightChannel,
+        mut queue: VecDeque<(WorldPos, u8)>,
+        changed: &mut HashSet<i32>,
+    ) -> VecDeque<WorldPos> {
+        let mut relight = VecDeque::new();
+        while let Some((world_pos, old_level)) = queue.pop_front() {
+            for direction in EVERY_DIRECTION {
+                let neighbor = world_pos.move_direction(&direction);
+                if !self.can_hold_light(&neighbor) {
+                    continue;
+                }
+                let level = self.get_light_channel(&neighbor, channel);
+                if level == 0 {
+                    continue;
+                }
+
+                let lit_by_removed = level < old_level
+                    || (channel == LightChannel::Sky
+                        && direction == Direction::Down
+                        && old_level == MAX_LIGHT
+                        && level == MAX_LIGHT);
+
+                if lit_by_removed {
+                    self.set_light_channel(&neighbor, channel, 0, changed);
+                    queue.push_back((neighbor, level));
+
+                    let emission = self.get_light_emission(&neighbor, channel);
+                    if emission > 0 {
+                        self.set_light_channel(&neighbor, channel, emission, changed);
+                        relight.push_back(neighbor);
+                    }
+                } else {
+                    relight.push_back(neighbor);
+                }
+            }
+        }
+        relight
+    }
+
+    /**
+     * Recalculates the light around a block that was just replaced.
+     * Returns the indices of the chunks whose light changed.
+     */
+    pub(super) fn update_light_at(
+        &mut self,
+        world_pos: &WorldPos,
+        old_block_type: BlockId,
+    ) -> HashSet<i32> {
+        let mut changed = HashSet::new();
+        let old_metadata = BlockMetaData::get_for_type(old_block_type);
+        let new_metadata = self.get_block_metadata(world_pos);
+        if old_metadata.light_filter == new_metadata.light_filter
+            && old_metadata.light_emission == new_metadata.light_emission
+        {
+            return changed;
+        }
+
+        for channel in CHANNELS {
+            let old_level = self.get_light_channel(world_pos, channel);
+            self.set_light_channel(world_pos, channel, 0, &mut changed);
+            let mut relight = self.remove_light(
+                channel,
+                VecDeque::from([(*world_pos, old_level)]),
+                &mut changed,
+            );
+
+            let emission = self.get_light_emission(world_pos, channel);
+            if emission > 0 {
+                self.set_light_channel(world_pos, channel, emission, &mut changed);
+                relight.push_back(*world_pos);
+            }
+            // Pull light back in from the neighbors, including the sky above the world
+            for direction in EVERY_DIRECTION {
+                relight.push_back(world_pos.move_direction(&direction));
+            }
+
+            self.propagate_light(channel, relight, &mut changed);
+        }
+
+        changed
+    }
+
+    /**
+     * Calculates all of the light in a chunk that was just inserted,
+     * then spreads light across the borders it shares with loaded neighbors.
+     * Returns the indices of the chunks whose light changed.
+     */
+    pub(super) fn light_chunk(&mut self, chunk_pos: &ChunkPos) -> HashSet<i32> {
+        let mut changed = HashSet::new();
+        let mut sky_queue = VecDeque::new();
+        let mut block_queue = VecDeque::new();
+
+        let chunk = match self.get_mut_chunk(chunk_pos) {
+            Ok(chunk) => chunk,
+            Err(_) => return changed,
+        };
+        chunk.reset_light();
+        let lit_height = chunk.get_light_section_count() * SECTION_HEIGHT as usize;
+
+        // Sunlight falls straight down every column until something stops it
+        for x in 0..CHUNK_WIDTH as u8 {
+            for z in 0..CHUNK_WIDTH as u8 {
+                let mut sky = MAX_LIGHT;
+                for y in (0..lit_height).rev() {
+                    let pos = InnerChunkPos::new(x, y as u8, z);
+                    let metadata = BlockMetaData::get_for_type(chunk.get_block(&pos).block_type);
+                    sky = spread_level(
+                        LightChannel::Sky,
+                        Direction::Down,
+                        sky,
+                        metadata.light_filter,
+                    );
+                    if sky > 0 {
+                        chunk.set_light(&pos, LightChannel::Sky, sky);
+                        sky_queue.push_back(pos.to_world_pos(chunk_pos));
+                    }
+
+                    let emission = metadata.light_emission;
+                    if emission > 0 {
+                        chunk.set_light(&pos, LightChannel::Block, emission);
+                        block_queue.push_back(pos.to_world_pos(chunk_pos));
+                    }
+                }
+            }
+        }
+        changed.insert(chunk_pos.to_world_index());
+
+        // Spread light both ways across the borders with loaded neighbors, up to the highest lit section of either
+        let border_height = chunk_pos
+            .get_adjacent_vecs()
+            .iter()
+            .filter_map(|neighbor| self.get_chunk(neighbor).ok())
+            .map(|neighbor| neighbor.get_light_section_count() * SECTION_HEIGHT as usize)
+            .fold(lit_height, usize::max)
+            .min(self.height as usize);
+
+        let chunk_origin = InnerChunkPos::new(0, 0, 0).to_world_pos(chunk_pos);
+        let last = CHUNK_WIDTH as i32 - 1;
+        for x in 0..CHUNK_WIDTH as i32 {
+            for z in 0..CHUNK_WIDTH as i32 {
+                if x != 0 && x != last && z != 0 && z != last {
+                    continue;
+                }
+                for y in 0..border_height as i32 {
+                    let world_pos = WorldPos::new(chunk_origin.x + x, y, chunk_origin.z + z);
+                    let outside = [
+                        Direction::North,
+                        Direction::South,
+                        Direction::East,
+                        Direction::West,
+                    ]
+                    .iter()
+                    .map(|direction| world_pos.move_direction(direction))
+                    .filter(|neighbor| neighbor.to_chunk_pos() != *chunk_pos);
+                    for world_pos in std::iter::once(world_pos).chain(outside) {
+                        sky_queue.push_back(world_pos);
+                        block_queue.push_back(world_pos);
+                    }
+                }
+            }
+        }
+
+        self.propagate_light(LightChannel::Sky, sky_queue, &mut changed);
+        self.propagate_light(LightChannel::Block, block_queue, &mut changed);
+
+        changed
+    }
+
+    /** Turns chunk indices into the ids used by `WorldStateDiff` */
+    pub(super) fn get_chunk_ids(&self, chunk_indices: &HashSet<i32>) -> HashSet<String> {
+        chunk_indices
+            .iter()
+            .filter_map(|index| self.chunks.get(index))
+            .map(|chunk| chunk.position.to_index())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        block::{BlockData, BlockType, ChunkBlock},
+        chunk::{chunk_light::LightLevel, Chunk},
+        positions::{ChunkPos, InnerChunkPos, WorldPos},
+        world::{world_block::WorldBlock, World},
+    };
+
+    /** A chunk with a stone roof at y = 5 over the whole chunk */
+    fn roofed_chunk(chunk_pos: ChunkPos) -> Chunk {
+        let mut chunk = Chunk::new(chunk_pos);
+        for x in 0..16 {
+            for z in 0..16 {
+                chunk.add_block(ChunkBlock {
+                    pos: InnerChunkPos::new(x, 5, z),
+                    block_type: BlockType::Stone.into(),
+                    extra_data: BlockData::None,
+                });
+            }
+        }
+        chunk
+    }
+
+    fn sky(world: &World, x: i32, y: i32, z: i32) -> u8 {
+        world.get_light(&WorldPos::new(x, y, z)).sky
+    }
+
+    #[test]
+    fn sunlight_falls_down_and_spreads_under_overhangs() {
+        let mut world = World::default();
+        world.insert_chunk(Chunk::new(ChunkPos::new(0, 0)));
+        world
+            .add_block(&WorldBlock {
+                block_type: BlockType::Stone.into(),
+                extra_data: BlockData::None,
+                world_pos: WorldPos::new(8, 10, 8),
+            })
+            .unwrap();
+
+        assert_eq!(sky(&world, 8, 11, 8), 15);
+        assert_eq!(sky(&world, 8, 10, 8), 0);
+        // Lit from the open columns right next to it
+        assert_eq!(sky(&world, 8, 9, 8), 14);
+        assert_eq!(sky(&world, 8, 0, 8), 14);
+        assert_eq!(sky(&world, 9, 9, 8), 15);
+    }
+
+    #[test]
+    fn roofs_block_the_sky() {
+        let mut world = World::default();
+        world.insert_chunk(roofed_chunk(ChunkPos::new(0, 0)));
+
+        assert_eq!(sky(&world, 3, 6, 3), 15);
+        assert_eq!(sky(&world, 3, 5, 3), 0);
+        assert_eq!(sky(&world, 3, 4, 3), 0);
+        assert_eq!(world.get_light(&WorldPos::new(3, 0, 3)), LightLevel::DARK);
+    }
+
+    #[test]
+    fn light_spreads_through_holes_and_is_removed_again() {
+        let mut world = World::default();
+        world.insert_chunk(roofed_chunk(ChunkPos::new(0, 0)));
+
+        world.remove_block(&WorldPos::new(8, 5, 8)).unwrap();
+        assert_eq!(sky(&world, 8, 5, 8), 15);
+        assert_eq!(sky(&world, 8, 0, 8), 15);
+        assert_eq!(sky(&world, 10, 4, 8), 13);
+        assert_eq!(sky(&world, 8, 4, 12), 11);
+
+        world
+            .add_block(&WorldBlock {
+                block_type: BlockType::Stone.into(),
+                extra_data: BlockData::None,
+                world_pos: WorldPos::new(8, 5, 8),
+            })
+            .unwrap();
+        assert_eq!(sky(&world, 8, 5, 8), 0);
+        assert_eq!(sky(&world, 8, 0, 8), 0);
+        assert_eq!(sky(&world, 10, 4, 8), 0);
+    }
+
+    #[test]
+    fn light_crosses_chunk_borders() {
+        let mut world = World::default();
+        world.insert_chunk(roofed_chunk(ChunkPos::new(0, 0)));
+        world.insert_chunk(roofed_chunk(ChunkPos::new(1, 0)));
+        assert_eq!(sky(&world, 15, 4, 8), 0);
+
+        let diff = world.remove_block(&WorldPos::new(16, 5, 8)).unwrap();
+
+        assert_eq!(sky(&world, 16, 4, 8), 15);
+        assert_eq!(sky(&world, 15, 4, 8), 14);
+        assert_eq!(sky(&world, 14, 4, 8), 13);
+        assert!(diff.chunk_ids.contains(&ChunkPos::new(0, 0).to_index()));
+    }
+
+    #[test]
+    fn inserted_chunks_light_their_neighbors() {
+        let mut world = World::default();
+        world.insert_chunk(roofed_chunk(ChunkPos::new(0, 0)));
+        assert_eq!(sky(&world, 15, 4, 8), 0);
+
+        // The open chunk next door lets light in under the edge of the roof
+        let diff = world.insert_chunk(Chunk::new(ChunkPos::new(1, 0)));
+
+        assert_eq!(sky(&world, 15, 4, 8), 14);
+        assert_eq!(sky(&world, 12, 4, 8), 11);
+        assert!(diff.chunk_ids.contains(&ChunkPos::new(0, 0).to_index()));
+    }
+
+    fn place(world: &mut World, block_type: BlockType, x: i32, y: i32, z: i32) {
+        world
+            .add_block(&WorldBlock {
+                block_type: block_type.into(),
+                extra_data: BlockData::None,
+                world_pos: WorldPos::new(x, y, z),
+            })
+            .unwrap();
+    }
+
+    fn block_light(world: &World, x: i32, y: i32, z: i32) -> u8 {
+        world.get_light(&WorldPos::new(x, y, z)).block
+    }
+
+    #[test]
+    fn torches_light_up_caves_until_removed() {
+        let mut world = World::default();
+        world.insert_chunk(roofed_chunk(ChunkPos::new(0, 0)));
+
+        place(&mut world, BlockType::Torch, 8, 1, 8);
+        assert_eq!(block_light(&world, 8, 1, 8), 14);
+        assert_eq!(block_light(&world, 8, 1, 10), 12);
+        assert_eq!(block_light(&world, 8, 4, 8), 11);
+        // The roof stops it
+        assert_eq!(block_light(&world, 8, 6, 8), 0);
+        assert_eq!(sky(&world, 8, 1, 8), 0);
+
+        world.remove_block(&WorldPos::new(8, 1, 8)).unwrap();
+        assert_eq!(block_light(&world, 8, 1, 8), 0);
+        assert_eq!(block_light(&world, 8, 1, 10), 0);
+    }
+
+    #[test]
+    fn overlapping_lights_keep_the_brightest() {
+        let mut world = World::default();
+        world.insert_chunk(roofed_chunk(ChunkPos::new(0, 0)));
+
+        place(&mut world, BlockType::Glowstone, 2, 1, 8);
+        place(&mut world, BlockType::Torch, 6, 1, 8);
+        // Glowstone is solid, so it only lights its neighbors
+        assert_eq!(block_light(&world, 3, 1, 8), 14);
+        assert_eq!(block_light(&world, 4, 1, 8), 13);
+        assert_eq!(block_light(&world, 5, 1, 8), 13);
+
+        world.remove_block(&WorldPos::new(6, 1, 8)).unwrap();
+        assert_eq!(block_light(&world, 4, 1, 8), 13);
+        assert_eq!(block_light(&world, 5, 1, 8), 12);
+        assert_eq!(block_light(&world, 6, 1, 8), 11);
+    }
+
+    #[test]
+    fn inserted_chunks_light_their_emitters() {
+        let mut chunk = roofed_chunk(ChunkPos::new(0, 0));
+        chunk.add_block(ChunkBlock {
+            pos: InnerChunkPos::new(4, 0, 4),
+            block_type: BlockType::Torch.into(),
+            extra_data: BlockData::None,
+        });
+        let mut world = World::default();
+        world.insert_chunk(chunk);
+
+        assert_eq!(block_light(&world, 4, 0, 4), 14);
+        assert_eq!(block_light(&world, 4, 0, 7), 11);
+    }
+}
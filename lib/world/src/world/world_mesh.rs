@@ -2,7 +2,10 @@ use std::collections::HashSet;
 
 use super::{ChunkNotLoadedError, World, WorldStateDiff};
 use crate::{
-    chunk::chunk_mesh::{BlockMesh, ChunkMesh},
+    chunk::{
+        chunk_geometry::ChunkGeometry,
+        chunk_mesh::{BlockMesh, ChunkMesh},
+    },
     positions::{ChunkPos, WorldPos},
 };
 
@@ -32,6 +35,16 @@ impl World {
             .ok_or(ChunkNotLoadedError)
     }
 
+    /** Builds greedy meshed vertex buffers for a chunk from its visible faces */
+    pub fn get_chunk_geometry(
+        &self,
+        chunk_pos: &ChunkPos,
+    ) -> Result<ChunkGeometry, ChunkNotLoadedError> {
+        let chunk = self.get_chunk(chunk_pos)?;
+        let mesh = self.get_chunk_mesh(chunk_pos)?;
+        Ok(ChunkGeometry::from_chunk(chunk, mesh, self))
+    }
+
     fn get_chunk_mesh_mut(
         &mut self,
         chunk_pos: &ChunkPos,
@@ -50,6 +63,25 @@ impl World {
         Ok(())
     }
 
+    /**
+     * Updates the faces of each block and its neighbours rather than whole chunks.
+     * Used when lots of blocks change at once. Returns the ids of the chunks touched.
+     */
+    pub(super) fn update_meshes_around_blocks(
+        &mut self,
+        world_positions: &[WorldPos],
+    ) -> HashSet<String> {
+        let mut updated_ids = HashSet::new();
+        for world_pos in world_positions {
+            for pos in world_pos.get_cross_vecs() {
+                if self.update_mesh_at_pos(pos).is_ok() {
+                    updated_ids.insert(pos.to_chunk_pos().to_index());
+                }
+            }
+        }
+        updated_ids
+    }
+
     /**
      * Updates all chunks surrounding a block.
      * Does not update the chunk the block is in. ( I think this is false now? )
@@ -75,6 +107,7 @@ impl World {
 
         WorldStateDiff {
             chunk_ids: updated_ids,
+            ..Default::default()
         }
     }
 }
@@ -100,7 +133,7 @@ mod tests {
 
         world
             .add_block(&WorldBlock {
-                block_type: BlockType::Cloud,
+                block_type: BlockType::Cloud.into(),
                 extra_data: BlockData::None,
                 world_pos,
             })
@@ -130,7 +163,7 @@ mod tests {
 
         world
             .add_block(&WorldBlock {
-                block_type: BlockType::Cloud,
+                block_type: BlockType::Cloud.into(),
                 extra_data: BlockData::None,
                 world_pos,
             })
@@ -138,7 +171,7 @@ mod tests {
 
         world
             .add_block(&WorldBlock {
-                block_type: BlockType::Cloud,
+                block_type: BlockType::Cloud.into(),
                 extra_data: BlockData::None,
                 world_pos: adjacent_world_pos,
             })
@@ -169,7 +202,7 @@ mod tests {
 
         world
             .add_block(&WorldBlock {
-                block_type: BlockType::Cloud,
+                block_type: BlockType::Cloud.into(),
                 extra_data: BlockData::None,
                 world_pos,
             })
@@ -177,7 +210,7 @@ mod tests {
 
         world
             .add_block(&WorldBlock {
-                block_type: BlockType::Cloud,
+                block_type: BlockType::Cloud.into(),
                 extra_data: BlockData::None,
                 world_pos: adjacent_world_pos,
             })
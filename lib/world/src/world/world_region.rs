@@ -0,0 +1,81 @@
+use super::{World, WorldStateDiff};
+use crate::{
+    chunk::Chunk,
+    positions::ChunkPos,
+    region::{ChunkWriter, RegionError, RegionFile, RegionStore},
+};
+use std::io::{Read, Seek, Write};
+
+impl World {
+    /** Inserts a chunk that was just read from disk, so it doesn't need saving again */
+    fn insert_saved_chunk(&mut self, chunk: Option<Chunk>) -> Option<WorldStateDiff> {
+        chunk.map(|chunk| {
+            let index = chunk.position.to_world_index();
+            let diff = self.insert_chunk(chunk);
+            self.unsaved_chunks.remove(&index);
+            diff
+        })
+    }
+
+    /**
+     * Loads a single chunk out of a region into the world.
+     * Returns None when the region doesn't have the chunk yet.
+     */
+    pub fn load_chunk_from_region<S: Read + Write + Seek>(
+        &mut self,
+        region: &mut RegionFile<S>,
+        chunk_pos: &ChunkPos,
+    ) -> Result<Option<WorldStateDiff>, RegionError> {
+        let chunk = region.read_chunk(chunk_pos)?;
+        Ok(self.insert_saved_chunk(chunk))
+    }
+
+    pub fn save_chunk_to_region<S: Read + Write + Seek>(
+        &mut self,
+        region: &mut RegionFile<S>,
+        chunk_pos: &ChunkPos,
+    ) -> Result<(), RegionError> {
+        let chunk = self
+            .get_chunk(chunk_pos)
+            .map_err(|_| RegionError::ChunkNotLoaded(*chunk_pos))?;
+        region.write_chunk(chunk)?;
+        self.unsaved_chunks.remove(&chunk_pos.to_world_index());
+        Ok(())
+    }
+
+    /** Loads a chunk from the store if it has been saved before */
+    pub fn load_chunk_from_store(
+        &mut self,
+        store: &mut RegionStore,
+        chunk_pos: &ChunkPos,
+    ) -> Result<Option<WorldStateDiff>, RegionError> {
+        let chunk = store.read_chunk(chunk_pos)?;
+        Ok(self.insert_saved_chunk(chunk))
+    }
+
+    /**
+     * Writes every chunk that changed since it was last saved or loaded, and returns how many were written.
+     * A chunk only stops being unsaved once it is written. If any writes fail the rest are still tried,
+     * the failed chunks are left for the next save, and the first error is returned.
+     */
+    pub fn save_to_store<W: ChunkWriter>(&mut self, store: &mut W) -> Result<usize, RegionError> {
+        let mut saved = 0;
+        let mut first_error = None;
+        let indices: Vec<i32> = self.unsaved_chunks.iter().copied().collect();
+        for index in indices {
+            if let Some(chunk) = self.chunks.get(&index) {
+                if let Err(e) = store.write_chunk(chunk) {
+                    first_error.get_or_insert(e);
+                    continue;
+                }
+                saved += 1;
+            }
+            self.unsaved_chunks.remove(&index);
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(saved),
+        }
+    }
+}
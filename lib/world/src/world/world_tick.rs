@@ -0,0 +1,344 @@
+use super::{World, WorldStateDiff};
+use crate::{
+    block::BlockType,
+    chunk::{CHUNK_WIDTH, SECTION_HEIGHT},
+    positions::{InnerChunkPos, WorldPos},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashSet},
+};
+use wasm_bindgen::prelude::*;
+
+pub const TICKS_PER_SECOND: u32 = 20;
+/** If the game falls further behind than this the extra time is dropped instead of caught up */
+const MAX_TICKS_PER_CALL: u32 = 10;
+/** How many random blocks get a random tick in each allocated chunk section every tick */
+pub const RANDOM_TICKS_PER_SECTION: usize = 3;
+pub const FLUID_TICK_DELAY: u64 = 5;
+pub const FALL_TICK_DELAY: u64 = 2;
+pub const LEAF_TICK_DELAY: u64 = 1;
+
+/** What a scheduled tick does when it comes due */
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum TickKind {
+    /** Recalculate the fluid at the position */
+    Fluid,
+    /** Drop the block at the position if it is gravitable and has nothing under it */
+    Fall,
+    /** Recalculate how far the leaves at the position are from a log */
+    LeafDistance,
+}
+
+struct ScheduledTick {
+    due: u64,
+    /** Ticks due at the same time run in the order they were scheduled */
+    order: u64,
+    world_pos: WorldPos,
+    kind: TickKind,
+}
+
+impl PartialEq for ScheduledTick {
+    fn eq(&self, other: &Self) -> bool {
+        self.due == other.due && self.order == other.order
+    }
+}
+
+impl Eq for ScheduledTick {}
+
+impl PartialOrd for ScheduledTick {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScheduledTick {
+    /** Reversed so the binary heap pops the earliest tick first */
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .due
+            .cmp(&self.due)
+            .then_with(|| other.order.cmp(&self.order))
+    }
+}
+
+/** Xorshift, plenty random enough to pick blocks for random ticks */
+struct TickRng(u64);
+
+impl TickRng {
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    fn below(&mut self, max: i16) -> u8 {
+        (self.next() % max as u64) as u8
+    }
+}
+
+/** The world's clock, ticks waiting to run and the random state for random ticks */
+pub(super) struct TickScheduler {
+    current_tick: u64,
+    /** Seconds passed to tick that haven't added up to a whole tick yet */
+    unspent_time: f32,
+    next_order: u64,
+    queue: BinaryHeap<ScheduledTick>,
+    scheduled: HashSet<(WorldPos, TickKind)>,
+    rng: TickRng,
+}
+
+impl Default for TickScheduler {
+    fn default() -> Self {
+        TickScheduler {
+            current_tick: 0,
+            unspent_time: 0.0,
+            next_order: 0,
+            queue: BinaryHeap::new(),
+            scheduled: HashSet::new(),
+            rng: TickRng(0x2545_F491_4F6C_DD1D),
+        }
+    }
+}
+
+impl World {
+    pub fn get_current_tick(&self) -> u64 {
+        self.ticks.current_tick
+    }
+
+    /**
+     * Runs the tick kind at the position after delay ticks, at least one tick from now.
+     * Does nothing if the same kind of tick is already waiting at the position.
+     */
+    pub fn schedule_tick(&mut self, world_pos: WorldPos, kind: TickKind, delay: u64) {
+        let ticks = &mut self.ticks;
+        if !ticks.scheduled.insert((world_pos, kind)) {
+            return;
+        }
+        ticks.queue.push(ScheduledTick {
+            due: ticks.current_tick + delay.max(1),
+            order: ticks.next_order,
+            world_pos,
+            kind,
+        });
+        ticks.next_order += 1;
+    }
+
+    pub fn has_scheduled_ticks(&self) -> bool {
+        !self.ticks.queue.is_empty()
+    }
+
+    /** Random ticks are deterministic for a given seed */
+    pub fn set_random_tick_seed(&mut self, seed: u64) {
+        // Xorshift gets stuck on 0
+        self.ticks.rng = TickRng(seed.max(1));
+    }
+
+    /**
+     * Advances the world by dt seconds, running a tick for every 1 / TICKS_PER_SECOND seconds.
+     * Time that doesn't make up a whole tick is saved for the next call.
+     */
+    pub fn tick(&mut self, dt: f32) -> WorldStateDiff {
+        let tick_length = 1.0 / TICKS_PER_SECOND as f32;
+        self.ticks.unspent_time += dt.max(0.0);
+
+        let mut diff = WorldStateDiff::default();
+        let mut ticks_run = 0;
+        while self.ticks.unspent_time >= tick_length {
+            if ticks_run == MAX_TICKS_PER_CALL {
+                self.ticks.unspent_time = 0.0;
+                break;
+            }
+            self.ticks.unspent_time -= tick_length;
+            diff.extend(self.run_tick());
+            ticks_run += 1;
+        }
+        diff
+    }
+
+    /** Runs a single tick, every scheduled tick that is due then the random ticks */
+    pub fn run_tick(&mut self) -> WorldStateDiff {
+        self.ticks.current_tick += 1;
+
+        let mut fluid_positions = Vec::new();
+        let mut fall_positions = Vec::new();
+        let mut leaf_positions = Vec::new();
+        while let Some(scheduled) = self.ticks.queue.peek() {
+            if scheduled.due > self.ticks.current_tick {
+                break;
+            }
+            let scheduled = self.ticks.queue.pop().unwrap();
+            self.ticks
+                .scheduled
+                .remove(&(scheduled.world_pos, scheduled.kind));
+            match scheduled.kind {
+                TickKind::Fluid => fluid_positions.push(scheduled.world_pos),
+                TickKind::Fall => fall_positions.push(scheduled.world_pos),
+                TickKind::LeafDistance => leaf_positions.push(scheduled.world_pos),
+            }
+        }
+
+        let mut diff = WorldStateDiff::default();
+        if !fluid_positions.is_empty() {
+            diff.extend(self.update_fluids(fluid_positions));
+        }
+        if !fall_positions.is_empty() {
+            diff.extend(self.drop_blocks(fall_positions));
+        }
+        if !leaf_positions.is_empty() {
+            self.update_leaf_distances(leaf_positions);
+        }
+        for world_pos in self.get_random_tick_positions() {
+            diff.extend(self.random_tick_block(&world_pos));
+        }
+        diff
+    }
+
+    /** Picks RANDOM_TICKS_PER_SECTION random blocks from every allocated section of every loaded chunk */
+    fn get_random_tick_positions(&mut self) -> Vec<WorldPos> {
+        // Sorted so the same seed always picks the same blocks
+        let mut chunk_indices: Vec<&i32> = self.chunks.keys().collect();
+        chunk_indices.sort();
+
+        let rng = &mut self.ticks.rng;
+        let mut positions = Vec::new();
+        for chunk_index in chunk_indices {
+            let chunk = &self.chunks[chunk_index];
+            for section_y in 0..chunk.get_section_count() {
+                if !chunk.has_section(section_y) {
+                    continue;
+                }
+                for _ in 0..RANDOM_TICKS_PER_SECTION {
+                    let inner_pos = InnerChunkPos::new(
+                        rng.below(CHUNK_WIDTH),
+                        section_y as u8 * SECTION_HEIGHT as u8 + rng.below(SECTION_HEIGHT),
+                        rng.below(CHUNK_WIDTH),
+                    );
+                    positions.push(inner_pos.to_world_pos(&chunk.position));
+                }
+            }
+        }
+        positions
+    }
+
+    /** A random number below max, from the same generator that picks random tick blocks */
+    pub(super) fn random_below(&mut self, max: i16) -> u8 {
+        self.ticks.rng.below(max)
+    }
+
+    /** Lets the block at the position react to a random tick. Block types opt in here */
+    pub(super) fn random_tick_block(&mut self, world_pos: &WorldPos) -> WorldStateDiff {
+        if self.get_block(world_pos).block_type == BlockType::Leaf {
+            self.random_tick_leaf(world_pos)
+        } else {
+            WorldStateDiff::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TickKind, RANDOM_TICKS_PER_SECTION, TICKS_PER_SECOND};
+    use crate::{
+        block::{BlockData, BlockType},
+        chunk::Chunk,
+        positions::{ChunkPos, WorldPos},
+        world::{world_block::WorldBlock, World},
+    };
+
+    #[test]
+    fn runs_a_tick_per_twentieth_of_a_second() {
+        let mut world = World::default();
+        world.tick(5.0 / TICKS_PER_SECOND as f32);
+        assert_eq!(world.get_current_tick(), 5);
+
+        // A long pause only catches up ten ticks
+        world.tick(1.0);
+        assert_eq!(world.get_current_tick(), 15);
+
+        // Leftover time carries over
+        world.tick(0.03);
+        assert_eq!(world.get_current_tick(), 15);
+        world.tick(0.03);
+        assert_eq!(world.get_current_tick(), 16);
+    }
+
+    #[test]
+    fn runs_scheduled_ticks_when_due() {
+        let mut world = World::default();
+        world.insert_chunk(Chunk::new(ChunkPos { x: 0, y: 0 }));
+        world
+            .add_block(&WorldBlock {
+                block_type: BlockType::Stone.into(),
+                extra_data: BlockData::None,
+                world_pos: WorldPos::new(0, 0, 0),
+            })
+            .unwrap();
+        world
+            .add_block(&WorldBlock {
+                block_type: BlockType::Stone.into(),
+                extra_data: BlockData::None,
+                world_pos: WorldPos::new(0, 3, 0),
+            })
+            .unwrap();
+        // Sneak sand in under the stone without going through add_block so nothing is scheduled
+        let chunk = world.get_mut_chunk(&ChunkPos { x: 0, y: 0 }).unwrap();
+        chunk.add_block(
+            WorldBlock {
+                block_type: BlockType::Sand.into(),
+                extra_data: BlockData::None,
+                world_pos: WorldPos::new(0, 4, 0),
+            }
+            .to_chunk_block(),
+        );
+
+        world.schedule_tick(WorldPos::new(0, 4, 0), TickKind::Fall, 3);
+        world.schedule_tick(WorldPos::new(0, 4, 0), TickKind::Fall, 1);
+        assert!(world.has_scheduled_ticks());
+
+        // Removing the stone schedules its own check for the block above it
+        world.remove_block(&WorldPos::new(0, 3, 0)).unwrap();
+        world.run_tick();
+        let diff = world.run_tick();
+        assert!(diff.moved_blocks.is_empty());
+        let diff = world.run_tick();
+        assert_eq!(diff.moved_blocks.len(), 1);
+        assert_eq!(
+            world.get_block(&WorldPos::new(0, 1, 0)).block_type,
+            BlockType::Sand
+        );
+    }
+
+    #[test]
+    fn random_ticks_pick_blocks_in_allocated_sections() {
+        let mut world = World::default();
+        let mut chunk = Chunk::new(ChunkPos { x: 1, y: -1 });
+        chunk.add_block(
+            WorldBlock {
+                block_type: BlockType::Stone.into(),
+                extra_data: BlockData::None,
+                world_pos: WorldPos::new(16, 40, -16),
+            }
+            .to_chunk_block(),
+        );
+        world.insert_chunk(chunk);
+        world.set_random_tick_seed(7);
+
+        let positions = world.get_random_tick_positions();
+        // Only the section holding the stone has blocks to tick
+        assert_eq!(positions.len(), RANDOM_TICKS_PER_SECTION);
+        for pos in &positions {
+            assert!(pos.y >= 32 && pos.y < 48);
+            assert!(pos.x >= 16 && pos.x < 32);
+            assert!(pos.z >= -16 && pos.z < 0);
+        }
+
+        world.set_random_tick_seed(7);
+        assert_eq!(world.get_random_tick_positions(), positions);
+    }
+}